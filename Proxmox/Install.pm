@@ -13,7 +13,7 @@ use Proxmox::Install::RunEnv;
 use Proxmox::Install::Config;
 use Proxmox::Install::StorageConfig;
 
-use Proxmox::Sys::Block qw(get_cached_disks wipe_disk partition_bootable_disk);
+use Proxmox::Sys::Block qw(get_cached_disks wipe_disk secure_wipe_disks partition_bootable_disk);
 use Proxmox::Sys::Command qw(run_command syscmd);
 use Proxmox::Sys::File qw(file_read_firstline file_read_all file_write_all);
 use Proxmox::Sys::ZFS;
@@ -733,6 +733,90 @@ my sub setup_proxmox_first_boot_service {
     }
 }
 
+# Describes existing volume groups and ZFS pools with the names the installation uses, as
+# human-readable report lines.
+#
+# Arguments:
+#
+# $vgname - Name of the volume group the installation creates
+# $vgs - Volume groups named $vgname, as returned by get_pv_list_from_vgname()
+# $pool_name - Name of the ZFS pool the installation creates
+# $pools - Exported ZFS pools, as returned by Proxmox::Sys::ZFS::get_exported_pools()
+sub existing_storage_report {
+    my ($vgname, $vgs, $pool_name, $pools) = @_;
+
+    $vgs //= {};
+    my $report = [];
+    for my $vg_uuid (sort keys $vgs->%*) {
+	push @$report, "LVM volume group '$vgname' on $vgs->{$vg_uuid}->{pvs}";
+    }
+    for my $pool ($pools->@*) {
+	push @$report, "ZFS pool '$pool->{name}' (id $pool->{id})" if $pool->{name} eq $pool_name;
+    }
+
+    return $report;
+}
+
+# Returns the names of the volume groups with a physical volume on any of the given disks.
+#
+# Arguments:
+#
+# $disks - Paths of the disks, e.g. ['/dev/sda']
+# $pvs - Physical volumes as [pv_name, vg_name] pairs, as reported by `pvs`
+sub volume_groups_on_disks {
+    my ($disks, $pvs) = @_;
+
+    my $vgs = {};
+    for my $pv ($pvs->@*) {
+	my ($pv_name, $vg_name) = $pv->@*;
+	next if !$vg_name;
+	$vgs->{$vg_name} = 1 if grep { $pv_name =~ m/^\Q$_\E(?:p?\d+)?$/ } $disks->@*;
+    }
+
+    return [ sort keys $vgs->%* ];
+}
+
+# Deactivates the volume groups on the target disks and clears all signatures and partition
+# tables from them, so that nothing of the previous data gets picked up again. ZFS pools are
+# never imported in the installation environment, so clearing their labels suffices.
+my sub clear_existing_data {
+    my ($target_disks) = @_;
+
+    my $pvs = [];
+    my $parser = sub {
+	my ($line) = @_;
+	my ($pv_name, $vg_name) = split(' ', $line);
+	push @$pvs, [$pv_name, $vg_name] if defined($pv_name);
+	return;
+    };
+    run_command("pvs --noheadings -o pv_name,vg_name", $parser, undef, 1);
+
+    for my $vg (volume_groups_on_disks($target_disks, $pvs)->@*) {
+	syscmd(['vgchange', '-an', $vg]) == 0
+	    or die "unable to deactivate volume group '$vg'\n";
+    }
+
+    Proxmox::Sys::Block::clear_disk_signatures($_) for $target_disks->@*;
+}
+
+# Collects everything on the target disks the installation would overwrite, as well as existing
+# storage with the names the installation uses, as human-readable report lines.
+my sub find_existing_data {
+    my ($target_disks, $vgname, $pool_name) = @_;
+
+    my $report = [ map { Proxmox::Sys::Block::get_existing_data($_)->@* } $target_disks->@* ];
+
+    my $storage = existing_storage_report(
+	$vgname,
+	get_pv_list_from_vgname($vgname),
+	$pool_name,
+	Proxmox::Sys::ZFS::get_exported_pools(),
+    );
+    push @$report, $storage->@*;
+
+    return $report;
+}
+
 sub extract_data {
     my $iso_env = Proxmox::Install::ISOEnv::get();
     my $run_env = Proxmox::Install::RunEnv::get();
@@ -788,8 +872,33 @@ sub extract_data {
 
 	update_progress(0, 0, $maxper, "cleanup root-disks");
 
+	my $target_disks = [];
+	if (!is_test_mode()) {
+	    $target_disks = $use_zfs || $use_btrfs
+		? [ map { $_->[1] } &$get_raid_devlist()->@* ]
+		: [ Proxmox::Install::Config::get_target_hd() ];
+	}
+
+	my $existing_data = Proxmox::Install::Config::get_existing_data() // '';
+	if (!is_test_mode() && $existing_data eq 'refuse') {
+	    my $report = find_existing_data($target_disks, $iso_env->{product}, $zfs_pool_name);
+	    die "Refusing to overwrite existing data, as 'existing_data' is set to 'refuse':\n"
+		. join('', map { "  $_\n" } $report->@*)
+		if scalar($report->@*);
+	} elsif (!is_test_mode() && $existing_data eq 'wipe') {
+	    clear_existing_data($target_disks);
+	}
+
 	syscmd("vgchange -an") if !is_test_mode(); # deactivate all detected VGs
 
+	if (my $method = Proxmox::Install::Config::get_wipe()) {
+	    secure_wipe_disks($target_disks, $method, sub {
+		my ($frac, $dev, $dev_frac) = @_;
+		my $text = sprintf("wipe %s (%s) - %d%%", $dev, $method, $dev_frac * 100);
+		update_progress($frac, 0, 0.02, $text);
+	    });
+	}
+
 	if (is_test_mode()) {
 
 	    my $test_images = Proxmox::Install::ISOEnv::get_test_images();
@@ -1069,30 +1178,109 @@ sub extract_data {
 	my $ntype = $ip_version == 4 ? 'inet' : 'inet6';
 
 	my $ethdev = Proxmox::Install::Config::get_mngmt_nic();
+	my $vlan_id = Proxmox::Install::Config::get_vlan_id();
+	my $bond = Proxmox::Install::Config::get_bond_opts();
 	my $cidr = Proxmox::Install::Config::get_cidr();
 	my $gateway = Proxmox::Install::Config::get_gateway();
+	my $cidr6 = Proxmox::Install::Config::get_cidr6();
+	my $gateway6 = Proxmox::Install::Config::get_gateway6();
+	my $mtu = Proxmox::Install::Config::get_mtu();
+	my $bridge_opts = Proxmox::Install::Config::get_bridge_opts() // {};
+
+	my $mtu_opt = defined($mtu) ? "\tmtu $mtu\n" : '';
+
+	# the interface the bridge or the host address is put on, either the NIC itself or a bond
+	my $uplink = $ethdev;
+	my $uplink_opts = $mtu_opt;
+	my $uplink_manual = "iface $ethdev $ntype manual\n$mtu_opt";
+	my $nics = { $ethdev => 1 };
+
+	if (defined($bond)) {
+	    $uplink = 'bond0';
+	    $uplink_opts = "\tbond-slaves " . join(' ', $bond->{slaves}->@*) . "\n" .
+		"\tbond-miimon $bond->{miimon}\n" .
+		"\tbond-mode $bond->{mode}\n";
+	    $uplink_opts .= "\tbond-xmit-hash-policy $bond->{xmit_hash_policy}\n"
+		if defined($bond->{xmit_hash_policy});
+	    $uplink_opts .= $mtu_opt;
+
+	    $uplink_manual = '';
+	    for my $slave ($bond->{slaves}->@*) {
+		$uplink_manual .= "iface $slave $ntype manual\n$mtu_opt\n";
+		$nics->{$slave} = 1;
+	    }
+	    $uplink_manual .= "auto $uplink\niface $uplink $ntype manual\n$uplink_opts";
+	}
+
+	# the interface the host address is put on
+	my $address_iface;
 
 	if ($iso_env->{cfg}->{bridged_network}) {
-	    $ifaces .= "iface $ethdev $ntype manual\n";
+	    my $bridge = $bridge_opts->{name} // 'vmbr0';
 
-	    $ifaces .=
-		"\nauto vmbr0\niface vmbr0 $ntype static\n" .
-		"\taddress $cidr\n" .
-		"\tgateway $gateway\n" .
-		"\tbridge-ports $ethdev\n" .
+	    $ifaces .= $uplink_manual;
+
+	    my $bridge_settings =
+		"\tbridge-ports $uplink\n" .
 		"\tbridge-stp off\n" .
-		"\tbridge-fd 0\n";
-	} else {
-	    $ifaces .= "auto $ethdev\n" .
-		"iface $ethdev $ntype static\n" .
+		"\tbridge-fd 0\n" .
+		$mtu_opt;
+
+	    # the bridge is always VLAN-aware if the management traffic is tagged, the bridge options
+	    # then always include the VLAN IDs to pass through
+	    if (defined($vlan_id) || $bridge_opts->{vlan_aware}) {
+		die "no VLAN IDs set for VLAN-aware bridge '$bridge'\n"
+		    if !defined($bridge_opts->{vids});
+		$bridge_settings .= "\tbridge-vlan-aware yes\n\tbridge-vids $bridge_opts->{vids}\n";
+	    }
+
+	    if (defined($vlan_id)) {
+		# the host itself is reachable on the tagged VLAN of the bridge
+		$address_iface = "$bridge.$vlan_id";
+		$ifaces .= "\nauto $bridge\niface $bridge $ntype manual\n$bridge_settings";
+
+		$ifaces .=
+		    "\nauto $address_iface\niface $address_iface $ntype static\n" .
+		    "\taddress $cidr\n" .
+		    "\tgateway $gateway\n";
+	    } else {
+		$address_iface = $bridge;
+		$ifaces .=
+		    "\nauto $bridge\niface $bridge $ntype static\n" .
+		    "\taddress $cidr\n" .
+		    "\tgateway $gateway\n" .
+		    $bridge_settings;
+	    }
+	} elsif (defined($vlan_id)) {
+	    $address_iface = "$uplink.$vlan_id";
+	    $ifaces .= $uplink_manual;
+
+	    $ifaces .= "\nauto $uplink.$vlan_id\n" .
+		"iface $uplink.$vlan_id $ntype static\n" .
 		"\taddress $cidr\n" .
 		"\tgateway $gateway\n";
+	} else {
+	    $address_iface = $uplink;
+	    $ifaces .= join('', map { "iface $_ $ntype manual\n$mtu_opt\n" } $bond->{slaves}->@*)
+		if defined($bond);
+
+	    $ifaces .= "auto $uplink\n" .
+		"iface $uplink $ntype static\n" .
+		"\taddress $cidr\n" .
+		"\tgateway $gateway\n" .
+		$uplink_opts;
+	}
+
+	if (defined($cidr6)) {
+	    $ifaces .= "\niface $address_iface inet6 static\n\taddress $cidr6\n";
+	    $ifaces .= "\tgateway $gateway6\n" if defined($gateway6);
 	}
 
 	my $ipconf = $run_env->{ipconf};
 	foreach my $iface (sort keys %{$ipconf->{ifaces}}) {
 	    my $name = $ipconf->{ifaces}->{$iface}->{name};
-	    next if $name eq $ethdev;
+	    next if $nics->{$name};
+	    next if $name =~ m/\.\d+$/; # VLAN interfaces of the installation environment
 
 	    $ifaces .= "\niface $name $ntype manual\n";
 	}
@@ -1103,8 +1291,13 @@ sub extract_data {
 
 	# configure dns
 
-	my $dnsserver = Proxmox::Install::Config::get_dns();
-	my $resolvconf = "search $domain\nnameserver $dnsserver\n";
+	my $search_domains = Proxmox::Install::Config::get_search_domains() // [];
+	my $search = scalar(@$search_domains) ? join(' ', @$search_domains) : $domain;
+	my $dnsservers = [
+	    Proxmox::Install::Config::get_dns(),
+	    (Proxmox::Install::Config::get_additional_dns() // [])->@*,
+	];
+	my $resolvconf = "search $search\n" . join('', map { "nameserver $_\n" } @$dnsservers);
 	file_write_all("$targetdir/etc/resolv.conf", $resolvconf);
 
 	# configure fstab
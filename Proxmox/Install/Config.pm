@@ -46,7 +46,8 @@ sub parse_kernel_cmdline {
     my @filtered = grep {
 	$_ !~ m/^(BOOT_IMAGE|root|ramdisk_size|splash|vga)=\S+$/ &&
 	$_ !~ m/^(ro|rw|quiet)$/ &&
-	$_ !~ m/^(prox(debug|tui|auto)|proxmox-\S+)$/
+	$_ !~ m/^(prox(debug|tui|auto)|proxmox-\S+)$/ &&
+	$_ !~ m/^(ip=\S*\/\S*|(gateway|nameserver)=\S+)$/
     } split(/\s+/, $cmdline);
 
     $cfg->{target_cmdline} = join(' ', @filtered);
@@ -89,6 +90,10 @@ my sub init_cfg {
 	target_hd => undef,
 	disk_selection => {},
 	existing_storage_auto_rename => 0,
+	# what to do about existing data, one of 'refuse', 'rename' or 'wipe'
+	existing_data => undef,
+	# how to erase the target disks before partitioning them, see secure_wipe_disks
+	wipe => undef,
 
 	# locale
 	country => $country,
@@ -105,11 +110,19 @@ my sub init_cfg {
 	# FIXME: fix call sites and remove below, it's just an ugly relict of GTK GUI and time
 	# pressure on creating the single source of truth for installation config
 	mngmt_nic_id => undef,
+	vlan_id => undef,
+	bond_opts => undef,
+	mtu => undef,
+	bridge_opts => undef,
 	hostname => undef,
 	domain => undef,
 	cidr => undef,
 	gateway => undef,
+	cidr6 => undef,
+	gateway6 => undef,
 	dns => undef,
+	additional_dns => [],
+	search_domains => [],
 	target_cmdline => undef,
 
 	# proxmox-first-boot setup
@@ -251,6 +264,18 @@ sub get_mngmt_nic { return get('mngmt_nic'); }
 sub set_mngmt_nic_id { set_key('mngmt_nic_id', $_[0]); }
 sub get_mngmt_nic_id { return get('mngmt_nic_id'); }
 
+sub set_vlan_id { set_key('vlan_id', $_[0]); }
+sub get_vlan_id { return get('vlan_id'); }
+
+sub set_bond_opts { set_key('bond_opts', $_[0]); }
+sub get_bond_opts { return get('bond_opts'); }
+
+sub set_mtu { set_key('mtu', $_[0]); }
+sub get_mtu { return get('mtu'); }
+
+sub set_bridge_opts { set_key('bridge_opts', $_[0]); }
+sub get_bridge_opts { return get('bridge_opts'); }
+
 sub set_hostname { set_key('hostname', $_[0]); }
 sub get_hostname { return get('hostname'); }
 
@@ -278,15 +303,33 @@ sub get_ip_version { # virtual config
 sub set_gateway { set_key('gateway', $_[0]); }
 sub get_gateway { return get('gateway'); }
 
+sub set_cidr6 { set_key('cidr6', $_[0]); }
+sub get_cidr6 { return get('cidr6'); }
+
+sub set_gateway6 { set_key('gateway6', $_[0]); }
+sub get_gateway6 { return get('gateway6'); }
+
 sub set_dns { set_key('dns', $_[0]); }
 sub get_dns { return get('dns'); }
 
+sub set_additional_dns { set_key('additional_dns', $_[0]); }
+sub get_additional_dns { return get('additional_dns'); }
+
+sub set_search_domains { set_key('search_domains', $_[0]); }
+sub get_search_domains { return get('search_domains'); }
+
 sub set_target_cmdline { set_key('target_cmdline', $_[0]); }
 sub get_target_cmdline { return get('target_cmdline'); }
 
 sub set_existing_storage_auto_rename { set_key('existing_storage_auto_rename', $_[0]); }
 sub get_existing_storage_auto_rename { return get('existing_storage_auto_rename'); }
 
+sub set_existing_data { set_key('existing_data', $_[0]); }
+sub get_existing_data { return get('existing_data'); }
+
+sub set_wipe { set_key('wipe', $_[0]); }
+sub get_wipe { return get('wipe'); }
+
 sub set_first_boot_opt {
     my ($k, $v) = @_;
     my $opts = get('first_boot');
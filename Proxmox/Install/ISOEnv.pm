@@ -18,24 +18,32 @@ my $product_cfg = {
 	port => '8006',
 	enable_btrfs => 1,
 	bridged_network => 1,
+	min_hdsize => 2, # GB
+	recommended_min_hdsize => 8, # GB
     },
     pmg => {
 	fullname => 'Proxmox Mail Gateway',
 	port => '8006',
 	enable_btrfs => 0,
 	bridged_network => 0,
+	min_hdsize => 2, # GB
+	recommended_min_hdsize => 8, # GB
     },
     pbs => {
 	fullname => 'Proxmox Backup Server',
 	port => '8007',
 	enable_btrfs => 0,
 	bridged_network => 0,
+	min_hdsize => 2, # GB
+	recommended_min_hdsize => 8, # GB
     },
     pdm => {
 	fullname => 'Proxmox Datacenter Manager',
 	port => '8443', # TODO: confirm
 	enable_btrfs => 0,
 	bridged_network => 0,
+	min_hdsize => 2, # GB
+	recommended_min_hdsize => 8, # GB
     },
 };
 
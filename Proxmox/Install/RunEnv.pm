@@ -64,6 +64,8 @@ sub query_cpu_hvm_support : prototype() {
 #         mac => <mac address>,
 #         index => <index>,
 #         name => <ifname>,
+#         carrier => <0|1>, # only if known
+#         speed => <link speed in Mbit/s>, # only if known
 #         addresses => [
 #             family => <inet|inet6>,
 #             address => <mac address>,
@@ -106,6 +108,14 @@ my sub query_netdevs : prototype() {
 	    state => uc($state),
 	};
 	$ifs->{$name}->{addresses} = \@valid_addrs if @valid_addrs;
+
+	# reading these fails for interfaces which are administratively down
+	my $carrier = file_read_firstline("/sys/class/net/$name/carrier");
+	$ifs->{$name}->{carrier} = int($carrier) if defined($carrier) && $carrier =~ m/^[01]$/;
+
+	# reported as -1 if unknown, e.g. without a link
+	my $speed = file_read_firstline("/sys/class/net/$name/speed");
+	$ifs->{$name}->{speed} = int($speed) if defined($speed) && $speed =~ m/^\d+$/ && $speed > 0;
     }
 
     return $ifs;
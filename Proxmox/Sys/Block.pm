@@ -3,18 +3,20 @@ package Proxmox::Sys::Block;
 use strict;
 use warnings;
 
+use Cwd ();
 use File::Basename;
 use IO::File;
-use List::Util qw(first);
+use List::Util qw(first min);
 
 use Proxmox::Install::ISOEnv;
-use Proxmox::Sys::Command qw(syscmd);
-use Proxmox::Sys::File qw(file_read_firstline);
+use Proxmox::Log;
+use Proxmox::Sys::Command qw(run_command syscmd);
+use Proxmox::Sys::File qw(file_read_firstline file_read_all);
 use Proxmox::Sys::Udev;
 use Proxmox::UI;
 
 use base qw(Exporter);
-our @EXPORT_OK = qw(get_cached_disks wipe_disk partition_bootable_disk);
+our @EXPORT_OK = qw(get_cached_disks wipe_disk secure_wipe_disks partition_bootable_disk);
 
 my sub is_same_file {
     my ($file_a, $file_b) = @_;
@@ -196,6 +198,175 @@ sub udevadm_disk_details {
     return $result;
 }
 
+my sub read_sysfs_attribute {
+    my ($path) = @_;
+
+    my $value = file_read_firstline($path) // return;
+    $value =~ s/^\s+|\s+$//g;
+    return length($value) ? $value : undef;
+}
+
+my sub disk_transport {
+    my ($sys_path) = @_;
+
+    my $name = basename($sys_path);
+    return 'nvme' if $name =~ m/^nvme/;
+    return 'mmc' if $name =~ m/^mmcblk/;
+    return 'virtio' if $name =~ m/^vd[a-z]/;
+
+    my $dev_path = Cwd::abs_path($sys_path) // return;
+    return 'usb' if $dev_path =~ m|/usb\d|;
+    return 'sata' if $dev_path =~ m|/ata\d|;
+    return 'sas' if $dev_path =~ m|/end_device-|;
+    return 'virtio' if $dev_path =~ m|/virtio\d|;
+    return 'scsi' if $name =~ m/^sd[a-z]/;
+    return;
+}
+
+my sub disk_is_multipath {
+    my ($sys_path) = @_;
+
+    opendir(my $dh, "$sys_path/holders") or return;
+    my @holders = grep { !/^\./ } readdir($dh);
+    closedir($dh);
+
+    for my $holder (@holders) {
+	my $uuid = read_sysfs_attribute("/sys/block/$holder/dm/uuid") // next;
+	return 1 if $uuid =~ m/^mpath-/;
+    }
+    return 0;
+}
+
+# Normalized attributes of a disk, read from sysfs. Unlike the udev properties, these do not
+# depend on the controller the disk is attached to. Boolean attributes are stored as 0 or 1.
+sub get_disk_attributes {
+    my ($sys_path) = @_;
+
+    my $attributes = {};
+
+    my $flags = { rotational => 'queue/rotational', removable => 'removable' };
+    for my $attr (sort keys $flags->%*) {
+	my $value = read_sysfs_attribute("$sys_path/$flags->{$attr}");
+	$attributes->{$attr} = int($value) ? 1 : 0 if defined($value) && $value =~ m/^\d+$/;
+    }
+
+    my $physical_bsize = read_sysfs_attribute("$sys_path/queue/physical_block_size");
+    $attributes->{'physical-block-size'} = int($physical_bsize)
+	if defined($physical_bsize) && $physical_bsize =~ m/^\d+$/;
+
+    my $transport = disk_transport($sys_path);
+    $attributes->{transport} = $transport if defined($transport);
+
+    my $wwn = read_sysfs_attribute("$sys_path/wwid") // read_sysfs_attribute("$sys_path/device/wwid");
+    $attributes->{wwn} = $wwn if defined($wwn);
+
+    my $is_multipath = disk_is_multipath($sys_path);
+    $attributes->{'is-multipath'} = $is_multipath if defined($is_multipath);
+
+    return $attributes;
+}
+
+# returns the name of the disk in /sys/block a block device belongs to, e.g. 'sdb' for '/dev/sdb1'
+my sub block_device_disk {
+    my ($dev) = @_;
+
+    my $path = Cwd::abs_path($dev) // return;
+    return if !-b $path;
+
+    my $name = basename($path);
+    my $sys_path = Cwd::abs_path("/sys/class/block/$name") // return;
+    return -e "$sys_path/partition" ? basename(dirname($sys_path)) : $name;
+}
+
+# disks holding the mounted installation ISO and the partition the answer file is read from
+my sub installation_media_disks {
+    my $media = { boot => {}, answer => {} };
+
+    for my $line (split("\n", file_read_all('/proc/mounts'))) {
+	my ($dev, $mountpoint) = split(/\s+/, $line);
+	next if !defined($mountpoint) || $mountpoint ne '/cdrom';
+	my $disk = block_device_disk($dev);
+	$media->{boot}->{$disk} = 1 if defined($disk);
+    }
+
+    my $label = 'proxmox-ais';
+    if (-f '/cdrom/auto-installer-mode.toml') {
+	my $mode = file_read_all('/cdrom/auto-installer-mode.toml');
+	$label = $1 if $mode =~ m/^\s*partition_label\s*=\s*["']([^"']+)["']\s*$/m;
+    }
+    for my $candidate ($label, uc($label), lc($label)) {
+	my $disk = block_device_disk("/dev/disk/by-label/$candidate");
+	$media->{answer}->{$disk} = 1 if defined($disk);
+    }
+
+    return $media;
+}
+
+sub disk_attributes_details {
+    my $disks = get_cached_disks();
+    my $media = installation_media_disks();
+
+    my $result = {};
+    for my $disk_info ($disks->@*) {
+	my ($dev_index, $sys_path) = ($disk_info->[0], $disk_info->[5]);
+	my $attributes = get_disk_attributes($sys_path);
+
+	my $name = basename($sys_path);
+	$attributes->{'is-boot-medium'} = $media->{boot}->{$name} ? 1 : 0;
+	$attributes->{'has-answer-partition'} = $media->{answer}->{$name} ? 1 : 0;
+
+	$result->{$dev_index} = $attributes;
+    }
+    return $result;
+}
+
+# Parses the output of `lsblk --pairs --paths --output NAME,PTTYPE,FSTYPE,LABEL` for a disk into
+# human-readable report lines of the partition table and signatures found on it.
+#
+# Arguments:
+#
+# $fh - Filehandle to read the output from
+# $disk - Path of the disk, e.g. '/dev/sda'
+sub lsblk_existing_data_parse_output {
+    my ($fh, $disk) = @_;
+
+    my $fstype_desc = {
+	LVM2_member => 'LVM physical volume',
+	zfs_member => 'ZFS pool member',
+	btrfs => 'Btrfs filesystem',
+	linux_raid_member => 'software RAID member',
+    };
+
+    my $report = [];
+    while (defined(my $line = <$fh>)) {
+	chomp($line);
+	my $dev = { map { m/^(\w+)="(.*)"$/ ? ($1 => $2) : () } split(/\s+(?=\w+=")/, $line) };
+	next if !$dev->{NAME};
+
+	# partitions report the partition table of their disk too
+	push @$report, "$dev->{NAME}: $dev->{PTTYPE} partition table"
+	    if $dev->{PTTYPE} && $dev->{NAME} eq $disk;
+	if (my $fstype = $dev->{FSTYPE}) {
+	    my $desc = $fstype_desc->{$fstype} // "'$fstype' signature";
+	    $desc .= " labelled '$dev->{LABEL}'" if $dev->{LABEL};
+	    push @$report, "$dev->{NAME}: $desc";
+	}
+    }
+
+    return $report;
+}
+
+# Returns human-readable report lines of the partition table and signatures found on a disk.
+sub get_existing_data {
+    my ($disk) = @_;
+
+    my $output = run_command(
+	['lsblk', '--noheadings', '--pairs', '--paths', '--output', 'NAME,PTTYPE,FSTYPE,LABEL', $disk],
+    );
+    open(my $fh, '<', \$output) or die "failed to read lsblk output for '$disk'\n";
+    return lsblk_existing_data_parse_output($fh, $disk);
+}
+
 sub wipe_disk {
     my ($disk) = @_;
 
@@ -214,6 +385,229 @@ sub wipe_disk {
     warn "$@" if $@;
 };
 
+# Removes all signatures and partition tables from a disk and its partitions, including LVM, ZFS
+# and RAID labels as well as the backup GPT at the end of the disk.
+sub clear_disk_signatures {
+    my ($disk) = @_;
+
+    wipe_disk($disk);
+
+    # these fail if there is no such label on the disk, which is fine
+    syscmd(['pvremove', '-ff', '-y', $disk]);
+    syscmd(['zpool', 'labelclear', '-f', $disk]);
+
+    syscmd(['sgdisk', '--zap-all', $disk]) == 0
+	or die "unable to clear partition table of '$disk'\n";
+    syscmd(['wipefs', '--all', $disk]) == 0
+	or die "unable to clear signatures of '$disk'\n";
+}
+
+# zeroes the range in chunks, as dd only reports its progress on stderr
+my sub zero_disk_range {
+    my ($disk, $offset_mb, $count_mb, $progress) = @_;
+
+    my $chunk_mb = 128;
+    for (my $done_mb = 0; $done_mb < $count_mb; $done_mb += $chunk_mb) {
+	my $chunk_count_mb = min($chunk_mb, $count_mb - $done_mb);
+	run_command([
+	    'dd',
+	    'if=/dev/zero',
+	    "of=$disk",
+	    'bs=1M',
+	    'oflag=direct',
+	    'conv=fsync',
+	    'seek=' . ($offset_mb + $done_mb),
+	    "count=$chunk_count_mb",
+	    'status=none',
+	]);
+	$progress->(($done_mb + $chunk_count_mb) / $count_mb);
+    }
+}
+
+my sub nvme_sanitize_status {
+    my ($ctrl) = @_;
+
+    my ($sprog, $sstat);
+    run_command("nvme sanitize-log $ctrl", sub {
+	my ($line) = @_;
+	$sprog = $1 if $line =~ m/\(SPROG\)\s*:\s*(\d+)/;
+	if ($line =~ m/\(SSTAT\)\s*:\s*(0x[0-9a-f]+|\d+)/i) {
+	    $sstat = $1 =~ m/^0x/i ? hex($1) : $1;
+	}
+	return;
+    });
+    die "unable to parse sanitize log of '$ctrl'\n" if !defined($sstat);
+
+    return ($sstat & 0x7, ($sprog // 0) / 65535);
+}
+
+# Returns the NVMe controller of a namespace along with all of its namespaces, e.g.
+# ('/dev/nvme0', ['/dev/nvme0n1', '/dev/nvme0n2']) for '/dev/nvme0n1'.
+my sub nvme_controller_namespaces {
+    my ($disk) = @_;
+
+    my ($ctrl) = $disk =~ m|^(/dev/nvme\d+)n\d+$|
+	or die "'$disk' is not a NVMe namespace\n";
+    my $namespaces = [ sort grep { m|^\Q$ctrl\En\d+$| } glob("${ctrl}n*") ];
+
+    return ($ctrl, $namespaces);
+}
+
+my sub nvme_sanitize {
+    my ($ctrl, $progress) = @_;
+
+    syscmd(['nvme', 'sanitize', $ctrl, '--sanact=2']) == 0
+	or die "unable to start sanitize of NVMe controller '$ctrl'\n";
+
+    # a block erase usually takes seconds to minutes, but leave slow controllers plenty of time
+    my $timeout = 4 * 60 * 60;
+    my $started = time();
+    while (1) {
+	sleep(1);
+	my ($status, $frac) = nvme_sanitize_status($ctrl);
+
+	# 1 and 4 mean completed, 2 in progress, 3 failed and 0 that it was never sanitized
+	last if $status == 1 || $status == 4;
+	die "sanitize of NVMe controller '$ctrl' failed\n" if $status == 3;
+	die "sanitize of NVMe controller '$ctrl' did not start\n" if $status == 0;
+	die "sanitize of NVMe controller '$ctrl' reported unknown status $status\n"
+	    if $status != 2;
+	die "sanitize of NVMe controller '$ctrl' did not finish within $timeout seconds\n"
+	    if time() - $started > $timeout;
+
+	$progress->($frac);
+    }
+}
+
+# Parses the output of `hdparm -I` for the state of the ATA security feature set. Returns undef
+# if the disk does not report the feature set at all.
+sub hdparm_security_parse_output {
+    my ($output) = @_;
+
+    my ($section) = $output =~ m/^Security:[^\n]*\n((?:[ \t]+[^\n]*\n?)*)/m;
+    return if !defined($section);
+
+    # negated states are reported as e.g. "not	frozen" on the same line
+    my $state = {};
+    for my $flag (qw(supported enabled locked frozen)) {
+	$state->{$flag} = $section =~ m/^\s+\Q$flag\E\s*$/m ? 1 : 0;
+    }
+    return $state;
+}
+
+# Returns a random password for the ATA security erase, as 16 hexadecimal digits.
+sub ata_security_password {
+    open(my $fh, '<', '/dev/urandom') or die "unable to open /dev/urandom - $!\n";
+    my $count = sysread($fh, my $bytes, 8);
+    close($fh);
+    die "unable to read from /dev/urandom\n" if !defined($count) || $count != 8;
+
+    return unpack('H*', $bytes);
+}
+
+my sub ata_secure_erase {
+    my ($disk) = @_;
+
+    my $security = hdparm_security_parse_output(run_command(['hdparm', '-I', $disk]));
+    die "'$disk' does not support the ATA security feature set\n"
+	if !$security || !$security->{supported};
+    die "'$disk' already has an ATA security password set\n"
+	if $security->{enabled} || $security->{locked};
+    die "'$disk' is security frozen, suspend and resume the system or re-plug the disk\n"
+	if $security->{frozen};
+
+    # The erase requires setting a user password, which the disk clears again once the erase
+    # completed. It is logged, so that a disk left locked by a failed erase can still be unlocked.
+    my $pass = ata_security_password();
+    log_info("using ATA user password '$pass' to secure erase '$disk'\n");
+
+    eval {
+	syscmd(['hdparm', '--user-master', 'u', '--security-set-pass', $pass, $disk]) == 0
+	    or die "unable to set ATA security password on '$disk'\n";
+	syscmd(['hdparm', '--user-master', 'u', '--security-erase', $pass, $disk]) == 0
+	    or die "unable to ATA secure erase '$disk'\n";
+    };
+    if (my $err = $@) {
+	syscmd(['hdparm', '--user-master', 'u', '--security-disable', $pass, $disk]) == 0
+	    or warn "unable to disable ATA security on '$disk', it might still be locked with"
+		. " the user password '$pass'\n";
+	die $err;
+    }
+}
+
+# Erases all data on a whole disk, or a NVMe controller for 'nvme-sanitize', using the given
+# method. The progress callback is called with the completed fraction, from 0 to 1.
+my sub secure_wipe_disk {
+    my ($disk, $method, $progress) = @_;
+
+    $progress->(0);
+
+    if ($method eq 'discard') {
+	syscmd(['blkdiscard', '--force', $disk]) == 0
+	    or die "unable to discard blocks of '$disk'\n";
+    } elsif ($method eq 'zero') {
+	# clear the first and last GiB, which holds the labels of MD RAID, LVM and ZFS
+	my $size_mb = int(hd_size($disk) / 1024);
+	my $range_mb = 1024;
+
+	if ($size_mb <= 2 * $range_mb) {
+	    zero_disk_range($disk, 0, $size_mb, $progress);
+	} else {
+	    zero_disk_range($disk, 0, $range_mb, sub { $progress->($_[0] / 2) });
+	    zero_disk_range($disk, $size_mb - $range_mb, $range_mb, sub {
+		$progress->(0.5 + $_[0] / 2);
+	    });
+	}
+    } elsif ($method eq 'nvme-format') {
+	die "'$disk' is not a NVMe namespace\n" if $disk !~ m|^/dev/nvme\d+n\d+$|;
+	syscmd(['nvme', 'format', $disk, '--ses=1', '--force']) == 0
+	    or die "unable to format NVMe namespace '$disk'\n";
+    } elsif ($method eq 'nvme-sanitize') {
+	nvme_sanitize($disk, $progress);
+    } elsif ($method eq 'ata-secure-erase') {
+	ata_secure_erase($disk);
+    } else {
+	die "unknown disk wipe method '$method'\n";
+    }
+
+    $progress->(1);
+}
+
+# Erases all data on the given whole disks using the given method, before they get partitioned.
+# The progress callback is called with the overall completed fraction, from 0 to 1, the device
+# currently being wiped and its completed fraction.
+sub secure_wipe_disks {
+    my ($disks, $method, $progress) = @_;
+
+    # See also proxmox-installer-common/src/disk_checks.rs:check_disk_wipe_method()
+
+    my $devices = $disks;
+    if ($method eq 'nvme-sanitize') {
+	# sanitizing erases all namespaces of a controller, so only do it once per controller and
+	# only if all of its namespaces are installation targets anyway
+	my $is_target = { map { $_ => 1 } $disks->@* };
+	my $controllers = {};
+	for my $disk ($disks->@*) {
+	    my ($ctrl, $namespaces) = nvme_controller_namespaces($disk);
+	    my @others = grep { !$is_target->{$_} } $namespaces->@*;
+	    die "refusing to sanitize NVMe controller '$ctrl', as it would also erase "
+		. join(', ', @others) . ", which is not an installation target\n"
+		if scalar(@others);
+	    $controllers->{$ctrl} = 1;
+	}
+	$devices = [ sort keys $controllers->%* ];
+    }
+
+    my $count = scalar($devices->@*);
+    for my $i (0 .. $count - 1) {
+	my $dev = $devices->[$i];
+	secure_wipe_disk($dev, $method, sub {
+	    my ($frac) = @_;
+	    $progress->(($i + $frac) / $count, $dev, $frac);
+	});
+    }
+}
+
 sub partition_bootable_disk {
     my ($target_dev, $maxhdsizegb, $ptype) = @_;
 
@@ -239,7 +633,8 @@ sub partition_bootable_disk {
 
     my $hdgb = int($hdsize/(1024*1024));
 
-    my ($hard_limit, $soft_limit) = (2, 8);
+    my $cfg = Proxmox::Install::ISOEnv::get('cfg');
+    my ($hard_limit, $soft_limit) = ($cfg->{min_hdsize}, $cfg->{recommended_min_hdsize});
 
     die "root disk '$target_dev' too small (${hdgb} GB < $hard_limit GB)\n" if $hdgb < $hard_limit;
     if ($hdgb < $soft_limit) {
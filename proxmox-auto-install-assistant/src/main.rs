@@ -1,25 +1,35 @@
 use anyhow::{bail, format_err, Result};
 use clap::{Args, Parser, Subcommand, ValueEnum};
 use glob::Pattern;
-use serde::Serialize;
+use schemars::schema_for;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::{
     collections::BTreeMap,
     fmt, fs,
     io::{self, Read},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{self, Command, Stdio},
 };
 
 use proxmox_auto_installer::{
-    answer::{Answer, FilterMatch},
+    answer::{Answer, AnswerFormat, FilterMatch},
+    filter::{add_disk_attributes, disk_properties, DISK_BLOCK_SIZE_PROPERTY, DISK_SIZE_PROPERTY},
+    include::{resolve_includes, AnswerSource, INCLUDE_KEY},
+    multihost::{self, HostIdentity},
     sysinfo::SysInfo,
+    template,
+    udevinfo::{DiskAttributes, UdevInfo},
     utils::{
-        get_matched_udev_indexes, get_nic_list, get_single_udev_index,
-        verify_email_and_root_password_settings, verify_first_boot_settings,
-        verify_locale_settings, AutoInstSettings, FetchAnswerFrom, HttpOptions,
+        self, get_matched_udev_indexes, get_nic_list, get_single_udev_index, AutoInstSettings,
+        FetchAnswerFrom, HttpOptions,
     },
+    validation::{self, Diagnostic, Lint, LintContext, LintId},
+};
+use proxmox_installer_common::{
+    setup::{RuntimeInfo, SetupInfo},
+    FIRST_BOOT_EXEC_MAX_SIZE, FIRST_BOOT_EXEC_NAME, RUNTIME_DIR,
 };
-use proxmox_installer_common::{FIRST_BOOT_EXEC_MAX_SIZE, FIRST_BOOT_EXEC_NAME};
 
 static PROXMOX_ISO_FLAG: &str = "/auto-installer-capable";
 
@@ -27,6 +37,13 @@ static PROXMOX_ISO_FLAG: &str = "/auto-installer-capable";
 /// [LocaleInfo](`proxmox_installer_common::setup::LocaleInfo`) struct.
 const LOCALE_INFO: &str = include_str!("../../locale-info.json");
 
+/// Published JSON schemas of the payloads sent by `proxmox-fetch-answer` and `proxmox-post-hook`.
+/// They are kept in sync with the respective types by the tests of those crates.
+const HTTP_FETCH_PAYLOAD_SCHEMA: &str =
+    include_str!("../../proxmox-fetch-answer/schema/http-fetch-payload.json");
+const POST_HOOK_INFO_SCHEMA: &str =
+    include_str!("../../proxmox-post-hook/schema/post-hook-info.json");
+
 /// This tool can be used to prepare a Proxmox installation ISO for automated installations.
 /// Additional uses are to validate the format of an answer file or to test match filters and
 /// print information on the properties to match against for the current hardware.
@@ -35,15 +52,22 @@ const LOCALE_INFO: &str = include_str!("../../locale-info.json");
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[command(flatten)]
+    global: GlobalOpts,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     PrepareIso(CommandPrepareISO),
     ValidateAnswer(CommandValidateAnswer),
+    SimulateAnswer(CommandSimulateAnswer),
+    ShowAnswer(CommandShowAnswer),
     DeviceMatch(CommandDeviceMatch),
     DeviceInfo(CommandDeviceInfo),
     SystemInfo(CommandSystemInfo),
+    CaptureProfile(CommandCaptureProfile),
+    JsonSchema(CommandJsonSchema),
 }
 
 /// Show device information that can be used for filters
@@ -52,6 +76,11 @@ struct CommandDeviceInfo {
     /// For which device type information should be shown
     #[arg(name="type", short, long, value_enum, default_value_t=AllDeviceTypes::All)]
     device: AllDeviceTypes,
+
+    /// Read the devices from a hardware profile or a recorded 'run-env-udev.json' file instead of
+    /// the current system
+    #[arg(long)]
+    profile: Option<PathBuf>,
 }
 
 /// Test which devices the given filter matches against
@@ -61,38 +90,169 @@ struct CommandDeviceInfo {
 /// - `*`               Match any number of characters
 /// - `[a]`, `[0-9]`  Specific character or range of characters
 /// - `[!a]`          Negate a specific character of range
+/// - `~REGEX`          Search for a regular expression, e.g. `~^nvme[0-9]+n1$`
+/// - `>N`, `>=N`       Greater (or equal) than a number, e.g. `>400G`
+/// - `<N`, `<=N`       Less (or equal) than a number, e.g. `<=4096`
+/// - `!EXPR`           Negate any of the above, e.g. `!*SATADOM*`
+///
+/// Numbers can have a decimal (`K`, `M`, `G`, `T`, `P`) or binary (`Ki`, `Mi`, `Gi`, `Ti`,
+/// `Pi`) unit suffix. Besides their UDEV properties, disks can be matched by their `size` and
+/// logical `block_size` in bytes, as well as by the following attributes recorded by the
+/// installation environment: `rotational`, `removable` and `is_multipath` (`1` or `0`),
+/// `transport` (`nvme`, `sata`, `sas`, `scsi`, `usb`, `virtio` or `mmc`), `physical_block_size`
+/// and `wwn`. Disk attributes are only available within the installation environment or from a
+/// hardware profile.
 ///
 /// To avoid globbing characters being interpreted by the shell, use single quotes.
 /// Multiple filters can be defined.
 ///
+/// By default, the filters are matched against the devices of the current system. With the
+/// '--profile' option, they are matched against the devices of a hardware profile, as recorded
+/// by the 'capture-profile' command, or a 'run-env-udev.json' file from an installation
+/// environment instead.
+///
 /// Examples:
 /// Match disks against the serial number and device name, both must match:
 ///
 /// ```sh
 /// proxmox-auto-install-assistant match --filter-match all disk 'ID_SERIAL_SHORT=*2222*' 'DEVNAME=*nvme*'
 /// ```
+///
+/// Match all NVMe disks larger than 400 GB, which are not a SATADOM:
+///
+/// ```sh
+/// proxmox-auto-install-assistant match --filter-match all disk 'DEVNAME=/dev/nvme*' 'size=>400G' 'ID_MODEL=!*SATADOM*'
+/// ```
+///
+/// Match all SATA SSDs:
+///
+/// ```sh
+/// proxmox-auto-install-assistant match --filter-match all disk 'transport=sata' 'rotational=0'
+/// ```
 #[derive(Args, Debug)]
 #[command(verbatim_doc_comment)]
 struct CommandDeviceMatch {
     /// Device type to match the filter against
     r#type: Devicetype,
 
-    /// Filter in the format KEY=VALUE where the key is the UDEV key and VALUE the filter string.
-    /// Multiple filters are possible, separated by a space.
+    /// Filter in the format KEY=VALUE where the key is the UDEV key, or 'size', 'block_size' or
+    /// one of the disk attributes for disks, and VALUE the filter expression. Multiple filters are
+    /// possible, separated by a space.
     filter: Vec<String>,
 
     /// Defines if any filter or all filters must match.
     #[arg(long, value_enum, default_value_t=FilterMatch::Any)]
     filter_match: FilterMatch,
+
+    /// Read the devices from a hardware profile or a recorded 'run-env-udev.json' file instead of
+    /// the current system
+    #[arg(long)]
+    profile: Option<PathBuf>,
 }
 
 /// Validate if an answer file is formatted correctly.
+///
+/// Answer files can be written in TOML, JSON or YAML. Unless given with '--answer-format', the
+/// format is derived from the file extension or, if that is not conclusive, from the content.
+///
+/// Answer files including other answer files are validated with all of them merged, as shown by
+/// 'show-answer'.
+///
+/// Answer file templates, marked with 'template = true' and containing expressions like
+/// '{{ dmi.system.serial | lower }}' in their values, are rendered with the system information of
+/// the hardware profile given with '--profile'. Without a profile, only the syntax of the
+/// template expressions is checked.
+///
+/// With '--lint', the answer file is additionally checked for settings which are valid, but most
+/// likely not intended, like a root password stored in plain text. Each warning has an ID, which
+/// can be passed to '--allow' to suppress it.
 #[derive(Args, Debug)]
 struct CommandValidateAnswer {
     /// Path to the answer file
     path: PathBuf,
     #[arg(short, long, default_value_t = false)]
     debug: bool,
+
+    /// Format of the answer file
+    #[arg(long, value_enum)]
+    answer_format: Option<AnswerFormat>,
+
+    /// Also warn about suspicious, but valid settings
+    #[arg(long, default_value_t = false)]
+    lint: bool,
+
+    /// Suppress the warning with the given ID, can be specified multiple times
+    #[arg(long, value_enum)]
+    allow: Vec<LintId>,
+
+    /// Hardware profile of the target system, used to render templates, to select the host of
+    /// multi-host answer files and for lints depending on the system
+    #[arg(long)]
+    profile: Option<PathBuf>,
+
+    /// How the answer file will be fetched, enables lints depending on the fetch mode
+    #[arg(long, value_enum)]
+    fetch_from: Option<FetchAnswerFrom>,
+}
+
+/// Simulate an automated installation with an answer file against a recorded hardware profile.
+///
+/// Runs the full answer file processing of the auto-installer, including disk and network
+/// interface selection through filters, and prints the resulting configuration as it would be
+/// passed to the low-level installer. Nothing is installed.
+///
+/// The hardware profile is a directory containing the 'run-env-info.json' and 'run-env-udev.json'
+/// files, as created by the installer in '/run/proxmox-installer' on the target machine.
+/// Optionally, an 'iso-info.json' can be placed there too, otherwise a Proxmox VE ISO is assumed.
+///
+/// Profiles recorded with the 'capture-profile' command can be used directly, either as
+/// directory or as archive.
+#[derive(Args, Debug)]
+struct CommandSimulateAnswer {
+    /// Path to the answer file
+    path: PathBuf,
+
+    /// Path to the directory or archive containing the recorded hardware profile
+    #[arg(long)]
+    profile: PathBuf,
+}
+
+/// Print the effective answer file, with all included answer files merged.
+///
+/// Included answer files are resolved relative to the given answer file. With '--profile',
+/// templates are additionally rendered and the host section of multi-host answer files is
+/// selected for the recorded system, resulting in the answer file exactly as the auto-installer
+/// would use it.
+#[derive(Args, Debug)]
+struct CommandShowAnswer {
+    /// Path to the answer file
+    path: PathBuf,
+
+    /// Format of the answer file
+    #[arg(long, value_enum)]
+    answer_format: Option<AnswerFormat>,
+
+    /// Hardware profile of the target system, used to render templates and to select the host of
+    /// multi-host answer files
+    #[arg(long)]
+    profile: Option<PathBuf>,
+}
+
+/// Record the hardware information of the current system into a hardware profile.
+///
+/// The profile contains everything the automated installer bases its decisions on: the disks and
+/// network interfaces including their udev properties, the DMI information, the total memory and
+/// the boot type. It can be used to validate answer files offline, e.g. with the
+/// 'simulate-answer' command.
+///
+/// If the output path ends with '.tar', '.tar.gz' or '.tgz', a single archive is created,
+/// otherwise the files are placed into the given directory.
+///
+/// Must be run as root to be able to read all information.
+#[derive(Args, Debug)]
+struct CommandCaptureProfile {
+    /// Path to the directory or archive to store the hardware profile in
+    output: PathBuf,
 }
 
 /// Prepare an ISO for automated installation.
@@ -173,10 +333,33 @@ struct CommandPrepareISO {
 #[derive(Args, Debug)]
 struct CommandSystemInfo {}
 
+/// Print the JSON schema of the answer file or of a payload sent by the installer.
+///
+/// The answer file schema describes the TOML answer file in its JSON representation and can be
+/// used to validate dynamically generated answer files.
+///
+/// The 'http-fetch-payload' schema describes the system information sent when fetching the
+/// answer file via HTTP, 'post-hook-info' the data sent by the post-installation webhook. Both
+/// payloads carry their schema version in the '$schema.version' property.
+#[derive(Args, Debug)]
+struct CommandJsonSchema {
+    /// Document to print the JSON schema for
+    #[arg(value_enum)]
+    document: SchemaDocument,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+enum SchemaDocument {
+    Answer,
+    HttpFetchPayload,
+    PostHookInfo,
+}
+
 #[derive(Args, Debug)]
 struct GlobalOpts {
-    /// Output format
-    #[arg(long, short, value_enum)]
+    /// Output format of the 'device-info', 'device-match', 'system-info', 'validate-answer' and
+    /// 'show-answer' commands
+    #[arg(long, short, global = true, value_enum, default_value_t = OutputFormat::Pretty)]
     format: OutputFormat,
 }
 
@@ -193,26 +376,282 @@ enum Devicetype {
     Disk,
 }
 
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Clone, Debug, ValueEnum, PartialEq)]
 enum OutputFormat {
+    /// Human-readable tables
     Pretty,
+    /// Machine-readable JSON
     Json,
+    /// TOML, e.g. snippets for use in answer files
+    Toml,
+}
+
+impl OutputFormat {
+    /// Returns the serialization format to use, or `None` if the command should print
+    /// human-readable output itself.
+    fn data_format(&self) -> Option<DataFormat> {
+        match self {
+            OutputFormat::Pretty => None,
+            OutputFormat::Json => Some(DataFormat::Json),
+            OutputFormat::Toml => Some(DataFormat::Toml),
+        }
+    }
+}
+
+/// Machine-readable output formats, into which data can be serialized directly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DataFormat {
+    Json,
+    Toml,
+}
+
+/// Hardware and environment information as recorded from a system, used for simulating an
+/// installation without access to the machine itself.
+struct HardwareProfile {
+    setup_info: SetupInfo,
+    runtime_info: RuntimeInfo,
+    udev_info: UdevInfo,
+    system_info: Option<serde_json::Value>,
+}
+
+impl HardwareProfile {
+    const ISO_INFO_FILE: &'static str = "iso-info.json";
+    const RUN_ENV_INFO_FILE: &'static str = "run-env-info.json";
+    const RUN_ENV_UDEV_FILE: &'static str = "run-env-udev.json";
+    const SYSTEM_INFO_FILE: &'static str = "system-info.json";
+
+    fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        let setup_info = match Self::read_file(path, Self::ISO_INFO_FILE)? {
+            Some(content) => serde_json::from_str(&content)
+                .map_err(|err| format_err!("Failed to parse iso-info.json from {path:?}: {err}"))?,
+            None => SetupInfo::mocked(),
+        };
+
+        let mut runtime_info: RuntimeInfo =
+            Self::read_required_json(path, Self::RUN_ENV_INFO_FILE)?;
+        runtime_info.disks.sort();
+
+        let udev_info = Self::read_required_json(path, Self::RUN_ENV_UDEV_FILE)?;
+
+        let system_info = match Self::read_file(path, Self::SYSTEM_INFO_FILE)? {
+            Some(content) => Some(serde_json::from_str(&content).map_err(|err| {
+                format_err!("Failed to parse system-info.json from {path:?}: {err}")
+            })?),
+            None => None,
+        };
+
+        Ok(Self {
+            setup_info,
+            runtime_info,
+            udev_info,
+            system_info,
+        })
+    }
+
+    /// Returns the system information of the recorded system, as used for rendering answer file
+    /// templates and selecting the host section of multi-host answer files.
+    fn system_info(&self) -> Result<&serde_json::Value> {
+        self.system_info.as_ref().ok_or_else(|| {
+            format_err!(
+                "Hardware profile does not contain {}, which is required for templated and \
+                multi-host answer files",
+                Self::SYSTEM_INFO_FILE
+            )
+        })
+    }
+
+    /// Returns the identity of the recorded system, as used for selecting the host section of
+    /// multi-host answer files.
+    fn host_identity(&self) -> Result<HostIdentity> {
+        HostIdentity::from_sysinfo(self.system_info()?)
+    }
+
+    /// Loads only the udev information, either from a profile or directly from a recorded
+    /// 'run-env-udev.json' file.
+    fn load_udev_info(path: impl AsRef<Path>) -> Result<UdevInfo> {
+        let path = path.as_ref();
+
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "json") {
+            let content = fs::read_to_string(path)
+                .map_err(|err| format_err!("Failed to read {path:?}: {err}"))?;
+            let mut udev_info: UdevInfo = serde_json::from_str(&content)
+                .map_err(|err| format_err!("Failed to parse {path:?}: {err}"))?;
+            udev_info.disks = disk_properties(&udev_info, &[]);
+            return Ok(udev_info);
+        }
+
+        let mut udev_info: UdevInfo = Self::read_required_json(path, Self::RUN_ENV_UDEV_FILE)?;
+
+        // The size and block size of disks are only recorded in the runtime environment
+        if let Some(content) = Self::read_file(path, Self::RUN_ENV_INFO_FILE)? {
+            let runtime_info: RuntimeInfo = serde_json::from_str(&content).map_err(|err| {
+                format_err!(
+                    "Failed to parse {} from {path:?}: {err}",
+                    Self::RUN_ENV_INFO_FILE
+                )
+            })?;
+            udev_info.disks = disk_properties(&udev_info, &runtime_info.disks);
+        } else {
+            udev_info.disks = disk_properties(&udev_info, &[]);
+        }
+
+        Ok(udev_info)
+    }
+
+    fn read_required_json<T: for<'de> Deserialize<'de>>(path: &Path, name: &str) -> Result<T> {
+        let content = Self::read_file(path, name)?
+            .ok_or_else(|| format_err!("Failed to read {name} from {path:?}: file not found"))?;
+
+        serde_json::from_str(&content)
+            .map_err(|err| format_err!("Failed to parse {name} from {path:?}: {err}"))
+    }
+
+    /// Reads a single file of the profile, either from a directory or a tar archive. Returns
+    /// `None` if the profile does not contain the file.
+    fn read_file(path: &Path, name: &str) -> Result<Option<String>> {
+        if path.is_dir() {
+            return match fs::read_to_string(path.join(name)) {
+                Ok(content) => Ok(Some(content)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(err) => bail!("Failed to read {name} from {path:?}: {err}"),
+            };
+        }
+
+        if !path.exists() {
+            bail!("Hardware profile {path:?} does not exist");
+        }
+
+        let members = run_tar(Command::new("tar").arg("-tf").arg(path))?;
+        if !members.lines().any(|member| member == name) {
+            return Ok(None);
+        }
+
+        run_tar(Command::new("tar").arg("-xOf").arg(path).arg(name)).map(Some)
+    }
+
+    /// Records the hardware profile of the current system and stores it at the given location.
+    fn capture(output: &Path) -> Result<()> {
+        let (disks, udev_disks, disk_attributes) = capture_disks()?;
+
+        let run_env = json!({
+            "boot_type": if Path::new("/sys/firmware/efi").is_dir() { "efi" } else { "bios" },
+            "disks": disks,
+            "network": capture_network()?,
+            "kernel_cmdline": fs::read_to_string("/proc/cmdline")?.trim(),
+            "total_memory": query_total_memory()?,
+            "hvm_supported": query_cpu_hvm_support()? as u32,
+            "secure_boot": query_secure_boot().map(|enabled| enabled as u32),
+        });
+
+        // Ensure that the recorded data can actually be used as runtime environment later on.
+        if let Err(err) = serde_json::from_value::<RuntimeInfo>(run_env.clone()) {
+            bail!("Recorded runtime environment is not valid: {err}");
+        }
+
+        let udev = json!({
+            "disks": udev_disks,
+            "disk_attributes": disk_attributes,
+            "nics": get_nics().map_err(|err| format_err!("Error getting NIC data: {err}"))?,
+        });
+
+        let mut files = vec![
+            (
+                Self::RUN_ENV_INFO_FILE,
+                serde_json::to_string_pretty(&run_env)?,
+            ),
+            (
+                Self::RUN_ENV_UDEV_FILE,
+                serde_json::to_string_pretty(&udev)?,
+            ),
+            (
+                Self::SYSTEM_INFO_FILE,
+                serde_json::to_string_pretty(&SysInfo::get()?)?,
+            ),
+        ];
+
+        // Only present when running from the installation environment.
+        match fs::read_to_string(Path::new(RUNTIME_DIR).join(Self::ISO_INFO_FILE)) {
+            Ok(content) => files.push((Self::ISO_INFO_FILE, content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => bail!("Failed to read {}: {err}", Self::ISO_INFO_FILE),
+        }
+
+        let output_name = output.to_string_lossy();
+        let is_archive = [".tar", ".tar.gz", ".tgz"]
+            .iter()
+            .any(|ext| output_name.ends_with(ext));
+
+        let target_dir = if is_archive {
+            std::env::temp_dir().join(format!("proxmox-hardware-profile-{}", process::id()))
+        } else {
+            output.to_path_buf()
+        };
+
+        fs::create_dir_all(&target_dir)?;
+        for (name, content) in &files {
+            fs::write(target_dir.join(name), content)
+                .map_err(|err| format_err!("Failed to write {name} to {target_dir:?}: {err}"))?;
+        }
+
+        if is_archive {
+            let result = run_tar(
+                Command::new("tar")
+                    .arg("-C")
+                    .arg(&target_dir)
+                    .arg("-caf")
+                    .arg(output)
+                    .args(files.iter().map(|(name, _)| name)),
+            );
+            fs::remove_dir_all(&target_dir)?;
+            result?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Serialize)]
 struct Devs {
+    #[serde(skip_serializing_if = "Option::is_none")]
     disks: Option<BTreeMap<String, BTreeMap<String, String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     nics: Option<BTreeMap<String, BTreeMap<String, String>>>,
 }
 
+/// Filter section of an answer file, as printed by `device-match` in TOML mode.
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct FilterSnippet {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter_match: Option<&'static str>,
+    filter: BTreeMap<String, String>,
+}
+
+/// Result of validating an answer file, as printed by `validate-answer` in JSON and TOML mode.
+#[derive(Serialize)]
+struct ValidationReport {
+    valid: bool,
+    errors: Vec<Diagnostic>,
+    warnings: Vec<Lint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
 fn main() {
     let args = Cli::parse();
+    let format = &args.global.format;
     let res = match &args.command {
         Commands::PrepareIso(args) => prepare_iso(args),
-        Commands::ValidateAnswer(args) => validate_answer(args),
-        Commands::DeviceInfo(args) => info(args),
-        Commands::DeviceMatch(args) => match_filter(args),
-        Commands::SystemInfo(args) => show_system_info(args),
+        Commands::ValidateAnswer(args) => validate_answer(args, format),
+        Commands::SimulateAnswer(args) => simulate_answer(args),
+        Commands::ShowAnswer(args) => show_answer(args, format),
+        Commands::DeviceInfo(args) => info(args, format),
+        Commands::DeviceMatch(args) => match_filter(args, format),
+        Commands::SystemInfo(args) => show_system_info(args, format),
+        Commands::CaptureProfile(args) => capture_profile(args),
+        Commands::JsonSchema(args) => print_json_schema(args),
     };
     if let Err(err) = res {
         eprintln!("Error: {err:?}");
@@ -220,33 +659,52 @@ fn main() {
     }
 }
 
-fn info(args: &CommandDeviceInfo) -> Result<()> {
+fn info(args: &CommandDeviceInfo, format: &OutputFormat) -> Result<()> {
     let mut devs = Devs {
         disks: None,
         nics: None,
     };
 
+    let udev_info = args
+        .profile
+        .as_ref()
+        .map(HardwareProfile::load_udev_info)
+        .transpose()?;
+
     if args.device == AllDeviceTypes::Network || args.device == AllDeviceTypes::All {
-        match get_nics() {
-            Ok(res) => devs.nics = Some(res),
-            Err(err) => bail!("Error getting NIC data: {err}"),
-        }
+        devs.nics = Some(get_device_list(Devicetype::Network, udev_info.as_ref())?);
     }
     if args.device == AllDeviceTypes::Disk || args.device == AllDeviceTypes::All {
-        match get_disks() {
-            Ok(res) => devs.disks = Some(res),
-            Err(err) => bail!("Error getting disk data: {err}"),
+        devs.disks = Some(get_device_list(Devicetype::Disk, udev_info.as_ref())?);
+    }
+
+    if let Some(data_format) = format.data_format() {
+        return print_formatted(&devs, data_format);
+    }
+
+    if let Some(disks) = &devs.disks {
+        println!("Disks:");
+        print_device_table(Devicetype::Disk, disks, disks.keys());
+    }
+    if let Some(nics) = &devs.nics {
+        if devs.disks.is_some() {
+            println!();
         }
+        println!("Network interfaces:");
+        print_device_table(Devicetype::Network, nics, nics.keys());
     }
-    println!("{}", serde_json::to_string_pretty(&devs).unwrap());
+    println!();
+    println!("Use '--format json' to show all properties that can be used in filters.");
     Ok(())
 }
 
-fn match_filter(args: &CommandDeviceMatch) -> Result<()> {
-    let devs: BTreeMap<String, BTreeMap<String, String>> = match args.r#type {
-        Devicetype::Disk => get_disks().unwrap(),
-        Devicetype::Network => get_nics().unwrap(),
-    };
+fn match_filter(args: &CommandDeviceMatch, format: &OutputFormat) -> Result<()> {
+    let udev_info = args
+        .profile
+        .as_ref()
+        .map(HardwareProfile::load_udev_info)
+        .transpose()?;
+    let devs = get_device_list(args.r#type.clone(), udev_info.as_ref())?;
     // parse filters
 
     let mut filters: BTreeMap<String, String> = BTreeMap::new();
@@ -273,25 +731,368 @@ fn match_filter(args: &CommandDeviceMatch) -> Result<()> {
         Devicetype::Network => get_single_udev_index(&filters, &devs).map(|r| vec![r]),
     };
 
-    match result {
-        Ok(result) => println!("{}", serde_json::to_string_pretty(&result).unwrap()),
+    let result = match result {
+        Ok(result) => result,
         Err(err) => bail!("Error matching filters: {err}"),
+    };
+
+    match format {
+        OutputFormat::Pretty => {
+            if result.is_empty() {
+                println!("No device matched the given filters.");
+            } else {
+                print_device_table(args.r#type.clone(), &devs, result.iter());
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&result)?),
+        OutputFormat::Toml => {
+            let (section, filter_match) = match args.r#type {
+                Devicetype::Disk => (
+                    "disk-setup",
+                    Some(match args.filter_match {
+                        FilterMatch::Any => "any",
+                        FilterMatch::All => "all",
+                    }),
+                ),
+                Devicetype::Network => ("network", None),
+            };
+            let snippet = BTreeMap::from([(
+                section,
+                FilterSnippet {
+                    filter_match,
+                    filter: filters,
+                },
+            )]);
+
+            println!("# matches: {}", result.join(", "));
+            print!("{}", toml::to_string_pretty(&snippet)?);
+        }
+    }
+    Ok(())
+}
+
+fn validate_answer(args: &CommandValidateAnswer, format: &OutputFormat) -> Result<()> {
+    let mut content = read_answer_file(&args.path)?;
+    let mut answer_format = args
+        .answer_format
+        .unwrap_or_else(|| detect_answer_format(&args.path, &content));
+    let profile = args
+        .profile
+        .as_ref()
+        .map(HardwareProfile::load)
+        .transpose()?;
+
+    let mut merged = false;
+    let mut prepared = match merge_includes(&args.path, &content, answer_format) {
+        Ok(Some(answer)) => {
+            content = answer;
+            answer_format = AnswerFormat::Toml;
+            merged = true;
+            Ok(())
+        }
+        Ok(None) => Ok(()),
+        Err(err) => Err(vec![Diagnostic::new(INCLUDE_KEY, err.to_string())]),
+    };
+    if prepared.is_ok() {
+        match render_answer(&content, profile.as_ref()) {
+            Ok(Some(rendered)) => content = rendered,
+            Ok(None) => return report_unrendered_template(format),
+            Err(diagnostics) => prepared = Err(diagnostics),
+        }
+    }
+
+    let locales = serde_json::from_str(LOCALE_INFO)?;
+    let mut result = prepared
+        .and_then(|()| validation::validate_answer(&content, answer_format, Some(&locales)));
+
+    // All hosts of a multi-host answer file are valid at this point, so only check which one
+    // would be used for the profiled system.
+    if let (Ok(_), Some(profile)) = (&result, &profile) {
+        let toml = answer_format
+            .to_toml(&content)
+            .map_err(|diag| format_err!("{diag}"))?;
+
+        match multihost::resolve_host(&toml, || profile.host_identity()) {
+            Ok(Some(resolved)) => {
+                result = validation::validate_answer(&resolved, AnswerFormat::Toml, Some(&locales))
+                    .map_err(|mut diagnostics| {
+                        diagnostics.iter_mut().for_each(strip_position);
+                        diagnostics
+                    })
+            }
+            Ok(None) => {}
+            Err(err) => {
+                result = Err(vec![Diagnostic::new(
+                    multihost::HOST_SECTION,
+                    err.to_string(),
+                )])
+            }
+        }
+    }
+
+    let mut warnings = match &result {
+        Ok(answer) if args.lint => {
+            lint_answer(args, profile.as_ref(), &content, answer_format, answer)
+        }
+        _ => Vec::new(),
+    };
+
+    // Positions within the merged answer file would only be misleading
+    if merged {
+        if let Err(diagnostics) = &mut result {
+            diagnostics.iter_mut().for_each(strip_position);
+        }
+        for lint in &mut warnings {
+            strip_position(&mut lint.diagnostic);
+        }
+    }
+
+    let Some(data_format) = format.data_format() else {
+        let answer = match result {
+            Ok(answer) => answer,
+            Err(diagnostics) => {
+                print_diagnostics(&args.path, &diagnostics);
+                bail!(
+                    "Found {} problem(s) in the answer file {:?}",
+                    diagnostics.len(),
+                    args.path
+                );
+            }
+        };
+
+        for lint in &warnings {
+            print_diagnostic(
+                &args.path,
+                &format!("warning[{}]", lint.id),
+                &lint.diagnostic,
+            );
+        }
+        println!("The answer file was parsed successfully, no errors found!");
+        if !warnings.is_empty() {
+            println!("Found {} warning(s).", warnings.len());
+        }
+        if args.debug {
+            println!("Parsed data from answer file:\n{:#?}", answer);
+        }
+        return Ok(());
+    };
+
+    let report = ValidationReport {
+        valid: result.is_ok(),
+        errors: result.err().unwrap_or_default(),
+        warnings,
+        note: None,
+    };
+    print_formatted(&report, data_format)?;
+
+    if !report.valid {
+        bail!("The answer file is not valid");
     }
     Ok(())
 }
 
-fn validate_answer(args: &CommandValidateAnswer) -> Result<()> {
-    let answer = parse_answer(&args.path)?;
-    if args.debug {
-        println!("Parsed data from answer file:\n{:#?}", answer);
+/// Reports an answer file template, which could only be checked for syntax errors without a
+/// hardware profile, as valid.
+fn report_unrendered_template(format: &OutputFormat) -> Result<()> {
+    let note = "The answer file is a template, only its template expressions were checked. Use \
+        '--profile' to validate it for a specific system.";
+
+    match format.data_format() {
+        Some(data_format) => {
+            let report = ValidationReport {
+                valid: true,
+                errors: Vec::new(),
+                warnings: Vec::new(),
+                note: Some(note.to_owned()),
+            };
+            print_formatted(&report, data_format)
+        }
+        None => {
+            println!("{note}");
+            Ok(())
+        }
+    }
+}
+
+fn lint_answer(
+    args: &CommandValidateAnswer,
+    profile: Option<&HardwareProfile>,
+    content: &str,
+    answer_format: AnswerFormat,
+    answer: &Answer,
+) -> Vec<Lint> {
+    let context = LintContext {
+        runtime_info: profile.map(|profile| &profile.runtime_info),
+        setup_info: profile.map(|profile| &profile.setup_info),
+        fetch_from: args.fetch_from.clone(),
+    };
+
+    validation::lint_answer(content, answer_format, answer, &context)
+        .into_iter()
+        .filter(|lint| !args.allow.contains(&lint.id))
+        .collect()
+}
+
+fn simulate_answer(args: &CommandSimulateAnswer) -> Result<()> {
+    let profile = HardwareProfile::load(&args.profile)?;
+    let answer = read_answer(&args.path, &profile)?;
+
+    let config = utils::parse_answer(
+        &answer,
+        &profile.udev_info,
+        &profile.runtime_info,
+        &serde_json::from_str(LOCALE_INFO)?,
+        &profile.setup_info,
+    )
+    .map_err(|err| format_err!("Error simulating installation: {err:#}"))?;
+
+    println!("{}", serde_json::to_string_pretty(&config)?);
+    Ok(())
+}
+
+fn show_answer(args: &CommandShowAnswer, format: &OutputFormat) -> Result<()> {
+    let profile = args
+        .profile
+        .as_ref()
+        .map(HardwareProfile::load)
+        .transpose()?;
+    let content = effective_answer(&args.path, args.answer_format, profile.as_ref())?;
+
+    let answer: toml::Table = toml::from_str(&content)
+        .map_err(|err| format_err!("Error parsing answer file: {}", err.message()))?;
+    // TOML is already the human-readable form of an answer file
+    print_formatted(&answer, format.data_format().unwrap_or(DataFormat::Toml))
+}
+
+fn show_system_info(_args: &CommandSystemInfo, format: &OutputFormat) -> Result<()> {
+    let info = match SysInfo::get() {
+        Ok(info) => info,
+        Err(err) => bail!("Error fetching system info: {err}"),
+    };
+
+    if let Some(data_format) = format.data_format() {
+        return print_formatted(&info, data_format);
     }
+
+    let mut rows = Vec::new();
+    flatten_json_value(String::new(), &serde_json::to_value(&info)?, &mut rows);
+    print_table(&["KEY", "VALUE"], &rows);
     Ok(())
 }
 
-fn show_system_info(_args: &CommandSystemInfo) -> Result<()> {
-    match SysInfo::as_json_pretty() {
-        Ok(res) => println!("{res}"),
-        Err(err) => eprintln!("Error fetching system info: {err}"),
+/// Prints the data in the given machine-readable format.
+fn print_formatted<T: Serialize>(data: &T, format: DataFormat) -> Result<()> {
+    match format {
+        DataFormat::Json => println!("{}", serde_json::to_string_pretty(data)?),
+        DataFormat::Toml => print!("{}", toml::to_string_pretty(data)?),
+    }
+    Ok(())
+}
+
+/// Prints a summary table of the given devices, with the most commonly used udev properties.
+fn print_device_table<'a>(
+    r#type: Devicetype,
+    devs: &BTreeMap<String, BTreeMap<String, String>>,
+    names: impl Iterator<Item = &'a String>,
+) {
+    let columns: &[&str] = match r#type {
+        Devicetype::Disk => &[
+            "DEVNAME",
+            "ID_MODEL",
+            "ID_SERIAL_SHORT",
+            "ID_WWN",
+            "transport",
+            DISK_SIZE_PROPERTY,
+        ],
+        Devicetype::Network => &[
+            "ID_NET_DRIVER",
+            "ID_PATH",
+            "ID_VENDOR_FROM_DATABASE",
+            "ID_MODEL_FROM_DATABASE",
+        ],
+    };
+
+    let rows: Vec<Vec<String>> = names
+        .map(|name| {
+            let props = devs.get(name);
+            let mut row = vec![name.clone()];
+            row.extend(columns.iter().map(|column| {
+                props
+                    .and_then(|props| props.get(*column))
+                    .cloned()
+                    .unwrap_or_else(|| "-".to_owned())
+            }));
+            row
+        })
+        .collect();
+
+    let mut headers = vec!["DEVICE"];
+    headers.extend_from_slice(columns);
+    print_table(&headers, &rows);
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |cells: Vec<&str>| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+
+    print_row(headers.to_vec());
+    for row in rows {
+        print_row(row.iter().map(String::as_str).collect());
+    }
+}
+
+/// Flattens a JSON value into rows of dotted keys and their values.
+fn flatten_json_value(prefix: String, value: &serde_json::Value, rows: &mut Vec<Vec<String>>) {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                flatten_json_value(join(key), value, rows);
+            }
+        }
+        serde_json::Value::Array(list) => {
+            for (index, value) in list.iter().enumerate() {
+                flatten_json_value(join(&index.to_string()), value, rows);
+            }
+        }
+        serde_json::Value::String(s) => rows.push(vec![prefix, s.clone()]),
+        other => rows.push(vec![prefix, other.to_string()]),
+    }
+}
+
+fn capture_profile(args: &CommandCaptureProfile) -> Result<()> {
+    HardwareProfile::capture(&args.output)?;
+    println!("Hardware profile stored at {:?}.", args.output);
+    Ok(())
+}
+
+fn print_json_schema(args: &CommandJsonSchema) -> Result<()> {
+    match args.document {
+        SchemaDocument::Answer => {
+            println!("{}", serde_json::to_string_pretty(&schema_for!(Answer))?)
+        }
+        SchemaDocument::HttpFetchPayload => print!("{HTTP_FETCH_PAYLOAD_SCHEMA}"),
+        SchemaDocument::PostHookInfo => print!("{POST_HOOK_INFO_SCHEMA}"),
     }
     Ok(())
 }
@@ -374,7 +1175,18 @@ fn prepare_iso(args: &CommandPrepareISO) -> Result<()> {
     )?;
 
     if let Some(answer_file) = &args.answer_file {
-        inject_file_to_iso(&tmp_iso, answer_file, "/answer.toml", &uuid)?;
+        // Included answer files are not placed on the ISO, so embed the merged answer file
+        let content = read_answer_file(answer_file)?;
+        let format = detect_answer_format(answer_file, &content);
+        match merge_includes(answer_file, &content, format)? {
+            Some(merged) => {
+                let mut answer_file_tmp = tmp_base.clone();
+                answer_file_tmp.push("answer.toml");
+                fs::write(&answer_file_tmp, merged)?;
+                inject_file_to_iso(&tmp_iso, &answer_file_tmp, "/answer.toml", &uuid)?;
+            }
+            None => inject_file_to_iso(&tmp_iso, answer_file, "/answer.toml", &uuid)?,
+        }
     }
 
     if let Some(first_boot) = &args.on_first_boot {
@@ -466,7 +1278,7 @@ fn get_iso_uuid(iso: impl AsRef<Path>) -> Result<String> {
         if line.starts_with("-volume_date uuid") {
             uuid = line
                 .split(' ')
-                .last()
+                .next_back()
                 .ok_or_else(|| format_err!("xorriso did behave unexpectedly"))?
                 .replace('\'', "")
                 .trim()
@@ -477,6 +1289,24 @@ fn get_iso_uuid(iso: impl AsRef<Path>) -> Result<String> {
     Ok(uuid)
 }
 
+/// Returns the udev properties of all devices of the given type, either from the recorded udev
+/// information if given, or from the current system.
+fn get_device_list(
+    r#type: Devicetype,
+    udev_info: Option<&UdevInfo>,
+) -> Result<BTreeMap<String, BTreeMap<String, String>>> {
+    match (r#type, udev_info) {
+        (Devicetype::Disk, Some(info)) => Ok(info.disks.clone()),
+        (Devicetype::Network, Some(info)) => Ok(info.nics.clone()),
+        (Devicetype::Disk, None) => {
+            get_disks().map_err(|err| format_err!("Error getting disk data: {err}"))
+        }
+        (Devicetype::Network, None) => {
+            get_nics().map_err(|err| format_err!("Error getting NIC data: {err}"))
+        }
+    }
+}
+
 fn get_disks() -> Result<BTreeMap<String, BTreeMap<String, String>>> {
     let unwanted_block_devs = [
         Pattern::new("ram[0-9]*")?,
@@ -492,6 +1322,7 @@ fn get_disks() -> Result<BTreeMap<String, BTreeMap<String, String>>> {
     const PROP_ISO9660_FS: &str = "E: ID_FS_TYPE=iso9660";
 
     let mut disks: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut disk_attributes = recorded_disk_attributes()?;
 
     'outer: for entry in fs::read_dir("/sys/block")? {
         let entry = entry.unwrap();
@@ -535,11 +1366,52 @@ fn get_disks() -> Result<BTreeMap<String, BTreeMap<String, String>>> {
             }
         }
 
+        // Same as recorded by the installation environment, see `disk_properties`
+        let path = entry.path();
+        if let Some(sectors) = read_sysfs_number(&path.join("size")) {
+            udev_props.insert(DISK_SIZE_PROPERTY.to_owned(), (sectors * 512).to_string());
+        }
+        if let Some(block_size) = read_sysfs_number(&path.join("queue/logical_block_size")) {
+            udev_props.insert(DISK_BLOCK_SIZE_PROPERTY.to_owned(), block_size.to_string());
+        }
+        if let Some(attributes) = udev_props
+            .get("DEVNAME")
+            .and_then(|devname| disk_attributes.remove(devname))
+        {
+            add_disk_attributes(&mut udev_props, &attributes);
+        }
+
         disks.insert(name, udev_props);
     }
     Ok(disks)
 }
 
+/// Reads the disk attributes recorded by the low-level installer, keyed by the device name of the
+/// disk. Empty when not running within the installation environment.
+fn recorded_disk_attributes() -> Result<BTreeMap<String, DiskAttributes>> {
+    let path = Path::new(RUNTIME_DIR).join(HardwareProfile::RUN_ENV_UDEV_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => bail!("Failed to read {path:?}: {err}"),
+    };
+    let udev_info: UdevInfo = serde_json::from_str(&content)
+        .map_err(|err| format_err!("Failed to parse {path:?}: {err}"))?;
+
+    Ok(udev_info
+        .disk_attributes
+        .into_iter()
+        .filter_map(|(index, attributes)| {
+            let devname = udev_info.disks.get(&index)?.get("DEVNAME")?;
+            Some((devname.clone(), attributes))
+        })
+        .collect())
+}
+
+fn read_sysfs_number(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
 fn get_nics() -> Result<BTreeMap<String, BTreeMap<String, String>>> {
     let mut nics: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
 
@@ -570,6 +1442,229 @@ fn get_nics() -> Result<BTreeMap<String, BTreeMap<String, String>>> {
     Ok(nics)
 }
 
+/// Collects the disks of the system in the same format as the low-level installer records them
+/// in its runtime environment, along with their udev properties and attributes keyed by the disk
+/// index. The attributes are only available when running within the installation environment.
+#[allow(clippy::type_complexity)]
+fn capture_disks() -> Result<(
+    Vec<serde_json::Value>,
+    BTreeMap<String, BTreeMap<String, String>>,
+    BTreeMap<String, DiskAttributes>,
+)> {
+    let mut disks = Vec::new();
+    let mut udev_disks = BTreeMap::new();
+    let mut disk_attributes = BTreeMap::new();
+    let mut recorded_attributes = recorded_disk_attributes()?;
+
+    let all_disks = get_disks().map_err(|err| format_err!("Error getting disk data: {err}"))?;
+    for (name, udev_props) in all_disks {
+        let syspath = format!("/sys/block/{name}");
+
+        let size =
+            match read_sysfs_value(format!("{syspath}/size")).and_then(|s| s.parse::<u64>().ok()) {
+                Some(size) => size,
+                None => continue,
+            };
+
+        let model: String = read_sysfs_value(format!("{syspath}/device/model"))
+            .unwrap_or_default()
+            .chars()
+            .take(30)
+            .collect();
+
+        let logical_bsize: Option<usize> =
+            read_sysfs_value(format!("{syspath}/queue/logical_block_size"))
+                .and_then(|s| s.parse().ok());
+
+        let devname = udev_props
+            .get("DEVNAME")
+            .cloned()
+            .unwrap_or_else(|| format!("/dev/{name}"));
+
+        let index = disks.len();
+        if let Some(attributes) = recorded_attributes.remove(&devname) {
+            disk_attributes.insert(index.to_string(), attributes);
+        }
+        disks.push(json!([index, devname, size, model, logical_bsize, syspath]));
+        udev_disks.insert(index.to_string(), udev_props);
+    }
+
+    Ok((disks, udev_disks, disk_attributes))
+}
+
+/// Collects the network configuration of the system in the same format as the low-level
+/// installer records it in its runtime environment.
+fn capture_network() -> Result<serde_json::Value> {
+    #[derive(Deserialize)]
+    struct IpAddrInfo {
+        family: String,
+        local: String,
+        prefixlen: usize,
+        scope: String,
+    }
+
+    #[derive(Deserialize)]
+    struct IpLink {
+        ifindex: usize,
+        ifname: String,
+        operstate: String,
+        #[serde(default)]
+        address: String,
+        #[serde(default)]
+        addr_info: Vec<IpAddrInfo>,
+    }
+
+    #[derive(Deserialize)]
+    struct IpRoute {
+        dst: String,
+        dev: Option<String>,
+        gateway: Option<String>,
+    }
+
+    let mut interfaces = BTreeMap::new();
+    let links: Vec<IpLink> = run_ip_json(&["address", "show"])?;
+    for link in links {
+        if link.ifname == "lo" {
+            continue;
+        }
+
+        let state = link.operstate.to_uppercase();
+        let addresses: Vec<_> = link
+            .addr_info
+            .iter()
+            .filter(|addr| state == "UP" && addr.scope != "link")
+            .map(|addr| {
+                json!({
+                    "family": addr.family,
+                    "address": addr.local,
+                    "prefix": addr.prefixlen,
+                })
+            })
+            .collect();
+
+        let mut interface = json!({
+            "index": link.ifindex,
+            "name": link.ifname,
+            "mac": link.address,
+            "state": state,
+        });
+        if !addresses.is_empty() {
+            interface["addresses"] = json!(addresses);
+        }
+
+        interfaces.insert(link.ifname, interface);
+    }
+
+    let mut routes = serde_json::Map::new();
+    for (family, key) in [("-4", "gateway4"), ("-6", "gateway6")] {
+        let family_routes: Vec<IpRoute> = run_ip_json(&[family, "route", "show"])?;
+        let default_route = family_routes
+            .into_iter()
+            .find(|route| route.dst == "default" && route.gateway.is_some());
+
+        if let Some(route) = default_route {
+            routes.insert(
+                key.to_owned(),
+                json!({ "dev": route.dev, "gateway": route.gateway }),
+            );
+        }
+    }
+
+    let mut dns = Vec::new();
+    let mut domain = None;
+    if let Ok(resolv_conf) = fs::read_to_string("/etc/resolv.conf") {
+        for line in resolv_conf.lines() {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("nameserver"), Some(server)) => dns.push(server.to_owned()),
+                (Some("domain"), Some(name)) if domain.is_none() => domain = Some(name.to_owned()),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(json!({
+        "interfaces": interfaces,
+        "routes": (!routes.is_empty()).then_some(routes),
+        "dns": { "domain": domain, "dns": dns },
+    }))
+}
+
+fn run_ip_json<T: for<'de> Deserialize<'de>>(args: &[&str]) -> Result<T> {
+    let output = Command::new("/usr/sbin/ip")
+        .arg("--json")
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        bail!(
+            "Error running 'ip {}': {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+fn run_tar(cmd: &mut Command) -> Result<String> {
+    let output = match cmd.output() {
+        Ok(output) => output,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            bail!("Could not find the 'tar' binary. Please install it.")
+        }
+        Err(err) => bail!("unexpected error when trying to execute 'tar' - {err}"),
+    };
+    if !output.status.success() {
+        bail!(
+            "Error running tar: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn read_sysfs_value(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_owned())
+}
+
+/// Returns the total memory of the system in MiB.
+fn query_total_memory() -> Result<usize> {
+    let meminfo = fs::read_to_string("/proc/meminfo")?;
+    meminfo
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("MemTotal:")?
+                .trim()
+                .strip_suffix("kB")?
+                .trim()
+                .parse::<usize>()
+                .ok()
+        })
+        .map(|kib| kib / 1024)
+        .ok_or_else(|| format_err!("Could not determine total memory from /proc/meminfo"))
+}
+
+fn query_cpu_hvm_support() -> Result<bool> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo")?;
+    Ok(cpuinfo.lines().any(|line| {
+        line.starts_with("flags")
+            && line
+                .split_whitespace()
+                .any(|flag| flag == "vmx" || flag == "svm")
+    }))
+}
+
+/// Returns whether SecureBoot is enabled, or `None` if it could not be determined, e.g. on
+/// legacy BIOS systems.
+fn query_secure_boot() -> Option<bool> {
+    const SECURE_BOOT_EFIVAR: &str =
+        "/sys/firmware/efi/efivars/SecureBoot-8be4df61-93ca-11d2-aa0d-00e098032b8c";
+
+    // The first four bytes are the attributes of the variable, followed by the actual value.
+    fs::read(SECURE_BOOT_EFIVAR)
+        .ok()
+        .and_then(|content| content.get(4).map(|value| *value == 1))
+}
+
 fn get_udev_properties(path: impl AsRef<Path> + fmt::Debug) -> Result<String> {
     let udev_output = Command::new("udevadm")
         .arg("info")
@@ -584,7 +1679,7 @@ fn get_udev_properties(path: impl AsRef<Path> + fmt::Debug) -> Result<String> {
     Ok(String::from_utf8(udev_output.stdout)?)
 }
 
-fn parse_answer(path: impl AsRef<Path> + fmt::Debug) -> Result<Answer> {
+fn read_answer_file(path: impl AsRef<Path> + fmt::Debug) -> Result<String> {
     let mut file = match fs::File::open(&path) {
         Ok(file) => file,
         Err(err) => bail!("Opening answer file {path:?} failed: {err}"),
@@ -594,18 +1689,155 @@ fn parse_answer(path: impl AsRef<Path> + fmt::Debug) -> Result<Answer> {
         bail!("Reading from file {path:?} failed: {err}");
     }
 
-    match toml::from_str(&contents) {
-        Ok(answer) => {
-            verify_locale_settings(&answer, &serde_json::from_str(LOCALE_INFO)?)?;
-            verify_first_boot_settings(&answer)?;
-            verify_email_and_root_password_settings(&answer)?;
-            println!("The answer file was parsed successfully, no errors found!");
-            Ok(answer)
+    Ok(contents)
+}
+
+/// Determines the format of an answer file by its extension, falling back to its content.
+fn detect_answer_format(path: impl AsRef<Path>, content: &str) -> AnswerFormat {
+    AnswerFormat::from_path(path).unwrap_or_else(|| AnswerFormat::detect(content))
+}
+
+/// Renders an answer file template against the system information of the hardware profile.
+/// Templates can only be checked for syntax errors if no profile is given, in which case `None`
+/// is returned.
+fn render_answer(
+    content: &str,
+    profile: Option<&HardwareProfile>,
+) -> Result<Option<String>, Vec<Diagnostic>> {
+    if !template::is_template(content) {
+        return Ok(Some(content.to_owned()));
+    }
+
+    let Some(profile) = profile else {
+        template::check(content)?;
+        return Ok(None);
+    };
+
+    let sysinfo = profile
+        .system_info()
+        .map_err(|err| vec![Diagnostic::new("", err.to_string())])?;
+    template::render(content, sysinfo).map(Some)
+}
+
+/// Merges all answer files included by the given one, relative to its location. Returns `None`
+/// if it does not include any, or if it cannot be parsed at all, which is left to the validation
+/// to report.
+fn merge_includes(path: &Path, content: &str, format: AnswerFormat) -> Result<Option<String>> {
+    match format.to_toml(content) {
+        Ok(content) if toml::from_str::<toml::Table>(&content).is_ok() => {
+            resolve_includes(&content, &AnswerSource::File(path.to_owned()))
         }
-        Err(err) => bail!("Error parsing answer file: {err}"),
+        _ => Ok(None),
     }
 }
 
+/// Builds the effective answer file in TOML, as passed on to the auto-installer, by merging all
+/// included answer files. With a hardware profile, the answer file is additionally rendered and
+/// the host section of multi-host answer files is selected for the system of the profile.
+fn effective_answer(
+    path: &Path,
+    format: Option<AnswerFormat>,
+    profile: Option<&HardwareProfile>,
+) -> Result<String> {
+    let content = read_answer_file(path)?;
+    let content = format
+        .unwrap_or_else(|| detect_answer_format(path, &content))
+        .to_toml(&content)
+        .map_err(|diag| format_err!("Error parsing answer file: {diag}"))?;
+    let content = resolve_includes(&content, &AnswerSource::File(path.to_owned()))
+        .map_err(|err| format_err!("Error including answer files: {err}"))?
+        .unwrap_or(content);
+
+    let Some(profile) = profile else {
+        return Ok(content);
+    };
+
+    let content = render_answer(&content, Some(profile))
+        .map_err(|diagnostics| {
+            let messages: Vec<String> = diagnostics.iter().map(|diag| diag.to_string()).collect();
+            format_err!("Error rendering answer file: {}", messages.join(", "))
+        })?
+        .unwrap_or(content);
+    Ok(multihost::resolve_host(&content, || profile.host_identity())?.unwrap_or(content))
+}
+
+/// Reads and validates the answer file the same way the auto-installer does. Templates and
+/// multi-host answer files are resolved for the system of the hardware profile.
+fn read_answer(path: &Path, profile: &HardwareProfile) -> Result<Answer> {
+    let content = effective_answer(path, None, None)?;
+    let locales = serde_json::from_str(LOCALE_INFO)?;
+
+    Answer::try_from_reader_with(
+        content.as_bytes(),
+        || profile.system_info().cloned(),
+        Some(&locales),
+    )
+}
+
+/// Reads and validates the answer file, reporting all problems found at once. Templates can only
+/// be checked for syntax errors, as they are rendered on the target system.
+fn parse_answer(path: &Path) -> Result<()> {
+    let content = read_answer_file(path)?;
+    let format = detect_answer_format(path, &content);
+    let (content, format, merged) = match merge_includes(path, &content, format)? {
+        Some(merged) => (merged, AnswerFormat::Toml, true),
+        None => (content, format, false),
+    };
+
+    let result = if template::is_template(&content) {
+        template::check(&content).map(|_| {
+            println!(
+                "The answer file is a template, only its template expressions were checked. Use \
+                'validate-answer' with a hardware profile to validate it for a specific system."
+            )
+        })
+    } else {
+        validation::validate_answer(&content, format, Some(&serde_json::from_str(LOCALE_INFO)?))
+            .map(|_| println!("The answer file was parsed successfully, no errors found!"))
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(mut diagnostics) => {
+            if merged {
+                diagnostics.iter_mut().for_each(strip_position);
+            }
+            print_diagnostics(path, &diagnostics);
+            bail!(
+                "Found {} problem(s) in the answer file {path:?}",
+                diagnostics.len()
+            );
+        }
+    }
+}
+
+/// Removes the position of a problem, for answer files which were changed before validation.
+fn strip_position(diag: &mut Diagnostic) {
+    diag.line = None;
+    diag.column = None;
+}
+
+fn print_diagnostics(path: &Path, diagnostics: &[Diagnostic]) {
+    for diag in diagnostics {
+        print_diagnostic(path, "error", diag);
+    }
+}
+
+fn print_diagnostic(path: &Path, label: &str, diag: &Diagnostic) {
+    let location = match (diag.line, diag.column) {
+        (Some(line), Some(column)) => format!("{}:{line}:{column}", path.display()),
+        _ => path.display().to_string(),
+    };
+
+    eprintln!("{label}: {}", diag.message);
+    if diag.path.is_empty() {
+        eprintln!("  --> {location}");
+    } else {
+        eprintln!("  --> {location} ('{}')", diag.path);
+    }
+    eprintln!();
+}
+
 fn check_prepare_requirements(args: &CommandPrepareISO) -> Result<()> {
     match Path::try_exists(&args.input) {
         Ok(true) => (),
@@ -635,3 +1867,65 @@ fn check_prepare_requirements(args: &CommandPrepareISO) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use super::*;
+
+    fn validate(name: &str, content: &str) -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "proxmox-answer-validate-{}-{name}.toml",
+            process::id()
+        ));
+        fs::write(&path, content).unwrap();
+
+        let cli = Cli::try_parse_from([
+            OsStr::new("proxmox-auto-install-assistant"),
+            OsStr::new("validate-answer"),
+            path.as_os_str(),
+        ])
+        .unwrap();
+        let Commands::ValidateAnswer(args) = &cli.command else {
+            unreachable!();
+        };
+        let result = validate_answer(args, &cli.global.format);
+
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn templates_without_profile_are_only_checked() {
+        let content =
+            "template = true\n\n[global]\nfqdn = \"pve-{{ dmi.system.serial | lower }}\"\n";
+        assert!(render_answer(content, None).unwrap().is_none());
+        assert!(validate("template", content).is_ok());
+
+        let content = "template = true\n\n[global]\nfqdn = \"pve-{{ dmi.system.serial | lower \"\n";
+        assert!(render_answer(content, None).is_err());
+        assert!(validate("template-invalid", content).is_err());
+    }
+
+    #[test]
+    fn simulated_answers_are_validated() {
+        let resources =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../proxmox-auto-installer/tests/resources");
+        let profile = HardwareProfile::load(&resources).unwrap();
+
+        let minimal = resources.join("parse_answer/minimal.toml");
+        assert!(read_answer(&minimal, &profile).is_ok());
+
+        let path =
+            std::env::temp_dir().join(format!("proxmox-answer-simulate-{}.toml", process::id()));
+        let content = fs::read_to_string(&minimal)
+            .unwrap()
+            .replace(r#"country = "at""#, r#"country = "zz""#);
+        fs::write(&path, content).unwrap();
+
+        let err = read_answer(&path, &profile).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(err.to_string().contains("country code 'zz' is not valid"));
+    }
+}
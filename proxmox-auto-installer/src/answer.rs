@@ -1,19 +1,37 @@
 use anyhow::{format_err, Result};
 use clap::ValueEnum;
 use proxmox_installer_common::{
+    net_checks::{check_addresses, check_bridge, check_dns_servers, check_gateways, check_mtu},
     options::{
-        BtrfsCompressOption, BtrfsRaidLevel, FsType, ZfsChecksumOption, ZfsCompressOption,
-        ZfsRaidLevel,
+        BondMode, BondXmitHashPolicy, BridgeOptions, BtrfsCompressOption, BtrfsRaidLevel,
+        DiskWipeMethod, ExistingDataPolicy, FsType, VlanIdRange, ZfsChecksumOption,
+        ZfsCompressOption, ZfsRaidLevel, DEFAULT_BRIDGE_NAME, VLAN_ID_RANGE,
     },
+    setup::LocaleInfo,
     utils::{CidrAddress, Fqdn},
 };
-use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, io::BufRead, net::IpAddr};
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{
+    de::{self, value::SeqAccessDeserializer, IntoDeserializer, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{
+    borrow::Cow, collections::BTreeMap, fmt, io::BufRead, marker::PhantomData, net::IpAddr,
+    path::Path,
+};
+
+use crate::{
+    filter::parse_filter,
+    multihost::{resolve_host, HostIdentity},
+    sysinfo::SysInfo,
+    template,
+    validation::{validate_answer, Diagnostic},
+};
 
 // BTreeMap is used to store filters as the order of the filters will be stable, compared to
 // storing them in a HashMap
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Answer {
     pub global: Global,
@@ -28,6 +46,18 @@ pub struct Answer {
 
 impl Answer {
     pub fn try_from_reader(reader: impl BufRead) -> Result<Self> {
+        Self::try_from_reader_with(reader, || Ok(serde_json::to_value(SysInfo::get()?)?), None)
+    }
+
+    /// Like [`Answer::try_from_reader`], but renders templates and resolves multi-host answer
+    /// files with the given system information instead of the one of the running system, e.g. as
+    /// recorded in a hardware profile. If given, the locale settings are checked against
+    /// `locales` as well.
+    pub fn try_from_reader_with(
+        reader: impl BufRead,
+        sysinfo: impl Fn() -> Result<serde_json::Value>,
+        locales: Option<&LocaleInfo>,
+    ) -> Result<Self> {
         let mut buffer = String::new();
         let lines = reader.lines();
         for line in lines {
@@ -35,11 +65,169 @@ impl Answer {
             buffer.push('\n');
         }
 
-        toml::from_str(&buffer).map_err(|err| format_err!("Failed parsing answer file: {err}"))
+        let rendered = template::is_template(&buffer);
+        if rendered {
+            buffer = template::render(&buffer, &sysinfo()?).map_err(|diagnostics| {
+                let messages: Vec<String> =
+                    diagnostics.iter().map(|diag| format!("  {diag}")).collect();
+                format_err!(
+                    "Failed rendering answer file template:\n{}",
+                    messages.join("\n")
+                )
+            })?;
+        }
+
+        let format = AnswerFormat::detect(&buffer);
+        let content = format
+            .to_toml(&buffer)
+            .map_err(|diag| format_err!("Failed parsing answer file: {diag}"))?;
+
+        // Positions are only meaningful if the answer file was used as-is
+        let identity = || HostIdentity::from_sysinfo(&sysinfo()?);
+        let (content, has_positions) = match resolve_host(&content, identity)? {
+            Some(resolved) => (resolved, false),
+            None => (content, format == AnswerFormat::Toml && !rendered),
+        };
+
+        validate_answer(&content, AnswerFormat::Toml, locales).map_err(|diagnostics| {
+            let messages: Vec<String> = diagnostics
+                .into_iter()
+                .map(|diag| {
+                    if has_positions {
+                        format!("  {diag}")
+                    } else {
+                        format!("  {}", Diagnostic::new(diag.path, diag.message))
+                    }
+                })
+                .collect();
+            format_err!("Failed parsing answer file:\n{}", messages.join("\n"))
+        })
     }
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// Formats an answer file can be written in. All of them are parsed into the same [`Answer`], the
+/// TOML representation being the canonical one.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AnswerFormat {
+    #[default]
+    Toml,
+    Json,
+    Yaml,
+}
+
+serde_plain::derive_display_from_serialize!(AnswerFormat);
+
+impl AnswerFormat {
+    /// Maps the media type of a `Content-Type` header to the answer format, if it is a known one.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+
+        match media_type.as_str() {
+            "application/toml" | "text/toml" | "text/x-toml" => Some(Self::Toml),
+            "application/json" | "text/json" => Some(Self::Json),
+            "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => {
+                Some(Self::Yaml)
+            }
+            other if other.ends_with("+json") => Some(Self::Json),
+            other if other.ends_with("+yaml") => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Derives the answer format from the file extension, if it is a known one.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the first line with actual content. Falls back to TOML if the
+    /// format cannot be determined, so that parsing errors are reported for TOML.
+    pub fn detect(content: &str) -> Self {
+        let line = content
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'));
+
+        match line {
+            Some(line) if line.starts_with('{') => Self::Json,
+            Some(line) if line.starts_with("---") || line.starts_with("%YAML") => Self::Yaml,
+            // Top-level key-value pairs are `key = value` in TOML, `key: value` in YAML
+            Some(line) => match line.find(['=', ':']) {
+                Some(pos) if line[pos..].starts_with(':') => Self::Yaml,
+                _ => Self::Toml,
+            },
+            None => Self::Toml,
+        }
+    }
+
+    /// Converts an answer file in this format into its TOML representation.
+    ///
+    /// `null` values are treated as if the key was not present, as TOML has no equivalent.
+    pub fn to_toml(self, content: &str) -> Result<String, Diagnostic> {
+        let value: serde_json::Value = match self {
+            Self::Toml => return Ok(content.to_owned()),
+            Self::Json => serde_json::from_str(content).map_err(|err| {
+                let mut diag = Diagnostic::new("", strip_error_location(&err.to_string()));
+                diag.line = Some(err.line());
+                diag.column = Some(err.column());
+                diag
+            })?,
+            Self::Yaml => serde_norway::from_str(content).map_err(|err| {
+                let mut diag = Diagnostic::new("", strip_error_location(&err.to_string()));
+                if let Some(location) = err.location() {
+                    diag.line = Some(location.line());
+                    diag.column = Some(location.column());
+                }
+                diag
+            })?,
+        };
+
+        if !value.is_object() {
+            return Err(Diagnostic::new(
+                "",
+                format!("expected the {self} answer file to contain an object at the top level"),
+            ));
+        }
+
+        toml::to_string(&remove_null_values(value))
+            .map_err(|err| Diagnostic::new("", format!("cannot be represented as TOML: {err}")))
+    }
+}
+
+/// Removes the "at line X column Y" suffix of JSON and YAML parser errors, as the position is
+/// part of the diagnostic already.
+fn strip_error_location(message: &str) -> &str {
+    message
+        .rsplit_once(" at line ")
+        .map(|(message, _)| message)
+        .unwrap_or(message)
+}
+
+fn remove_null_values(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(key, value)| (key, remove_null_values(value)))
+            .collect(),
+        serde_json::Value::Array(values) => values.into_iter().map(remove_null_values).collect(),
+        value => value,
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Global {
     pub country: String,
@@ -55,7 +243,7 @@ pub struct Global {
     pub root_ssh_keys: Vec<String>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct PostNotificationHookInfo {
     /// URL to send a POST request to
@@ -65,7 +253,7 @@ pub struct PostNotificationHookInfo {
 }
 
 /// Possible sources for the optional first-boot hook script/executable file.
-#[derive(Clone, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub enum FirstBootHookSourceMode {
     /// Fetch the executable file from an URL, specified in the parent.
@@ -81,7 +269,7 @@ pub enum FirstBootHookSourceMode {
 /// file.
 // Must be kept in sync with Proxmox::Install::Config and the service files in the
 // proxmox-first-boot package.
-#[derive(Clone, Default, Deserialize, Debug, PartialEq)]
+#[derive(Clone, Default, Deserialize, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub enum FirstBootHookServiceOrdering {
     /// Needed for bringing up the network itself, runs before any networking is attempted.
@@ -106,7 +294,7 @@ impl FirstBootHookServiceOrdering {
 
 /// Describes from where to fetch the first-boot hook script, either being baked into the ISO or
 /// from a URL.
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct FirstBootHookInfo {
     /// Mode how to retrieve the first-boot executable file, either from an URL or from the ISO if
@@ -121,95 +309,447 @@ pub struct FirstBootHookInfo {
     pub cert_fingerprint: Option<String>,
 }
 
-#[derive(Clone, Deserialize, Debug, Default, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, Default, PartialEq, JsonSchema)]
 #[serde(deny_unknown_fields)]
-enum NetworkConfigMode {
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum NetworkConfigMode {
     #[default]
     #[serde(rename = "from-dhcp")]
     FromDhcp,
     #[serde(rename = "from-answer")]
     FromAnswer,
+    /// Use the static configuration passed on the kernel commandline, e.g. with `ip=`,
+    /// `gateway=` and `nameserver=`.
+    #[serde(rename = "from-cmdline")]
+    FromCmdline,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+serde_plain::derive_display_from_serialize!(NetworkConfigMode);
+
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
-struct NetworkInAnswer {
+pub(crate) struct NetworkInAnswer {
     #[serde(default)]
     pub source: NetworkConfigMode,
-    pub cidr: Option<CidrAddress>,
-    pub dns: Option<IpAddr>,
-    pub gateway: Option<IpAddr>,
+    /// VLAN ID the management traffic is tagged with, if source == "from-answer".
+    pub vlan: Option<u16>,
+    /// Address(es) in CIDR notation, at most one IPv4 and one IPv6 address.
+    pub cidr: Option<OneOrMany<CidrAddress>>,
+    /// DNS server(s) in order of preference.
+    pub dns: Option<OneOrMany<IpAddr>>,
+    /// Gateway(s), one for the address of each IP version.
+    pub gateway: Option<OneOrMany<IpAddr>>,
     pub filter: Option<BTreeMap<String, String>>,
+    /// Policy for selecting the interface among all interfaces matching the filter, or all
+    /// interfaces if no filter is set, if source == "from-answer".
+    pub nic_selection: Option<NicSelection>,
+    /// Bond of multiple interfaces to use instead of a single one, if source == "from-answer".
+    pub bond: Option<BondInAnswer>,
+    /// DNS search domains, instead of the domain of the FQDN.
+    #[serde(default)]
+    pub search_domains: Vec<String>,
+    /// MTU of the management interface, e.g. 9000 for jumbo frames.
+    pub mtu: Option<u16>,
+    /// Bridge the management interface is put on, for products using a bridged network setup.
+    pub bridge: Option<BridgeInAnswer>,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+/// Policy for selecting the management interface, e.g. `nic_selection.policy = "fastest-link"`.
+#[derive(Clone, Deserialize, Debug, JsonSchema, PartialEq)]
+#[serde(tag = "policy", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum NicSelection {
+    /// The first interface with a carrier, i.e. a link.
+    FirstWithCarrier,
+    /// The interface the installation environment received its DHCP lease on.
+    DhcpLease,
+    /// The interface with the first MAC address of the list found.
+    Mac { macs: Vec<String> },
+    /// The interface with the fastest link, the first one of those with the same speed.
+    FastestLink,
+}
+
+impl fmt::Display for NicSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::FirstWithCarrier => "first-with-carrier",
+            Self::DhcpLease => "dhcp-lease",
+            Self::Mac { .. } => "mac",
+            Self::FastestLink => "fastest-link",
+        })
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BondInAnswer {
+    pub mode: BondMode,
+    /// Selects the member interfaces by their udev properties, all matching interfaces are used.
+    pub filter: BTreeMap<String, String>,
+    pub filter_match: Option<FilterMatch>,
+    /// Only supported for the 'balance-xor' and '802.3ad' modes.
+    pub xmit_hash_policy: Option<BondXmitHashPolicy>,
+    /// MII link monitoring interval in milliseconds.
+    pub miimon: Option<u32>,
+}
+
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct BridgeInAnswer {
+    /// Name of the bridge, defaults to 'vmbr0'.
+    pub name: Option<String>,
+    /// Whether the bridge is VLAN-aware, which it always is if 'vlan' is set.
+    #[serde(default)]
+    pub vlan_aware: bool,
+    /// VLAN IDs passed through a VLAN-aware bridge, defaults to '2-4094', extended to include
+    /// 'vlan' if necessary.
+    pub vids: Option<VlanIdRange>,
+}
+
+impl BridgeInAnswer {
+    /// Returns the bridge settings, with the defaults for the given VLAN ID of the management
+    /// interface filled in.
+    fn options(&self, vlan: Option<u16>) -> BridgeOptions {
+        BridgeOptions {
+            name: self
+                .name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BRIDGE_NAME.to_owned()),
+            vlan_aware: self.vlan_aware,
+            vids: self.vids.unwrap_or_else(|| VlanIdRange::default_for(vlan)),
+        }
+    }
+}
+
+/// A single value or a list of values, e.g. `dns = "192.0.2.1"` as well as
+/// `dns = ["192.0.2.1", "192.0.2.2"]`.
+#[derive(Clone, Debug)]
+pub struct OneOrMany<T>(pub Vec<T>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for OneOrMany<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OneOrManyVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrManyVisitor<T> {
+            type Value = OneOrMany<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a value or a list of values")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                T::deserialize(value.into_deserializer()).map(|value| OneOrMany(vec![value]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(OneOrMany)
+            }
+        }
+
+        deserializer.deserialize_any(OneOrManyVisitor(PhantomData))
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for OneOrMany<T> {
+    fn schema_name() -> Cow<'static, str> {
+        format!("OneOrMany_{}", T::schema_name()).into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        let item = generator.subschema_for::<T>();
+        json_schema!({
+            "anyOf": [item, { "type": "array", "items": item }],
+        })
+    }
+}
+
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 #[serde(try_from = "NetworkInAnswer", deny_unknown_fields)]
+#[schemars(with = "NetworkInAnswer")]
 pub struct Network {
     pub network_settings: NetworkSettings,
+    pub search_domains: Vec<String>,
+    pub mtu: Option<u16>,
+    pub bridge: Option<BridgeOptions>,
 }
 
-impl TryFrom<NetworkInAnswer> for Network {
-    type Error = &'static str;
+impl NetworkInAnswer {
+    /// Checks the network section for consistency and returns all problems found, as pairs of
+    /// the offending key relative to the section and an error message.
+    pub(crate) fn check(&self) -> Vec<(&'static str, String)> {
+        let mut problems = Vec::new();
+
+        for domain in &self.search_domains {
+            if !Fqdn::is_valid_domain(domain) {
+                problems.push((
+                    "search_domains",
+                    format!("'{domain}' is not a valid search domain."),
+                ));
+            }
+        }
 
-    fn try_from(network: NetworkInAnswer) -> Result<Self, Self::Error> {
-        if network.source == NetworkConfigMode::FromAnswer {
-            if network.cidr.is_none() {
-                return Err("Field 'cidr' must be set.");
+        if let Some(Err(err)) = self.mtu.map(check_mtu) {
+            problems.push(("mtu", err));
+        }
+        if let Some(bridge) = &self.bridge {
+            let vlan = self
+                .vlan
+                .filter(|_| self.source == NetworkConfigMode::FromAnswer);
+            if bridge.vids.is_some() && !bridge.vlan_aware && vlan.is_none() {
+                problems.push((
+                    "bridge.vids",
+                    "Field 'vids' is only used if 'vlan_aware' or 'vlan' is set.".into(),
+                ));
             }
-            if network.dns.is_none() {
-                return Err("Field 'dns' must be set.");
+            problems.extend(
+                check_bridge(&bridge.options(vlan), vlan)
+                    .into_iter()
+                    .map(|message| ("bridge", message)),
+            );
+        }
+
+        if self.source == NetworkConfigMode::FromAnswer {
+            let cidrs = self.cidr.as_ref().map(|cidr| cidr.0.as_slice());
+            let gateways = self.gateway.as_ref().map(|gateway| gateway.0.as_slice());
+            let dns = self.dns.as_ref().map(|dns| dns.0.as_slice());
+
+            match cidrs {
+                None | Some([]) => problems.push(("cidr", "Field 'cidr' must be set.".into())),
+                Some(cidrs)
+                    if cidrs.len() > 2
+                        || cidrs.len() == 2 && cidrs[0].is_ipv4() == cidrs[1].is_ipv4() =>
+                {
+                    problems.push((
+                        "cidr",
+                        "At most one IPv4 and one IPv6 address can be set.".into(),
+                    ))
+                }
+                Some(cidrs) => problems.extend(
+                    check_addresses(cidrs)
+                        .into_iter()
+                        .map(|message| ("cidr", message)),
+                ),
             }
-            if network.gateway.is_none() {
-                return Err("Field 'gateway' must be set.");
+            match gateways {
+                None | Some([]) => {
+                    problems.push(("gateway", "Field 'gateway' must be set.".into()))
+                }
+                Some(gateways) => problems.extend(
+                    check_gateways(cidrs.unwrap_or_default(), gateways)
+                        .into_iter()
+                        .map(|message| ("gateway", message)),
+                ),
             }
-            if network.filter.is_none() {
-                return Err("Field 'filter' must be set.");
+            match dns {
+                None | Some([]) => problems.push(("dns", "Field 'dns' must be set.".into())),
+                Some(dns) => problems.extend(
+                    check_dns_servers(cidrs.unwrap_or_default(), dns)
+                        .into_iter()
+                        .map(|message| ("dns", message)),
+                ),
+            }
+            match (&self.filter, &self.nic_selection, &self.bond) {
+                (None, None, None) => problems.push((
+                    "filter",
+                    "Either field 'filter', 'nic_selection' or 'bond' must be set.".into(),
+                )),
+                (Some(_), _, Some(_)) => {
+                    problems.push(("bond", "Cannot use both, 'filter' and 'bond'.".into()))
+                }
+                (_, Some(_), Some(_)) => problems.push((
+                    "bond",
+                    "Cannot use both, 'nic_selection' and 'bond'.".into(),
+                )),
+                _ => {}
+            }
+            if let Some(Err(err)) = self.filter.as_ref().map(parse_filter) {
+                problems.push(("filter", err.to_string()));
+            }
+            if let Some(NicSelection::Mac { macs }) = &self.nic_selection {
+                if macs.is_empty() {
+                    problems.push((
+                        "nic_selection.macs",
+                        "Field 'macs' must not be empty.".into(),
+                    ));
+                }
+                for mac in macs {
+                    if !is_valid_mac(mac) {
+                        problems.push((
+                            "nic_selection.macs",
+                            format!("'{mac}' is not a valid MAC address."),
+                        ));
+                    }
+                }
+            }
+            if let Some(bond) = &self.bond {
+                if bond.filter.is_empty() {
+                    problems.push(("bond.filter", "Field 'filter' must not be empty.".into()));
+                }
+                if let Err(err) = parse_filter(&bond.filter) {
+                    problems.push(("bond.filter", err.to_string()));
+                }
+                if bond.xmit_hash_policy.is_some() && !bond.mode.uses_xmit_hash_policy() {
+                    problems.push(("bond.xmit_hash_policy", "Field 'xmit_hash_policy' is only supported for 'balance-xor' and '802.3ad' bonds.".into()));
+                }
+            }
+            if self.vlan.is_some_and(|vlan| !VLAN_ID_RANGE.contains(&vlan)) {
+                problems.push(("vlan", "VLAN ID must be between 1 and 4094.".into()));
             }
-
-            Ok(Network {
-                network_settings: NetworkSettings::Manual(NetworkManual {
-                    cidr: network.cidr.unwrap(),
-                    dns: network.dns.unwrap(),
-                    gateway: network.gateway.unwrap(),
-                    filter: network.filter.unwrap(),
-                }),
-            })
         } else {
-            if network.cidr.is_some() {
-                return Err("Field 'cidr' not supported for 'from-dhcp' config.");
+            if self.vlan.is_some() {
+                problems.push((
+                    "vlan",
+                    format!("Field 'vlan' not supported for '{}' config.", self.source),
+                ));
+            }
+            if self.cidr.is_some() {
+                problems.push((
+                    "cidr",
+                    format!("Field 'cidr' not supported for '{}' config.", self.source),
+                ));
             }
-            if network.dns.is_some() {
-                return Err("Field 'dns' not supported for 'from-dhcp' config.");
+            if self.dns.is_some() {
+                problems.push((
+                    "dns",
+                    format!("Field 'dns' not supported for '{}' config.", self.source),
+                ));
             }
-            if network.gateway.is_some() {
-                return Err("Field 'gateway' not supported for 'from-dhcp' config.");
+            if self.gateway.is_some() {
+                problems.push((
+                    "gateway",
+                    format!(
+                        "Field 'gateway' not supported for '{}' config.",
+                        self.source
+                    ),
+                ));
             }
-            if network.filter.is_some() {
-                return Err("Field 'filter' not supported for 'from-dhcp' config.");
+            if self.filter.is_some() {
+                problems.push((
+                    "filter",
+                    format!("Field 'filter' not supported for '{}' config.", self.source),
+                ));
             }
+            if self.bond.is_some() {
+                problems.push((
+                    "bond",
+                    format!("Field 'bond' not supported for '{}' config.", self.source),
+                ));
+            }
+            if self.nic_selection.is_some() {
+                problems.push((
+                    "nic_selection",
+                    format!(
+                        "Field 'nic_selection' not supported for '{}' config.",
+                        self.source
+                    ),
+                ));
+            }
+        }
 
-            Ok(Network {
-                network_settings: NetworkSettings::FromDhcp,
-            })
+        problems
+    }
+}
+
+/// Checks whether the given string is a MAC address in the usual notation, e.g.
+/// `bc:24:11:00:00:01`.
+fn is_valid_mac(mac: &str) -> bool {
+    let octets: Vec<&str> = mac.split(':').collect();
+    octets.len() == 6
+        && octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+impl TryFrom<NetworkInAnswer> for Network {
+    type Error = String;
+
+    fn try_from(network: NetworkInAnswer) -> Result<Self, Self::Error> {
+        if let Some((_, err)) = network.check().into_iter().next() {
+            return Err(err);
         }
+
+        let network_settings = if network.source == NetworkConfigMode::FromAnswer {
+            // Use the IPv4 address as primary one for dual-stack configurations
+            let mut cidrs = network.cidr.unwrap().0;
+            cidrs.sort_by_key(|cidr| cidr.is_ipv6());
+            let gateways = network.gateway.unwrap().0;
+            let gateway_for = |cidr: &CidrAddress| {
+                gateways
+                    .iter()
+                    .find(|gateway| gateway.is_ipv4() == cidr.is_ipv4())
+                    .copied()
+            };
+
+            NetworkSettings::Manual(NetworkManual {
+                vlan: network.vlan,
+                // Safety: check() ensures that there is a gateway for each address
+                gateway: gateway_for(&cidrs[0]).unwrap(),
+                gateway6: cidrs.get(1).and_then(gateway_for),
+                cidr6: cidrs.get(1).cloned(),
+                cidr: cidrs.swap_remove(0),
+                dns: network.dns.unwrap().0,
+                interfaces: match (network.bond, network.nic_selection) {
+                    (Some(bond), _) => NetworkInterfaces::Bond(bond),
+                    (None, Some(selection)) => NetworkInterfaces::Selected {
+                        filter: network.filter,
+                        selection,
+                    },
+                    (None, None) => NetworkInterfaces::Single(network.filter.unwrap()),
+                },
+            })
+        } else if network.source == NetworkConfigMode::FromCmdline {
+            NetworkSettings::FromCmdline
+        } else {
+            NetworkSettings::FromDhcp
+        };
+
+        Ok(Network {
+            network_settings,
+            search_domains: network.search_domains,
+            mtu: network.mtu,
+            bridge: network.bridge.as_ref().map(|bridge| {
+                bridge.options(
+                    network
+                        .vlan
+                        .filter(|_| network.source == NetworkConfigMode::FromAnswer),
+                )
+            }),
+        })
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum NetworkSettings {
     FromDhcp,
+    FromCmdline,
     Manual(NetworkManual),
 }
 
 #[derive(Clone, Debug)]
 pub struct NetworkManual {
+    pub vlan: Option<u16>,
     pub cidr: CidrAddress,
-    pub dns: IpAddr,
     pub gateway: IpAddr,
-    pub filter: BTreeMap<String, String>,
+    /// Additional IPv6 address and gateway, for dual-stack configurations.
+    pub cidr6: Option<CidrAddress>,
+    pub gateway6: Option<IpAddr>,
+    pub dns: Vec<IpAddr>,
+    pub interfaces: NetworkInterfaces,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+/// Interface(s) the management network is configured on.
+#[derive(Clone, Debug)]
+pub enum NetworkInterfaces {
+    /// The first interface matching the filter.
+    Single(BTreeMap<String, String>),
+    /// The interface selected by the policy among all interfaces matching the filter, if any.
+    Selected {
+        filter: Option<BTreeMap<String, String>>,
+        selection: NicSelection,
+    },
+    Bond(BondInAnswer),
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct DiskSetup {
     pub filesystem: Filesystem,
@@ -217,29 +757,96 @@ pub struct DiskSetup {
     pub disk_list: Vec<String>,
     pub filter: Option<BTreeMap<String, String>>,
     pub filter_match: Option<FilterMatch>,
+    /// Allow installing to removable disks, e.g. USB sticks or SD cards.
+    #[serde(default)]
+    pub allow_removable: bool,
+    /// What to do about existing data on the target disks and existing storage with the names
+    /// used by the installation. Defaults to 'rename'.
+    pub existing_data: Option<ExistingDataPolicy>,
+    /// How to wipe the target disks before partitioning them.
+    pub wipe: Option<DiskWipeMethod>,
     pub zfs: Option<ZfsOptions>,
     pub lvm: Option<LvmOptions>,
     pub btrfs: Option<BtrfsOptions>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(try_from = "DiskSetup", deny_unknown_fields)]
+#[schemars(with = "DiskSetup")]
 pub struct Disks {
     pub fs_type: FsType,
     pub disk_selection: DiskSelection,
     pub filter_match: Option<FilterMatch>,
+    pub allow_removable: bool,
+    pub existing_data: Option<ExistingDataPolicy>,
+    pub wipe: Option<DiskWipeMethod>,
     pub fs_options: FsOptions,
 }
 
+impl DiskSetup {
+    /// Checks the disk setup section for consistency and returns all problems found, as pairs of
+    /// the offending key relative to the section and an error message.
+    pub(crate) fn check(&self) -> Vec<(&'static str, String)> {
+        let mut problems = Vec::new();
+
+        if self.disk_list.is_empty() && self.filter.is_none() {
+            problems.push(("", "Need either 'disk_list' or 'filter' set".into()));
+        }
+        if !self.disk_list.is_empty() && self.filter.is_some() {
+            problems.push(("filter", "Cannot use both, 'disk_list' and 'filter'".into()));
+        }
+        if let Some(Err(err)) = self.filter.as_ref().map(parse_filter) {
+            problems.push(("filter", err.to_string()));
+        }
+
+        // TODO: improve checks for foreign FS options. E.g. less verbose and handling new FS types
+        // automatically
+        match self.filesystem {
+            Filesystem::Xfs | Filesystem::Ext4 => {
+                if self.zfs.is_some() || self.btrfs.is_some() {
+                    let key = if self.zfs.is_some() { "zfs" } else { "btrfs" };
+                    problems.push((key, "make sure only 'lvm' options are set".into()));
+                }
+                if self.disk_list.len() > 1 {
+                    problems.push((
+                        "disk_list",
+                        "make sure to define only one disk for ext4 and xfs".into(),
+                    ));
+                }
+            }
+            Filesystem::Zfs => {
+                if self.lvm.is_some() || self.btrfs.is_some() {
+                    let key = if self.lvm.is_some() { "lvm" } else { "btrfs" };
+                    problems.push((key, "make sure only 'zfs' options are set".into()));
+                }
+                if !matches!(self.zfs, Some(ZfsOptions { raid: Some(_), .. })) {
+                    problems.push(("zfs.raid", "ZFS raid level 'zfs.raid' must be set".into()));
+                }
+            }
+            Filesystem::Btrfs => {
+                if self.zfs.is_some() || self.lvm.is_some() {
+                    let key = if self.zfs.is_some() { "zfs" } else { "lvm" };
+                    problems.push((key, "make sure only 'btrfs' options are set".into()));
+                }
+                if !matches!(self.btrfs, Some(BtrfsOptions { raid: Some(_), .. })) {
+                    problems.push((
+                        "btrfs.raid",
+                        "BTRFS raid level 'btrfs.raid' must be set".into(),
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+}
+
 impl TryFrom<DiskSetup> for Disks {
-    type Error = &'static str;
+    type Error = String;
 
     fn try_from(source: DiskSetup) -> Result<Self, Self::Error> {
-        if source.disk_list.is_empty() && source.filter.is_none() {
-            return Err("Need either 'disk_list' or 'filter' set");
-        }
-        if !source.disk_list.is_empty() && source.filter.is_some() {
-            return Err("Cannot use both, 'disk_list' and 'filter'");
+        if let Some((_, err)) = source.check().into_iter().next() {
+            return Err(err);
         }
 
         let disk_selection = if !source.disk_list.is_empty() {
@@ -248,47 +855,16 @@ impl TryFrom<DiskSetup> for Disks {
             DiskSelection::Filter(source.filter.clone().unwrap())
         };
 
-        let lvm_checks = |source: &DiskSetup| -> Result<(), Self::Error> {
-            if source.zfs.is_some() || source.btrfs.is_some() {
-                return Err("make sure only 'lvm' options are set");
-            }
-            if source.disk_list.len() > 1 {
-                return Err("make sure to define only one disk for ext4 and xfs");
-            }
-            Ok(())
-        };
-        // TODO: improve checks for foreign FS options. E.g. less verbose and handling new FS types
-        // automatically
         let (fs, fs_options) = match source.filesystem {
-            Filesystem::Xfs => {
-                lvm_checks(&source)?;
-                (FsType::Xfs, FsOptions::LVM(source.lvm.unwrap_or_default()))
-            }
-            Filesystem::Ext4 => {
-                lvm_checks(&source)?;
-                (FsType::Ext4, FsOptions::LVM(source.lvm.unwrap_or_default()))
-            }
+            Filesystem::Xfs => (FsType::Xfs, FsOptions::LVM(source.lvm.unwrap_or_default())),
+            Filesystem::Ext4 => (FsType::Ext4, FsOptions::LVM(source.lvm.unwrap_or_default())),
             Filesystem::Zfs => {
-                if source.lvm.is_some() || source.btrfs.is_some() {
-                    return Err("make sure only 'zfs' options are set");
-                }
-                match source.zfs {
-                    None | Some(ZfsOptions { raid: None, .. }) => {
-                        return Err("ZFS raid level 'zfs.raid' must be set")
-                    }
-                    Some(opts) => (FsType::Zfs(opts.raid.unwrap()), FsOptions::ZFS(opts)),
-                }
+                let opts = source.zfs.unwrap();
+                (FsType::Zfs(opts.raid.unwrap()), FsOptions::ZFS(opts))
             }
             Filesystem::Btrfs => {
-                if source.zfs.is_some() || source.lvm.is_some() {
-                    return Err("make sure only 'btrfs' options are set");
-                }
-                match source.btrfs {
-                    None | Some(BtrfsOptions { raid: None, .. }) => {
-                        return Err("BTRFS raid level 'btrfs.raid' must be set")
-                    }
-                    Some(opts) => (FsType::Btrfs(opts.raid.unwrap()), FsOptions::BTRFS(opts)),
-                }
+                let opts = source.btrfs.unwrap();
+                (FsType::Btrfs(opts.raid.unwrap()), FsOptions::BTRFS(opts))
             }
         };
 
@@ -296,6 +872,9 @@ impl TryFrom<DiskSetup> for Disks {
             fs_type: fs,
             disk_selection,
             filter_match: source.filter_match,
+            allow_removable: source.allow_removable,
+            existing_data: source.existing_data,
+            wipe: source.wipe,
             fs_options,
         };
         Ok(res)
@@ -314,14 +893,14 @@ pub enum DiskSelection {
     Selection(Vec<String>),
     Filter(BTreeMap<String, String>),
 }
-#[derive(Clone, Deserialize, Debug, PartialEq, ValueEnum)]
+#[derive(Clone, Deserialize, Debug, PartialEq, ValueEnum, JsonSchema)]
 #[serde(rename_all = "lowercase", deny_unknown_fields)]
 pub enum FilterMatch {
     Any,
     All,
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase", deny_unknown_fields)]
 pub enum Filesystem {
     Ext4,
@@ -330,7 +909,7 @@ pub enum Filesystem {
     Btrfs,
 }
 
-#[derive(Clone, Copy, Default, Deserialize, Debug)]
+#[derive(Clone, Copy, Default, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct ZfsOptions {
     pub raid: Option<ZfsRaidLevel>,
@@ -342,7 +921,7 @@ pub struct ZfsOptions {
     pub hdsize: Option<f64>,
 }
 
-#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug)]
+#[derive(Clone, Copy, Default, Deserialize, Serialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct LvmOptions {
     pub hdsize: Option<f64>,
@@ -352,7 +931,7 @@ pub struct LvmOptions {
     pub minfree: Option<f64>,
 }
 
-#[derive(Clone, Copy, Default, Deserialize, Debug)]
+#[derive(Clone, Copy, Default, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub struct BtrfsOptions {
     pub hdsize: Option<f64>,
@@ -360,7 +939,7 @@ pub struct BtrfsOptions {
     pub compress: Option<BtrfsCompressOption>,
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub enum KeyboardLayout {
     De,
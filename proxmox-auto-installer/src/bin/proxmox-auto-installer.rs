@@ -3,7 +3,7 @@ use log::{error, info, LevelFilter};
 use std::{
     env,
     fs::{self, File},
-    io::{BufRead, BufReader, Write},
+    io::{self, BufRead, BufReader, Write},
     path::PathBuf,
     process::ExitCode,
 };
@@ -11,8 +11,8 @@ use std::{
 use proxmox_installer_common::{
     http,
     setup::{
-        installer_setup, read_json, spawn_low_level_installer, LocaleInfo, LowLevelMessage,
-        RuntimeInfo, SetupInfo,
+        installer_setup, read_json, spawn_low_level_installer, InstallConfig, LocaleInfo,
+        LowLevelMessage, RuntimeInfo, SetupInfo,
     },
     FIRST_BOOT_EXEC_MAX_SIZE, FIRST_BOOT_EXEC_NAME, RUNTIME_DIR,
 };
@@ -65,7 +65,7 @@ fn setup_first_boot_executable(first_boot: &FirstBootHookInfo) -> Result<()> {
     }
 }
 
-fn auto_installer_setup(in_test_mode: bool) -> Result<(Answer, UdevInfo)> {
+fn auto_installer_setup(in_test_mode: bool, dry_run: bool) -> Result<(Answer, UdevInfo)> {
     let base_path = if in_test_mode { "./testdir" } else { "/" };
     let mut path = PathBuf::from(base_path);
 
@@ -82,8 +82,11 @@ fn auto_installer_setup(in_test_mode: bool) -> Result<(Answer, UdevInfo)> {
 
     let answer = Answer::try_from_reader(std::io::stdin().lock())?;
 
-    if let Some(first_boot) = &answer.first_boot {
-        setup_first_boot_executable(first_boot)?;
+    // Nothing gets installed in dry-run mode, so there is no need to fetch the hook either
+    if !dry_run {
+        if let Some(first_boot) = &answer.first_boot {
+            setup_first_boot_executable(first_boot)?;
+        }
     }
 
     Ok((answer, udev_info))
@@ -94,12 +97,16 @@ fn main() -> ExitCode {
         panic!("could not initialize logging: {}", err);
     }
 
-    let in_test_mode = match env::args().nth(1).as_deref() {
-        Some("-t") => true,
-        // Always force the test directory in debug builds
-        _ => cfg!(debug_assertions),
-    };
-    info!("Starting auto installer");
+    let args: Vec<String> = env::args().skip(1).collect();
+    // Always force the test directory in debug builds
+    let in_test_mode = args.iter().any(|arg| arg == "-t") || cfg!(debug_assertions);
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+
+    if dry_run {
+        info!("Starting auto installer in dry-run mode, no changes will be made");
+    } else {
+        info!("Starting auto installer");
+    }
 
     let (setup_info, locales, runtime_info) = match installer_setup(in_test_mode) {
         Ok(result) => result,
@@ -109,7 +116,7 @@ fn main() -> ExitCode {
         }
     };
 
-    let (answer, udevadm_info) = match auto_installer_setup(in_test_mode) {
+    let (answer, udevadm_info) = match auto_installer_setup(in_test_mode, dry_run) {
         Ok(result) => result,
         Err(err) => {
             error!("Autoinstaller setup error: {err}");
@@ -117,6 +124,16 @@ fn main() -> ExitCode {
         }
     };
 
+    if dry_run {
+        return match run_dry_run(&answer, &locales, &runtime_info, &udevadm_info, &setup_info) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                error!("Dry run failed: {err:#}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
     match run_installation(&answer, &locales, &runtime_info, &udevadm_info, &setup_info) {
         Ok(_) => info!("Installation done."),
         Err(err) => {
@@ -138,6 +155,117 @@ fn exit_failure(reboot_on_error: bool) -> ExitCode {
     }
 }
 
+/// Resolves the answer file into the final low-level installer configuration and prints it, along
+/// with a short summary of the selected disks and network interface. Never calls the low-level
+/// installer, thus no disks are touched.
+fn run_dry_run(
+    answer: &Answer,
+    locales: &LocaleInfo,
+    runtime_info: &RuntimeInfo,
+    udevadm_info: &UdevInfo,
+    setup_info: &SetupInfo,
+) -> Result<()> {
+    let config = parse_answer(answer, udevadm_info, runtime_info, locales, setup_info)?;
+
+    println!("{}", serde_json::to_string_pretty(&config)?);
+    println!();
+    write_dry_run_summary(&mut io::stdout().lock(), &config, runtime_info)?;
+
+    Ok(())
+}
+
+fn write_dry_run_summary(
+    out: &mut impl Write,
+    config: &InstallConfig,
+    runtime_info: &RuntimeInfo,
+) -> io::Result<()> {
+    writeln!(
+        out,
+        "Filesystem: {} ({:.2} GiB)",
+        config.filesys, config.hdsize
+    )?;
+    writeln!(
+        out,
+        "Existing data: {}",
+        config.existing_data.unwrap_or_default()
+    )?;
+    if let Some(wipe) = config.wipe {
+        writeln!(out, "Disk wipe: {wipe}")?;
+    }
+
+    // For LVM-based setups, the disk is referenced by its path, for ZFS and Btrfs by its index.
+    let disks = runtime_info.disks.iter().filter(|disk| {
+        config.target_hd.as_ref() == Some(&disk.path)
+            || config.disk_selection.values().any(|i| *i == disk.index)
+    });
+
+    writeln!(out, "Selected disks:")?;
+    for disk in disks {
+        writeln!(out, "  {disk}")?;
+    }
+
+    let link = match runtime_info.network.interfaces.get(&config.mngmt_nic) {
+        Some(iface) => {
+            let state = if iface.has_carrier() {
+                "link up"
+            } else {
+                "no link"
+            };
+            let mut link = vec![iface.mac.clone(), state.to_owned()];
+            if let Some(speed) = iface.speed {
+                link.push(format!("{speed} Mbit/s"));
+            }
+            link.join(", ")
+        }
+        None => "unknown".to_owned(),
+    };
+
+    writeln!(out, "Management interface: {} ({link})", config.mngmt_nic)?;
+    if let Some(bond) = &config.bond_opts {
+        let mut mode = bond.mode.to_string();
+        if let Some(policy) = bond.xmit_hash_policy {
+            mode.push_str(&format!(", hash policy {policy}"));
+        }
+        writeln!(out, "  Bond:     {} ({mode})", bond.slaves.join(", "))?;
+    }
+    if let Some(vlan_id) = config.vlan_id {
+        writeln!(out, "  VLAN:     {vlan_id}")?;
+    }
+    if let Some(bridge) = &config.bridge_opts {
+        if bridge.vlan_aware {
+            writeln!(
+                out,
+                "  Bridge:   {} (VLAN-aware, VIDs {})",
+                bridge.name, bridge.vids
+            )?;
+        } else {
+            writeln!(out, "  Bridge:   {}", bridge.name)?;
+        }
+    }
+    if let Some(mtu) = config.mtu {
+        writeln!(out, "  MTU:      {mtu}")?;
+    }
+    writeln!(out, "  Hostname: {}.{}", config.hostname, config.domain)?;
+    writeln!(out, "  Address:  {}", config.cidr)?;
+    writeln!(out, "  Gateway:  {}", config.gateway)?;
+    if let Some(cidr6) = &config.cidr6 {
+        writeln!(out, "  Address6: {cidr6}")?;
+    }
+    if let Some(gateway6) = config.gateway6 {
+        writeln!(out, "  Gateway6: {gateway6}")?;
+    }
+    let dns: Vec<String> = std::iter::once(&config.dns)
+        .chain(&config.additional_dns)
+        .map(ToString::to_string)
+        .collect();
+    writeln!(out, "  DNS:      {}", dns.join(", "))?;
+    if !config.search_domains.is_empty() {
+        writeln!(out, "  Search:   {}", config.search_domains.join(", "))?;
+    }
+
+    Ok(())
+}
+
 fn run_installation(
     answer: &Answer,
     locales: &LocaleInfo,
@@ -234,3 +362,51 @@ fn run_installation(
     };
     inner().map_err(|err| format_err!("low level installer returned early: {err}"))
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use proxmox_installer_common::setup::load_installer_setup_files;
+
+    use super::*;
+
+    #[test]
+    fn dry_run_summary_shows_network_setup() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/resources");
+        let (setup_info, locales, mut runtime_info) =
+            load_installer_setup_files(&resources).unwrap();
+        runtime_info.disks.sort();
+        let udev_info: UdevInfo = read_json(resources.join("run-env-udev.json")).unwrap();
+
+        let content =
+            fs::read_to_string(resources.join("parse_answer/dual_stack_bond_vlan.toml")).unwrap();
+        let answer: Answer = toml::from_str(&content).unwrap();
+        let config =
+            parse_answer(&answer, &udev_info, &runtime_info, &locales, &setup_info).unwrap();
+
+        let mut summary = Vec::new();
+        write_dry_run_summary(&mut summary, &config, &runtime_info).unwrap();
+
+        pretty_assertions::assert_eq!(
+            String::from_utf8(summary).unwrap(),
+            "Filesystem: ext4 (223.57 GiB)
+Existing data: rename
+Selected disks:
+  /dev/sda (SAMSUNG MZ7KM240) (223.57 GiB)
+Management interface: enp129s0f0np0 (1c:34:da:5c:5e:24, no link)
+  Bond:     enp129s0f0np0, enp129s0f1np1 (802.3ad, hash policy layer3+4)
+  VLAN:     42
+  Bridge:   vmbr1 (VLAN-aware, VIDs 2-100)
+  MTU:      9000
+  Hostname: pveauto.testinstall
+  Address:  10.10.10.10/24
+  Gateway:  10.10.10.1
+  Address6: 2001:db8::10/64
+  Gateway6: 2001:db8::1
+  DNS:      10.10.10.1, 2001:db8::1
+  Search:   testinstall, example.com
+"
+        );
+    }
+}
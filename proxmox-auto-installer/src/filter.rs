@@ -0,0 +1,280 @@
+//! Filter expressions for selecting disks and network interfaces by their properties, e.g.:
+//!
+//! ```toml
+//! [disk-setup]
+//! filter.DEVNAME = "/dev/nvme*"
+//! filter.size = ">400G"
+//! filter.ID_MODEL = "!*SATADOM*"
+//! filter_match = "all"
+//! ```
+//!
+//! Each value of a filter is matched against the property with the same name, according to its
+//! form:
+//!
+//! * `<glob>` matches the property against a glob pattern, e.g. `Micron_*`.
+//! * `~<regex>` searches the property for a regular expression, e.g. `~^nvme[0-9]+n1$`.
+//! * `>`, `>=`, `<` or `<=` followed by a number compares the property numerically, e.g.
+//!   `>=4096`. Numbers can have a unit suffix, either decimal (`K`, `M`, `G`, `T`, `P`) or binary
+//!   (`Ki`, `Mi`, `Gi`, `Ti`, `Pi`), optionally followed by a `B`, e.g. `<1.5TB` or `>=64GiB`.
+//! * `!<expression>` negates any of the above, e.g. `!~SATADOM` or `!<100G`.
+//! * `\<glob>` matches against a glob pattern starting with one of the above characters, e.g.
+//!   `\!important*` for a value starting with `!`, written as `'\!important*'` in TOML.
+//!
+//! A device without the property never matches the expression, negated or not. For example,
+//! `filter.ID_MODEL = "!*SATADOM*"` does not match a device without an `ID_MODEL` property, so
+//! with `filter_match = "all"` such a device is never selected.
+//!
+//! Besides their udev properties, disks have the `size` property with their size in bytes and
+//! the `block_size` property with their logical block size in bytes, as detected by the
+//! installer. Disks further have the following attributes read from sysfs, which unlike udev
+//! properties do not vary between controllers:
+//!
+//! * `rotational` and `removable`, either `1` or `0`
+//! * `transport`, one of `nvme`, `sata`, `sas`, `scsi`, `usb`, `virtio` or `mmc`
+//! * `physical_block_size`, in bytes
+//! * `wwn`, the world wide name as reported by the kernel
+//! * `is_multipath`, `1` if the disk is a path of a multipath device, `0` otherwise
+//! * `is_boot_medium` and `has_answer_partition`, `1` if the disk holds the installation ISO or
+//!   the answer file partition respectively, `0` otherwise
+
+use anyhow::{bail, format_err, Result};
+use glob::Pattern;
+use regex::Regex;
+use std::{collections::BTreeMap, str::FromStr};
+
+use crate::udevinfo::{DiskAttributes, UdevInfo, UdevProperties};
+use proxmox_installer_common::options::Disk;
+
+/// Property holding the size of a disk in bytes.
+pub const DISK_SIZE_PROPERTY: &str = "size";
+/// Property holding the logical block size of a disk in bytes.
+pub const DISK_BLOCK_SIZE_PROPERTY: &str = "block_size";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    fn holds(&self, value: f64, reference: f64) -> bool {
+        match self {
+            Self::Less => value < reference,
+            Self::LessOrEqual => value <= reference,
+            Self::Greater => value > reference,
+            Self::GreaterOrEqual => value >= reference,
+        }
+    }
+}
+
+/// A single filter expression, matched against one property of a device.
+#[derive(Clone, Debug)]
+pub enum FilterExpression {
+    Glob(Pattern),
+    Regex(Regex),
+    Compare(Comparison, f64),
+    Not(Box<FilterExpression>),
+}
+
+impl FilterExpression {
+    /// Matches the value of a property against the expression. Returns `None` if the device does
+    /// not have the property, which callers treat as not matching, also for negated expressions.
+    pub fn matches(&self, value: Option<&str>) -> Option<bool> {
+        let value = value?;
+        Some(match self {
+            Self::Glob(pattern) => pattern.matches(value),
+            Self::Regex(regex) => regex.is_match(value),
+            Self::Compare(comparison, reference) => value
+                .trim()
+                .parse::<f64>()
+                .is_ok_and(|value| comparison.holds(value, *reference)),
+            Self::Not(expression) => !expression.matches(Some(value))?,
+        })
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(glob) = s.strip_prefix('\\') {
+            return parse_glob(glob);
+        }
+        if let Some(expression) = s.strip_prefix('!') {
+            return Ok(Self::Not(Box::new(expression.parse()?)));
+        }
+        if let Some(regex) = s.strip_prefix('~') {
+            return Regex::new(regex)
+                .map(Self::Regex)
+                .map_err(|err| format_err!("invalid regular expression '{regex}': {err}"));
+        }
+
+        let comparison = [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+        ]
+        .into_iter()
+        .find_map(|(op, comparison)| Some((s.strip_prefix(op)?, comparison)));
+        if let Some((number, comparison)) = comparison {
+            return Ok(Self::Compare(comparison, parse_number(number)?));
+        }
+
+        parse_glob(s)
+    }
+}
+
+fn parse_glob(s: &str) -> Result<FilterExpression> {
+    Pattern::new(s)
+        .map(FilterExpression::Glob)
+        .map_err(|err| format_err!("invalid glob '{s}': {err}"))
+}
+
+/// Parses a number with an optional decimal or binary unit suffix, e.g. `400G` or `1.5TiB`.
+fn parse_number(s: &str) -> Result<f64> {
+    let s = s.trim();
+    let digits = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format_err!("invalid number '{s}' in comparison"))?;
+    let unit = unit.strip_suffix('B').unwrap_or(unit);
+    let factor: f64 = match unit {
+        "" => 1.,
+        "K" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "Ki" => 1024f64,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        _ => bail!("invalid unit '{unit}' in comparison '{s}'"),
+    };
+
+    Ok(number * factor)
+}
+
+/// Parses all values of a filter as expressions.
+pub fn parse_filter(filter: &BTreeMap<String, String>) -> Result<Vec<(&str, FilterExpression)>> {
+    filter
+        .iter()
+        .map(|(key, value)| {
+            value
+                .parse()
+                .map(|expression| (key.as_str(), expression))
+                .map_err(|err| format_err!("invalid filter for '{key}': {err}"))
+        })
+        .collect()
+}
+
+/// Returns the properties of all disks which can be used in filters, i.e. their udev properties
+/// along with their size, logical block size and attributes, keyed by the index of the disk.
+pub fn disk_properties(udev_info: &UdevInfo, disks: &[Disk]) -> BTreeMap<String, UdevProperties> {
+    let mut properties = udev_info.disks.clone();
+    for (index, attributes) in &udev_info.disk_attributes {
+        if let Some(props) = properties.get_mut(index) {
+            add_disk_attributes(props, attributes);
+        }
+    }
+    for disk in disks {
+        if let Some(props) = properties.get_mut(&disk.index) {
+            add_disk_properties(props, disk);
+        }
+    }
+    properties
+}
+
+/// Adds the size and logical block size of a disk to its properties.
+pub fn add_disk_properties(properties: &mut UdevProperties, disk: &Disk) {
+    // The size is stored in GiB, converted from the number of 512-byte sectors
+    let size = (disk.size * 1024f64.powi(3)).round() as u64;
+    properties.insert(DISK_SIZE_PROPERTY.to_owned(), size.to_string());
+    if let Some(block_size) = disk.block_size {
+        properties.insert(DISK_BLOCK_SIZE_PROPERTY.to_owned(), block_size.to_string());
+    }
+}
+
+/// Adds the attributes of a disk as recorded by the low-level installer to its properties.
+pub fn add_disk_attributes(properties: &mut UdevProperties, attributes: &DiskAttributes) {
+    let flag = |value: bool| if value { "1" } else { "0" }.to_owned();
+    let attributes = [
+        ("rotational", attributes.rotational.map(flag)),
+        ("transport", attributes.transport.map(|t| t.to_string())),
+        ("removable", attributes.removable.map(flag)),
+        (
+            "physical_block_size",
+            attributes.physical_block_size.map(|n| n.to_string()),
+        ),
+        ("wwn", attributes.wwn.clone()),
+        ("is_multipath", attributes.is_multipath.map(flag)),
+        ("is_boot_medium", attributes.is_boot_medium.map(flag)),
+        (
+            "has_answer_partition",
+            attributes.has_answer_partition.map(flag),
+        ),
+    ];
+
+    for (key, value) in attributes {
+        if let Some(value) = value {
+            properties.insert(key.to_owned(), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(expression: &str, value: &str) -> bool {
+        let expression: FilterExpression = expression.parse().unwrap();
+        expression.matches(Some(value)).unwrap()
+    }
+
+    #[test]
+    fn expressions() {
+        assert!(matches("Micron_*", "Micron_9300_MTFDHAL3T2TDR"));
+        assert!(!matches("Micron_*", "SAMSUNG MZ7KM240"));
+        assert!(matches("!*SATADOM*", "SAMSUNG MZ7KM240"));
+        assert!(!matches("!*SATADOM*", "SuperMicro SATADOM-SL 3ME"));
+
+        assert!(matches("~^/dev/nvme[0-9]+n1$", "/dev/nvme0n1"));
+        assert!(matches("~SATA", "SuperMicro SATADOM-SL 3ME"));
+        assert!(!matches("!~SATA", "SuperMicro SATADOM-SL 3ME"));
+
+        assert!(matches(">400G", "3200631791616"));
+        assert!(!matches(">400G", "240057409536"));
+        assert!(matches("<=240057409536", "240057409536"));
+        assert!(matches(">=223GiB", "240057409536"));
+        assert!(!matches(">=224GiB", "240057409536"));
+        assert!(matches("<1.5TB", "500107862016"));
+        assert!(matches(">=4096", "4096"));
+        assert!(!matches("<4096", "4096"));
+        assert!(matches("!<4096", "4096"));
+        assert!(!matches(">0", "not-a-number"));
+
+        assert!(matches("\\!important*", "!important disk"));
+        assert!(!matches("\\!important*", "important disk"));
+        assert!(matches("\\~*", "~backup"));
+        assert!(matches("\\>*", ">disk"));
+        assert!(matches("\\<*", "<disk"));
+        assert!(matches("\\\\*", "\\disk"));
+        assert!(matches("!\\~*", "disk"));
+
+        let expression: FilterExpression = "!>400G".parse().unwrap();
+        assert_eq!(expression.matches(None), None);
+
+        assert!("~(".parse::<FilterExpression>().is_err());
+        assert!(">400X".parse::<FilterExpression>().is_err());
+        assert!(">G".parse::<FilterExpression>().is_err());
+        assert!("[".parse::<FilterExpression>().is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Answer files including other answer files, e.g. to layer a role-specific overlay over a
+//! datacenter-wide one over a company-wide base:
+//!
+//! ```toml
+//! include = ["base.toml", "datacenter/fra1.toml", "roles/storage.toml"]
+//!
+//! [global]
+//! fqdn = "storage1.fra1.example.com"
+//! ```
+//!
+//! Paths are resolved relative to the location of the including answer file, i.e. the root of
+//! the ISO, the mounted answer partition or the URL the answer file was fetched from. Absolute
+//! paths and, for answer files fetched via HTTP, absolute URLs can be used too. Included files
+//! can be in any supported answer file format and can include further files themselves.
+//!
+//! The effective answer is built deterministically:
+//!
+//! 1. The included files are merged in the listed order, each over the previous ones. The
+//!    includes of an included file are resolved before it is merged.
+//! 2. The including answer file is merged last, so its own settings always take precedence.
+//!
+//! When merging a file over another, tables are merged recursively, while all other values,
+//! including arrays, replace the previous value. Filter tables are replaced as a whole, as
+//! combining two filters would change their meaning. The same rules apply to the host sections
+//! of [multi-host answer files](crate::multihost).
+
+use anyhow::{bail, format_err, Result};
+use log::info;
+use std::{
+    fmt, fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::answer::AnswerFormat;
+
+/// Key of the list of answer files to include.
+pub const INCLUDE_KEY: &str = "include";
+
+/// Location an answer file is read from, which its includes are resolved relative to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnswerSource {
+    /// A local file, e.g. on the ISO or the mounted answer partition.
+    File(PathBuf),
+    /// A file on a HTTP(S) server, along with the certificate fingerprint to pin, if any.
+    Url {
+        url: String,
+        fingerprint: Option<String>,
+    },
+}
+
+impl AnswerSource {
+    /// Returns the location of an answer file included from this one.
+    fn join(&self, include: &str) -> Self {
+        let fingerprint = match self {
+            Self::Url { url, fingerprint } if origin(url) == origin(include) => fingerprint.clone(),
+            _ => None,
+        };
+
+        if origin(include).is_some() {
+            return Self::Url {
+                url: include.to_owned(),
+                fingerprint,
+            };
+        }
+
+        match self {
+            Self::File(path) => Self::File(normalize_path(&match path.parent() {
+                Some(dir) => dir.join(include),
+                None => PathBuf::from(include),
+            })),
+            Self::Url { url, fingerprint } => {
+                let url = url.split(['?', '#']).next().unwrap_or_default();
+                let origin = origin(url).unwrap_or_default();
+                let path = match include.strip_prefix('/') {
+                    Some(absolute) => absolute.to_owned(),
+                    None => match url[origin.len()..].rsplit_once('/') {
+                        Some((dir, _)) => format!("{dir}/{include}"),
+                        None => include.to_owned(),
+                    },
+                };
+                let path = normalize_path(Path::new(&format!("/{path}")));
+                Self::Url {
+                    url: format!("{origin}{}", path.display()),
+                    fingerprint: fingerprint.clone(),
+                }
+            }
+        }
+    }
+
+    fn read(&self) -> Result<String> {
+        match self {
+            Self::File(path) => {
+                fs::read_to_string(path).map_err(|err| format_err!("failed to read {self}: {err}"))
+            }
+            Self::Url { url, fingerprint } => {
+                proxmox_installer_common::http::get(url, fingerprint.as_deref())
+                    .map_err(|err| format_err!("failed to fetch {self}: {err}"))
+            }
+        }
+    }
+
+    fn format(&self, content: &str) -> AnswerFormat {
+        let path = match self {
+            Self::File(path) => path.as_path(),
+            Self::Url { url, .. } => Path::new(url.split(['?', '#']).next().unwrap_or_default()),
+        };
+        AnswerFormat::from_path(path).unwrap_or_else(|| AnswerFormat::detect(content))
+    }
+}
+
+impl fmt::Display for AnswerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => write!(f, "'{}'", path.display()),
+            Self::Url { url, .. } => write!(f, "'{url}'"),
+        }
+    }
+}
+
+/// Resolves `.` and `..` components of a path lexically, so that includes of the same file are
+/// recognized as such.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !result.pop() {
+                    result.push(component);
+                }
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+/// Returns the scheme and authority of an URL, e.g. `https://example.com:8443`.
+fn origin(url: &str) -> Option<&str> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !matches!(scheme, "http" | "https") {
+        return None;
+    }
+    let end = rest.find('/').unwrap_or(rest.len());
+    Some(&url[..scheme.len() + 3 + end])
+}
+
+/// Resolves all includes of a TOML answer file read from the given source, returning the merged
+/// answer file. Returns `None` if the answer file does not include any other files.
+pub fn resolve_includes(content: &str, source: &AnswerSource) -> Result<Option<String>> {
+    let table: toml::Table = toml::from_str(content)
+        .map_err(|err| format_err!("failed to parse {source}: {}", err.message()))?;
+    if !table.contains_key(INCLUDE_KEY) {
+        return Ok(None);
+    }
+
+    let merged = load_includes(table, source, &mut vec![source.clone()])?;
+    Ok(Some(toml::to_string(&merged)?))
+}
+
+fn load_includes(
+    mut table: toml::Table,
+    source: &AnswerSource,
+    stack: &mut Vec<AnswerSource>,
+) -> Result<toml::Table> {
+    let Some(includes) = table.remove(INCLUDE_KEY) else {
+        return Ok(table);
+    };
+    let includes: Vec<String> = includes
+        .try_into()
+        .map_err(|_| format_err!("'{INCLUDE_KEY}' in {source} must be a list of paths"))?;
+
+    let mut merged = toml::Table::new();
+    for include in includes {
+        let included = source.join(&include);
+        if stack.contains(&included) {
+            let chain: Vec<String> = stack.iter().map(|source| source.to_string()).collect();
+            bail!(
+                "answer file {included} is included recursively ({} -> {included})",
+                chain.join(" -> ")
+            );
+        }
+
+        info!("Including answer file {included}");
+        let content = included.read()?;
+        let content = included
+            .format(&content)
+            .to_toml(&content)
+            .map_err(|diag| format_err!("failed to parse {included}: {diag}"))?;
+        let included_table: toml::Table = toml::from_str(&content)
+            .map_err(|err| format_err!("failed to parse {included}: {}", err.message()))?;
+
+        stack.push(included.clone());
+        let included_table = load_includes(included_table, &included, stack)?;
+        stack.pop();
+
+        merge(&mut merged, included_table);
+    }
+    merge(&mut merged, table);
+
+    Ok(merged)
+}
+
+/// Merges the overlay into the base table, according to the merge rules of answer files.
+pub(crate) fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) if key != "filter" => {
+                merge(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str, fingerprint: Option<&str>) -> AnswerSource {
+        AnswerSource::Url {
+            url: url.to_owned(),
+            fingerprint: fingerprint.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn resolves_relative_locations() {
+        let file = AnswerSource::File(PathBuf::from("/cdrom/answer.toml"));
+        assert_eq!(
+            file.join("roles/storage.toml"),
+            AnswerSource::File(PathBuf::from("/cdrom/roles/storage.toml"))
+        );
+        assert_eq!(
+            file.join("/mnt/answer/base.toml"),
+            AnswerSource::File(PathBuf::from("/mnt/answer/base.toml"))
+        );
+
+        let answer = url("https://example.com:8443/answers/get?node=1", Some("AB:CD"));
+        assert_eq!(
+            answer.join("base.yaml"),
+            url("https://example.com:8443/answers/base.yaml", Some("AB:CD"))
+        );
+        assert_eq!(
+            answer.join("/common/base.toml"),
+            url("https://example.com:8443/common/base.toml", Some("AB:CD"))
+        );
+        assert_eq!(
+            answer.join("https://other.example.com/base.toml"),
+            url("https://other.example.com/base.toml", None)
+        );
+        assert_eq!(
+            url("https://example.com", None).join("base.toml"),
+            url("https://example.com/base.toml", None)
+        );
+    }
+
+    #[test]
+    fn merges_includes_in_order() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "proxmox-answer-include-{}-{nanos}",
+            std::process::id()
+        ));
+        fs::create_dir(&dir).unwrap();
+        fs::create_dir(dir.join("roles")).unwrap();
+        fs::write(
+            dir.join("base.toml"),
+            "[global]\nkeyboard = \"de\"\ncountry = \"at\"\nroot_ssh_keys = [\"a\", \"b\"]\n\n\
+            [disk-setup]\nfilter.ID_MODEL = \"base*\"\nfilter_match = \"all\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("roles/storage.json"),
+            r#"{ "include": ["../dc.toml"], "global": { "root_ssh_keys": ["c"] },
+                "disk-setup": { "filter": { "ID_SERIAL": "S*" } } }"#,
+        )
+        .unwrap();
+        fs::write(dir.join("dc.toml"), "[global]\ncountry = \"de\"\n").unwrap();
+
+        let content = "include = [\"base.toml\", \"roles/storage.json\"]\n\n\
+            [global]\nkeyboard = \"en-us\"\n";
+        let merged =
+            resolve_includes(content, &AnswerSource::File(dir.join("answer.toml"))).unwrap();
+        let merged: toml::Table = toml::from_str(&merged.unwrap()).unwrap();
+
+        let expected: toml::Table = toml::from_str(
+            "[global]\nkeyboard = \"en-us\"\ncountry = \"de\"\nroot_ssh_keys = [\"c\"]\n\n\
+            [disk-setup]\nfilter.ID_SERIAL = \"S*\"\nfilter_match = \"all\"\n",
+        )
+        .unwrap();
+        assert_eq!(merged, expected);
+
+        fs::write(dir.join("dc.toml"), "include = [\"roles/storage.json\"]\n").unwrap();
+        let err = resolve_includes(content, &AnswerSource::File(dir.join("answer.toml")))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("is included recursively"), "{err}");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn answer_without_includes_is_unchanged() {
+        let source = AnswerSource::File(PathBuf::from("answer.toml"));
+        assert!(resolve_includes("[global]\n", &source).unwrap().is_none());
+    }
+
+    #[test]
+    fn reports_invalid_answer() {
+        let source = AnswerSource::File(PathBuf::from("answer.toml"));
+        let err = resolve_includes("[global\n", &source)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("failed to parse 'answer.toml'"), "{err}");
+    }
+}
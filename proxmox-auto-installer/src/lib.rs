@@ -1,5 +1,10 @@
 pub mod answer;
+pub mod filter;
+pub mod include;
 pub mod log;
+pub mod multihost;
 pub mod sysinfo;
+pub mod template;
 pub mod udevinfo;
 pub mod utils;
+pub mod validation;
@@ -0,0 +1,292 @@
+//! Multi-host answer files, consisting of a shared base and `[[host]]` sections overriding parts
+//! of it for individual machines:
+//!
+//! ```toml
+//! [[host]]
+//! match.dmi.system.serial = "ABC123"
+//! global.fqdn = "node1.example.com"
+//! network.cidr = "192.0.2.11/24"
+//! ```
+//!
+//! A host section matches on the DMI information of the system, as `dmi.<group>.<key>` like
+//! shown by `proxmox-auto-install-assistant system-info`, and/or on the MAC address of any of
+//! its network interfaces. All given criteria must match, and exactly one host section must
+//! match the system.
+//!
+//! The matching host section is merged over the base, following the same rules as
+//! [includes](crate::include): tables are merged recursively, all other values replace the value
+//! of the base. Filter tables are replaced as a whole, as combining the filters of the base and
+//! the host would change their meaning.
+
+use anyhow::{bail, format_err, Result};
+use log::info;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fmt};
+
+use crate::{include::merge, sysinfo::SysInfo};
+
+/// Name of the array of tables containing the host sections.
+pub const HOST_SECTION: &str = "host";
+
+/// Key of a host section containing its match criteria.
+const MATCH_KEY: &str = "match";
+
+/// Criteria a host section is selected by.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct HostMatch {
+    #[serde(default)]
+    dmi: BTreeMap<String, BTreeMap<String, String>>,
+    mac: Option<String>,
+}
+
+impl HostMatch {
+    /// Extracts the match criteria of a host section.
+    pub(crate) fn from_host(host: &toml::Table) -> Result<Self, String> {
+        let criteria: Self = host
+            .get(MATCH_KEY)
+            .ok_or_else(|| format!("host section without '{MATCH_KEY}' criteria"))?
+            .clone()
+            .try_into()
+            .map_err(|err: toml::de::Error| format!("invalid '{MATCH_KEY}': {}", err.message()))?;
+
+        if criteria.mac.is_none() && criteria.dmi.values().all(|keys| keys.is_empty()) {
+            return Err(format!("'{MATCH_KEY}' must contain at least one criterion"));
+        }
+        Ok(criteria)
+    }
+
+    fn matches(&self, identity: &HostIdentity) -> bool {
+        let dmi_matches = self.dmi.iter().all(|(group, keys)| {
+            keys.iter().all(|(key, expected)| {
+                identity
+                    .dmi
+                    .get(group)
+                    .and_then(|values| values.get(key))
+                    .is_some_and(|value| value.eq_ignore_ascii_case(expected))
+            })
+        });
+
+        let mac_matches = self.mac.as_ref().is_none_or(|expected| {
+            identity
+                .macs
+                .iter()
+                .any(|mac| mac.eq_ignore_ascii_case(expected))
+        });
+
+        dmi_matches && mac_matches
+    }
+}
+
+impl fmt::Display for HostMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut criteria = Vec::new();
+        for (group, keys) in &self.dmi {
+            for (key, value) in keys {
+                criteria.push(format!("dmi.{group}.{key} = '{value}'"));
+            }
+        }
+        if let Some(mac) = &self.mac {
+            criteria.push(format!("mac = '{mac}'"));
+        }
+        f.write_str(&criteria.join(", "))
+    }
+}
+
+/// Identifying information of a system, which host sections are matched against.
+#[derive(Clone, Debug, Default)]
+pub struct HostIdentity {
+    /// DMI information by group, e.g. `system`, and key, e.g. `serial`.
+    pub dmi: BTreeMap<String, BTreeMap<String, String>>,
+    /// MAC addresses of all network interfaces.
+    pub macs: Vec<String>,
+}
+
+impl HostIdentity {
+    /// Gathers the identifying information of the running system.
+    pub fn get() -> Result<Self> {
+        Self::from_sysinfo(&serde_json::to_value(SysInfo::get()?)?)
+    }
+
+    /// Extracts the identifying information from the system information as reported by
+    /// [`SysInfo`], e.g. as recorded in a hardware profile.
+    pub fn from_sysinfo(info: &serde_json::Value) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Netdev {
+            mac: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Info {
+            dmi: BTreeMap<String, BTreeMap<String, String>>,
+            network_interfaces: Vec<Netdev>,
+        }
+
+        let info = Info::deserialize(info)
+            .map_err(|err| format_err!("failed to parse system information: {err}"))?;
+
+        Ok(Self {
+            dmi: info.dmi,
+            macs: info
+                .network_interfaces
+                .into_iter()
+                .map(|netdev| netdev.mac)
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for HostIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let system = self.dmi.get("system");
+        let value = |key| {
+            system
+                .and_then(|values| values.get(key))
+                .map(String::as_str)
+        };
+
+        write!(
+            f,
+            "serial '{}', UUID '{}', MAC addresses '{}'",
+            value("serial").unwrap_or("unknown"),
+            value("uuid").unwrap_or("unknown"),
+            self.macs.join("', '")
+        )
+    }
+}
+
+/// Resolves a multi-host TOML answer file for the system with the given identity, by merging the
+/// matching host section over the base. Returns `None` if the answer file has no host sections,
+/// in which case the identity is not retrieved at all.
+pub fn resolve_host(
+    content: &str,
+    identity: impl FnOnce() -> Result<HostIdentity>,
+) -> Result<Option<String>> {
+    let mut base: toml::Table =
+        toml::from_str(content).map_err(|err| format_err!("failed to parse answer file: {err}"))?;
+
+    let Some(hosts) = base.remove(HOST_SECTION) else {
+        return Ok(None);
+    };
+    let hosts: Vec<toml::Table> = hosts
+        .try_into()
+        .map_err(|_| format_err!("'{HOST_SECTION}' must be an array of tables"))?;
+
+    let identity = identity()?;
+    let mut matching = Vec::new();
+    for (index, host) in hosts.iter().enumerate() {
+        let criteria =
+            HostMatch::from_host(host).map_err(|err| format_err!("host #{}: {err}", index + 1))?;
+        if criteria.matches(&identity) {
+            matching.push((index, criteria));
+        }
+    }
+
+    match &matching[..] {
+        [(index, criteria)] => {
+            info!("Using answer for host #{} ({criteria})", index + 1);
+            Ok(Some(toml::to_string(&apply_host(&base, &hosts[*index]))?))
+        }
+        [] => bail!("no host section of the answer file matches this system ({identity})"),
+        _ => {
+            let numbers: Vec<String> = matching
+                .iter()
+                .map(|(index, _)| format!("#{}", index + 1))
+                .collect();
+            bail!(
+                "host sections {} of the answer file all match this system, only one may match",
+                numbers.join(", ")
+            )
+        }
+    }
+}
+
+/// Returns the answer file for a single host, by merging its section over the base.
+pub(crate) fn apply_host(base: &toml::Table, host: &toml::Table) -> toml::Table {
+    let mut result = base.clone();
+    let mut overlay = host.clone();
+    overlay.remove(MATCH_KEY);
+    merge(&mut result, overlay);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANSWER: &str = r#"
+[global]
+fqdn = "base.example.com"
+
+[disk-setup]
+filter.ID_MODEL = "base*"
+filter_match = "all"
+
+[[host]]
+match.dmi.system.serial = "ABC123"
+global.fqdn = "node1.example.com"
+disk-setup.filter.ID_SERIAL = "node1*"
+
+[[host]]
+match.mac = "BC:24:11:00:00:02"
+global.fqdn = "node2.example.com"
+"#;
+
+    fn identity(serial: &str, mac: &str) -> HostIdentity {
+        HostIdentity {
+            dmi: BTreeMap::from([(
+                "system".to_owned(),
+                BTreeMap::from([("serial".to_owned(), serial.to_owned())]),
+            )]),
+            macs: vec![mac.to_owned()],
+        }
+    }
+
+    #[test]
+    fn selects_and_merges_matching_host() {
+        let resolved = resolve_host(ANSWER, || Ok(identity("ABC123", "bc:24:11:00:00:01")))
+            .unwrap()
+            .unwrap();
+        let resolved: toml::Table = toml::from_str(&resolved).unwrap();
+
+        assert!(!resolved.contains_key(HOST_SECTION));
+        assert_eq!(
+            resolved["global"]["fqdn"].as_str(),
+            Some("node1.example.com")
+        );
+
+        let disks = resolved["disk-setup"].as_table().unwrap();
+        assert_eq!(disks["filter_match"].as_str(), Some("all"));
+        let filter = disks["filter"].as_table().unwrap();
+        assert_eq!(filter.keys().collect::<Vec<_>>(), vec!["ID_SERIAL"]);
+
+        let resolved = resolve_host(ANSWER, || Ok(identity("XYZ", "bc:24:11:00:00:02")))
+            .unwrap()
+            .unwrap();
+        assert!(resolved.contains("node2.example.com"));
+    }
+
+    #[test]
+    fn fails_without_unique_match() {
+        let err = resolve_host(ANSWER, || Ok(identity("XYZ", "bc:24:11:00:00:01"))).unwrap_err();
+        assert!(err.to_string().starts_with("no host section"));
+
+        let err = resolve_host(ANSWER, || Ok(identity("ABC123", "bc:24:11:00:00:02"))).unwrap_err();
+        assert!(err.to_string().contains("#1, #2"));
+
+        let content = "[global]\n[[host]]\nglobal.fqdn = \"a.example.com\"\n";
+        let err = resolve_host(content, || Ok(HostIdentity::default())).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "host #1: host section without 'match' criteria"
+        );
+    }
+
+    #[test]
+    fn single_host_answer_is_unchanged() {
+        let content = "[global]\nfqdn = \"a.example.com\"\n";
+        assert!(resolve_host(content, || Ok(HostIdentity::default()))
+            .unwrap()
+            .is_none());
+    }
+}
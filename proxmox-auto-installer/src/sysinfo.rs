@@ -4,12 +4,13 @@ use proxmox_installer_common::{
     sysinfo::SystemDMI,
     RUNTIME_DIR,
 };
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::{fs, io, path::PathBuf};
 
 use crate::utils::get_nic_list;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SysInfo {
     product: ProductConfig,
     iso: IsoInfo,
@@ -43,7 +44,7 @@ impl SysInfo {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 struct NetdevWithMac {
     /// The network link name
     pub link: String,
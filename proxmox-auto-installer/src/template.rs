@@ -0,0 +1,552 @@
+//! Answer file templates, which are rendered with the information of the system to install before
+//! the answer file is parsed. This allows using a single answer file for many systems, e.g.:
+//!
+//! ```toml
+//! template = true
+//!
+//! [global]
+//! fqdn = "pve-{{ dmi.system.serial | lower | last(6) }}.example.com"
+//! ```
+//!
+//! Variables refer to the system information as shown by
+//! `proxmox-auto-install-assistant system-info`, separated by dots, with list entries selected
+//! by their index. For example `dmi.system.serial` for the serial number of the system,
+//! `network_interfaces.0.mac` for the MAC address of the first network interface or
+//! `product.product` for the short name of the product, like `pve`.
+//!
+//! The value of an expression can be transformed by helpers, each separated by `|`:
+//!
+//! - `lower` and `upper` change the case of the value,
+//! - `replace('from', 'to')` replaces all occurrences of `from` with `to`,
+//! - `last(n)` keeps only the last `n` characters of the value.
+//!
+//! Templates have to be written in TOML and explicitly marked by setting `template = true` at the
+//! top level, any other answer file is used as-is. Expressions are only evaluated within string
+//! values, with the rendered value being escaped as needed. A literal `{{` can be written as
+//! `{{ '{{' }}`.
+
+use serde_json::Value;
+use std::{fmt, ops::Range};
+use toml_edit::{DocumentMut, ImDocument, Item, TableLike};
+
+use crate::validation::Diagnostic;
+
+/// Top-level key marking an answer file as template, removed when rendering it.
+pub const TEMPLATE_KEY: &str = "template";
+
+const EXPRESSION_START: &str = "{{";
+const EXPRESSION_END: &str = "}}";
+
+/// Returns whether the content is a TOML answer file marked as template.
+pub fn is_template(content: &str) -> bool {
+    ImDocument::parse(content).is_ok_and(|doc| {
+        doc.get(TEMPLATE_KEY)
+            .and_then(Item::as_bool)
+            .unwrap_or_default()
+    })
+}
+
+/// Checks the syntax of all template expressions, without rendering them.
+pub fn check(content: &str) -> Result<(), Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    for string in string_values(content)? {
+        if let Err(errors) = Template::parse(&string.value) {
+            diagnostics.extend(
+                errors
+                    .into_iter()
+                    .map(|err| string.diagnostic(content, err)),
+            );
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(())
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Renders the template with the given system information, as reported by
+/// [`SysInfo`](crate::sysinfo::SysInfo). All problems found are reported at once.
+pub fn render(content: &str, sysinfo: &Value) -> Result<String, Vec<Diagnostic>> {
+    let mut replacements = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for string in string_values(content)? {
+        let template = match Template::parse(&string.value) {
+            Ok(template) => template,
+            Err(errors) => {
+                diagnostics.extend(
+                    errors
+                        .into_iter()
+                        .map(|err| string.diagnostic(content, err)),
+                );
+                continue;
+            }
+        };
+
+        let mut rendered = String::with_capacity(string.value.len());
+        for segment in template.segments {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Expression { offset, expression } => match expression.evaluate(sysinfo) {
+                    Ok(value) => rendered.push_str(&value),
+                    Err(err) => diagnostics.push(string.diagnostic(content, (offset, err))),
+                },
+            }
+        }
+        replacements.push((string.span, rendered));
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(diagnostics);
+    }
+
+    let mut result = content.to_owned();
+    for (span, rendered) in replacements.into_iter().rev() {
+        let value = toml_edit::Value::from(rendered).decorated("", "");
+        result.replace_range(span, &value.to_string());
+    }
+
+    let mut doc: DocumentMut = result.parse().map_err(|err| {
+        vec![Diagnostic::new(
+            "",
+            format!("rendered template is invalid: {err}"),
+        )]
+    })?;
+    doc.remove(TEMPLATE_KEY);
+    Ok(doc.to_string())
+}
+
+/// A string value of the answer file, which may contain template expressions.
+struct StringValue {
+    /// Position of the value in the answer file, including its quotes.
+    span: Range<usize>,
+    value: String,
+}
+
+impl StringValue {
+    /// Creates a diagnostic for a problem with an expression at the given offset of the value,
+    /// positioned at the same expression within the answer file.
+    fn diagnostic(&self, content: &str, (offset, message): (usize, String)) -> Diagnostic {
+        let index = self.value[..offset].matches(EXPRESSION_START).count();
+        let position = content[self.span.clone()]
+            .match_indices(EXPRESSION_START)
+            .nth(index)
+            .map(|(pos, _)| self.span.start + pos)
+            .unwrap_or(self.span.start);
+        diagnostic_at(content, position, message)
+    }
+}
+
+/// Collects all string values containing template expressions, in the order of the answer file.
+fn string_values(content: &str) -> Result<Vec<StringValue>, Vec<Diagnostic>> {
+    let doc = ImDocument::parse(content).map_err(|err| {
+        let offset = err.span().map(|span| span.start).unwrap_or_default();
+        vec![diagnostic_at(
+            content,
+            offset,
+            err.message().trim().to_owned(),
+        )]
+    })?;
+
+    let mut strings = Vec::new();
+    collect_table(doc.as_table(), &mut strings);
+    strings.sort_by_key(|string| string.span.start);
+    Ok(strings)
+}
+
+fn collect_table(table: &dyn TableLike, strings: &mut Vec<StringValue>) {
+    for (_, item) in table.iter() {
+        match item {
+            Item::Value(value) => collect_value(value, strings),
+            Item::Table(table) => collect_table(table, strings),
+            Item::ArrayOfTables(tables) => {
+                for table in tables.iter() {
+                    collect_table(table, strings);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn collect_value(value: &toml_edit::Value, strings: &mut Vec<StringValue>) {
+    match value {
+        toml_edit::Value::String(string) if string.value().contains(EXPRESSION_START) => {
+            if let Some(span) = string.span() {
+                strings.push(StringValue {
+                    span,
+                    value: string.value().clone(),
+                });
+            }
+        }
+        toml_edit::Value::Array(values) => {
+            for value in values.iter() {
+                collect_value(value, strings);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => collect_table(table, strings),
+        _ => {}
+    }
+}
+
+struct Template<'a> {
+    segments: Vec<Segment<'a>>,
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Expression {
+        /// Byte offset of the expression in the template, used for reporting problems.
+        offset: usize,
+        expression: Expression,
+    },
+}
+
+impl<'a> Template<'a> {
+    /// Parses all expressions of the template, returning the offset and message of each problem.
+    fn parse(content: &'a str) -> Result<Self, Vec<(usize, String)>> {
+        let mut segments = Vec::new();
+        let mut diagnostics = Vec::new();
+
+        let mut rest = 0;
+        while let Some(start) = content[rest..].find(EXPRESSION_START).map(|pos| rest + pos) {
+            segments.push(Segment::Text(&content[rest..start]));
+
+            let inner = start + EXPRESSION_START.len();
+            let Some(end) = content[inner..].find(EXPRESSION_END).map(|pos| inner + pos) else {
+                let err = format!("unterminated template expression, missing '{EXPRESSION_END}'");
+                diagnostics.push((start, err));
+                rest = content.len();
+                break;
+            };
+
+            match Expression::parse(&content[inner..end]) {
+                Ok(expression) => segments.push(Segment::Expression {
+                    offset: start,
+                    expression,
+                }),
+                Err(err) => diagnostics.push((start, err)),
+            }
+            rest = end + EXPRESSION_END.len();
+        }
+        segments.push(Segment::Text(&content[rest..]));
+
+        if diagnostics.is_empty() {
+            Ok(Self { segments })
+        } else {
+            Err(diagnostics)
+        }
+    }
+}
+
+/// A single template expression, consisting of a variable or string and the helpers applied to
+/// its value.
+#[derive(Debug, PartialEq)]
+struct Expression {
+    source: Source,
+    helpers: Vec<Helper>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Source {
+    Variable(String),
+    Literal(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Helper {
+    Lower,
+    Upper,
+    Replace(String, String),
+    Last(usize),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    String(String),
+    Pipe,
+    Open,
+    Close,
+    Comma,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Word(word) => write!(f, "'{word}'"),
+            Self::String(value) => write!(f, "string '{value}'"),
+            Self::Pipe => f.write_str("'|'"),
+            Self::Open => f.write_str("'('"),
+            Self::Close => f.write_str("')'"),
+            Self::Comma => f.write_str("','"),
+        }
+    }
+}
+
+impl Expression {
+    fn parse(input: &str) -> Result<Self, String> {
+        let mut tokens = tokenize(input)?.into_iter().peekable();
+
+        let source = match tokens.next() {
+            Some(Token::Word(path)) => Source::Variable(path),
+            Some(Token::String(value)) => Source::Literal(value),
+            Some(token) => return Err(format!("expected a variable or string, found {token}")),
+            None => return Err("empty template expression".to_owned()),
+        };
+
+        let mut helpers = Vec::new();
+        while let Some(token) = tokens.next() {
+            if token != Token::Pipe {
+                return Err(format!("expected '|' or '{EXPRESSION_END}', found {token}"));
+            }
+            let name = match tokens.next() {
+                Some(Token::Word(name)) => name,
+                Some(token) => return Err(format!("expected a helper name, found {token}")),
+                None => return Err("expected a helper name after '|'".to_owned()),
+            };
+
+            let mut args = Vec::new();
+            if tokens.next_if_eq(&Token::Open).is_some() {
+                loop {
+                    match tokens.next() {
+                        Some(Token::Close) if args.is_empty() => break,
+                        Some(Token::Word(arg) | Token::String(arg)) => args.push(arg),
+                        Some(token) => {
+                            return Err(format!("expected an argument to '{name}', found {token}"))
+                        }
+                        None => return Err(format!("unterminated arguments to '{name}'")),
+                    }
+                    match tokens.next() {
+                        Some(Token::Comma) => {}
+                        Some(Token::Close) => break,
+                        _ => return Err(format!("expected ',' or ')' in arguments to '{name}'")),
+                    }
+                }
+            }
+            helpers.push(Helper::new(&name, args)?);
+        }
+
+        Ok(Self { source, helpers })
+    }
+
+    fn evaluate(&self, sysinfo: &Value) -> Result<String, String> {
+        let mut value = match &self.source {
+            Source::Variable(path) => lookup(sysinfo, path)?,
+            Source::Literal(value) => value.clone(),
+        };
+        for helper in &self.helpers {
+            value = helper.apply(value);
+        }
+        Ok(value)
+    }
+}
+
+impl Helper {
+    fn new(name: &str, args: Vec<String>) -> Result<Self, String> {
+        let helper =
+            match (name, &args[..]) {
+                ("lower", []) => Self::Lower,
+                ("upper", []) => Self::Upper,
+                ("replace", [from, to]) => Self::Replace(from.clone(), to.clone()),
+                ("last", [count]) => Self::Last(count.parse().map_err(|_| {
+                    format!("'last' expects a number of characters, got '{count}'")
+                })?),
+                ("lower" | "upper", _) => return Err(format!("'{name}' takes no arguments")),
+                ("replace", _) => return Err("'replace' takes exactly two arguments".to_owned()),
+                ("last", _) => return Err("'last' takes exactly one argument".to_owned()),
+                _ => return Err(format!("unknown helper '{name}'")),
+            };
+        Ok(helper)
+    }
+
+    fn apply(&self, value: String) -> String {
+        match self {
+            Self::Lower => value.to_lowercase(),
+            Self::Upper => value.to_uppercase(),
+            Self::Replace(from, to) => value.replace(from.as_str(), to),
+            Self::Last(count) => {
+                let skip = value.chars().count().saturating_sub(*count);
+                value.chars().skip(skip).collect()
+            }
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '|' => Token::Pipe,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '\'' | '"' => {
+                let value: String = chars
+                    .by_ref()
+                    .map(|(_, c)| c)
+                    .take_while(|next| *next != c)
+                    .collect();
+                if input[start + 1..].find(c).is_none() {
+                    return Err("unterminated string in template expression".to_owned());
+                }
+                Token::String(value)
+            }
+            c if is_word_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some((pos, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    end = pos + c.len_utf8();
+                }
+                Token::Word(input[start..end].to_owned())
+            }
+            c => return Err(format!("unexpected character '{c}' in template expression")),
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Looks up a dotted variable path in the system information.
+fn lookup(sysinfo: &Value, path: &str) -> Result<String, String> {
+    let mut value = sysinfo;
+    for key in path.split('.') {
+        let next = match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(list) => key.parse::<usize>().ok().and_then(|index| list.get(index)),
+            _ => None,
+        };
+        value = next.ok_or_else(|| format!("unknown variable '{path}'"))?;
+    }
+
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        Value::Null => Err(format!("variable '{path}' has no value")),
+        Value::Array(_) | Value::Object(_) => {
+            Err(format!("variable '{path}' is not a single value"))
+        }
+    }
+}
+
+fn diagnostic_at(content: &str, offset: usize, message: String) -> Diagnostic {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+
+    let mut diag = Diagnostic::new("", message);
+    diag.line = Some(before.matches('\n').count() + 1);
+    diag.column = Some(before[line_start..].chars().count() + 1);
+    diag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sysinfo() -> Value {
+        json!({
+            "product": { "product": "pve", "fullname": "Proxmox VE" },
+            "dmi": { "system": { "serial": "SN-00AB12CD", "name": "Standard PC" } },
+            "network_interfaces": [
+                { "link": "eno1", "mac": "BC:24:11:00:00:01" },
+                { "link": "eno2", "mac": "BC:24:11:00:00:02" },
+            ],
+        })
+    }
+
+    #[test]
+    fn renders_variables_and_helpers() {
+        let content = r#"template = true
+
+[global]
+# {{ not rendered in comments }}
+fqdn = "{{ product.product }}-{{dmi.system.serial|lower|last(6)}}.example.com"
+mac = '{{ network_interfaces.1.mac | replace(":", "") | lower }}'
+name = "{{ dmi.system.name | replace(\" \", '-') | upper }}"
+literal = "{{ '{{' }} not an expression }}"
+list = ["{{ product.fullname }}", 1]
+"#;
+
+        assert_eq!(
+            render(content, &sysinfo()).unwrap(),
+            r#"
+[global]
+# {{ not rendered in comments }}
+fqdn = "pve-ab12cd.example.com"
+mac = "bc2411000002"
+name = "STANDARD-PC"
+literal = "{{ not an expression }}"
+list = ["Proxmox VE", 1]
+"#
+        );
+    }
+
+    #[test]
+    fn escapes_rendered_values() {
+        let sysinfo = json!({ "dmi": { "system": { "name": "a\"b\\c\nd = 1" } } });
+        let content = "template = true\n[global]\nname = \"{{ dmi.system.name }}\"\n";
+        let rendered = render(content, &sysinfo).unwrap();
+
+        let doc: toml::Table = toml::from_str(&rendered).unwrap();
+        assert_eq!(doc.keys().collect::<Vec<_>>(), vec!["global"]);
+        assert_eq!(doc["global"].as_table().unwrap().len(), 1);
+        assert_eq!(doc["global"]["name"].as_str(), Some("a\"b\\c\nd = 1"));
+    }
+
+    #[test]
+    fn reports_problems_with_position() {
+        let content = "a = \"{{ dmi.system.uuid }}\"\nb = \"x {{ dmi.system }}\"\n";
+        let diagnostics = render(content, &sysinfo()).unwrap_err();
+        let messages: Vec<String> = diagnostics.iter().map(|diag| diag.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 1, column 6: unknown variable 'dmi.system.uuid'",
+                "line 2, column 8: variable 'dmi.system' is not a single value",
+            ]
+        );
+
+        let content =
+            "a = \"{{ dmi.system.serial | last }}\"\n\nb = \"{{ x | trim }}\"\nc = \"{{\"";
+        let diagnostics = check(content).unwrap_err();
+        let messages: Vec<String> = diagnostics.iter().map(|diag| diag.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 1, column 6: 'last' takes exactly one argument",
+                "line 3, column 6: unknown helper 'trim'",
+                "line 4, column 6: unterminated template expression, missing '}}'",
+            ]
+        );
+    }
+
+    #[test]
+    fn template_marker_is_removed() {
+        let content = "template = true\n[global]\nfqdn = \"pve.example.com\"\n";
+        assert!(is_template(content));
+        assert_eq!(
+            render(content, &Value::Null).unwrap(),
+            "[global]\nfqdn = \"pve.example.com\"\n"
+        );
+    }
+
+    #[test]
+    fn requires_template_marker() {
+        assert!(!is_template("[global]\nroot_password = \"{{secret}}\"\n"));
+        assert!(!is_template(
+            "template = false\n[global]\nfqdn = \"{{ x }}\"\n"
+        ));
+        assert!(!is_template("{ \"template\": true }"));
+    }
+}
@@ -1,4 +1,5 @@
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::BTreeMap;
 
 /// Uses a BTreeMap to have the keys sorted
@@ -8,4 +9,113 @@ pub type UdevProperties = BTreeMap<String, String>;
 pub struct UdevInfo {
     pub disks: BTreeMap<String, UdevProperties>,
     pub nics: BTreeMap<String, UdevProperties>,
+    /// Attributes of the disks as recorded by the low-level installer, keyed by the index of the
+    /// disk.
+    #[serde(default)]
+    pub disk_attributes: BTreeMap<String, DiskAttributes>,
+}
+
+/// Transport over which a disk is attached to the system.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskTransport {
+    Nvme,
+    Sata,
+    Sas,
+    Scsi,
+    Usb,
+    Virtio,
+    Mmc,
+}
+
+serde_plain::derive_display_from_serialize!(DiskTransport);
+
+/// Attributes of a disk as computed by the low-level installer from sysfs. Unlike the udev
+/// properties, they are the same regardless of the controller the disk is attached to.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct DiskAttributes {
+    /// Whether the disk is a rotational one, i.e. a HDD.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub rotational: Option<bool>,
+    /// Transport over which the disk is attached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<DiskTransport>,
+    /// Whether the disk is removable, e.g. a SD card.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub removable: Option<bool>,
+    /// Physical block size in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub physical_block_size: Option<u64>,
+    /// World wide name of the disk, as reported by the kernel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wwn: Option<String>,
+    /// Whether the disk is one of the paths of a multipath device.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub is_multipath: Option<bool>,
+    /// Whether the disk holds the installation ISO the system was booted from.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub is_boot_medium: Option<bool>,
+    /// Whether the disk holds the partition the answer file is read from.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_flag",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub has_answer_partition: Option<bool>,
+}
+
+impl DiskAttributes {
+    /// Returns the reason why nothing must be installed to the disk, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `allow_removable` - Whether removable disks are acceptable installation targets.
+    pub fn installation_blocker(&self, allow_removable: bool) -> Option<&'static str> {
+        if self.is_boot_medium == Some(true) {
+            Some("it holds the installation medium")
+        } else if self.has_answer_partition == Some(true) {
+            Some("it holds the answer file partition")
+        } else if self.removable == Some(true) && !allow_removable {
+            Some("it is removable and 'allow_removable' is not set")
+        } else {
+            None
+        }
+    }
+}
+
+/// The low-level installer records flags as integers, like in the runtime environment.
+fn deserialize_flag<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(u32),
+    }
+
+    Ok(
+        Option::<Flag>::deserialize(deserializer)?.map(|flag| match flag {
+            Flag::Bool(value) => value,
+            Flag::Int(value) => value != 0,
+        }),
+    )
 }
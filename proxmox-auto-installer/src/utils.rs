@@ -1,18 +1,29 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, format_err, Context, Result};
 use clap::ValueEnum;
-use glob::Pattern;
 use log::info;
+use schemars::{generate::SchemaSettings, JsonSchema, Schema};
 use std::{collections::BTreeMap, process::Command};
 
 use crate::{
-    answer::{self, Answer, FirstBootHookSourceMode},
-    udevinfo::UdevInfo,
+    answer::{self, Answer, NicSelection},
+    filter::{disk_properties, parse_filter},
+    udevinfo::{DiskTransport, UdevInfo, UdevProperties},
+    validation::{
+        check_email_and_root_password_settings, check_first_boot_settings, check_locale_settings,
+        Diagnostic,
+    },
 };
 use proxmox_installer_common::{
-    options::{email_validate, FsType, NetworkOptions, ZfsChecksumOption, ZfsCompressOption},
+    cmdline::CmdlineNetwork,
+    disk_checks::check_disk_wipe_method,
+    options::{
+        BondOptions, Disk, DiskWipeMethod, ExistingDataPolicy, FsType, NetworkOptions,
+        ZfsChecksumOption, ZfsCompressOption,
+    },
     setup::{
-        InstallBtrfsOption, InstallConfig, InstallFirstBootSetup, InstallRootPassword,
-        InstallZfsOption, LocaleInfo, RuntimeInfo, SetupInfo,
+        InstallBondOption, InstallBridgeOption, InstallBtrfsOption, InstallConfig,
+        InstallFirstBootSetup, InstallRootPassword, InstallZfsOption, LocaleInfo, NetworkInfo,
+        RuntimeInfo, SetupInfo,
     },
 };
 use serde::{Deserialize, Serialize};
@@ -29,17 +40,175 @@ fn get_network_settings(
 
     // Always use the FQDN from the answer file
     network_options.fqdn = answer.global.fqdn.clone();
+    network_options.search_domains = answer.network.search_domains.clone();
+    network_options.mtu = answer.network.mtu;
+
+    if answer.network.bridge.is_some() && !setup_info.config.product.bridged_network() {
+        bail!(
+            "bridge settings are not supported by {}, as it does not use a bridge",
+            setup_info.config.fullname
+        );
+    }
+    network_options.bridge = answer.network.bridge.clone();
 
     if let answer::NetworkSettings::Manual(settings) = &answer.network.network_settings {
         network_options.address = settings.cidr.clone();
-        network_options.dns_server = settings.dns;
         network_options.gateway = settings.gateway;
-        network_options.ifname = get_single_udev_index(&settings.filter, &udev_info.nics)?;
+        network_options.address6 = settings.cidr6.clone();
+        network_options.gateway6 = settings.gateway6;
+        network_options.dns_servers = settings.dns.clone();
+        match &settings.interfaces {
+            answer::NetworkInterfaces::Single(filter) => {
+                network_options.ifname = get_single_udev_index(filter, &udev_info.nics)?;
+            }
+            answer::NetworkInterfaces::Selected { filter, selection } => {
+                let candidates = match filter {
+                    Some(filter) => get_matched_udev_indexes(filter, &udev_info.nics, false)
+                        .context("failed to select network interface")?,
+                    None => udev_info.nics.keys().cloned().collect(),
+                };
+                network_options.ifname = select_nic(selection, &candidates, &runtime_info.network)?;
+                info!(
+                    "Network interface '{}' selected by policy '{selection}'",
+                    network_options.ifname
+                );
+            }
+            answer::NetworkInterfaces::Bond(bond) => {
+                let filter_match = bond
+                    .filter_match
+                    .clone()
+                    .unwrap_or(answer::FilterMatch::Any);
+                let slaves = get_matched_udev_indexes(
+                    &bond.filter,
+                    &udev_info.nics,
+                    filter_match == answer::FilterMatch::All,
+                )
+                .context("failed to select bond interfaces")?;
+
+                network_options.ifname = slaves[0].clone();
+                network_options.bond = Some(BondOptions {
+                    slaves,
+                    mode: bond.mode,
+                    xmit_hash_policy: bond.xmit_hash_policy,
+                    miimon: bond.miimon.unwrap_or(BondOptions::DEFAULT_MIIMON),
+                });
+            }
+        }
+
+        network_options.vlan = settings.vlan;
+    } else if let answer::NetworkSettings::FromCmdline = &answer.network.network_settings {
+        let cmdline = CmdlineNetwork::parse(&runtime_info.kernel_cmdline)
+            .map_err(|err| {
+                format_err!("invalid network configuration on kernel commandline: {err}")
+            })?
+            .ok_or_else(|| {
+                format_err!("no static network configuration found on the kernel commandline")
+            })?;
+
+        // The interface the installation environment configured the address on, which might be
+        // a VLAN interface
+        let ifname = runtime_info
+            .network
+            .interfaces
+            .values()
+            .find(|iface| {
+                iface
+                    .addresses
+                    .as_ref()
+                    .is_some_and(|addresses| addresses.contains(&cmdline.address))
+            })
+            .map(|iface| iface.name.clone())
+            .or(cmdline.interface)
+            .filter(|name| runtime_info.network.interfaces.contains_key(name))
+            .ok_or_else(|| {
+                format_err!(
+                    "no network interface found for address {} from the kernel commandline",
+                    cmdline.address
+                )
+            })?;
+        (network_options.ifname, network_options.vlan) =
+            match runtime_info.network.vlan_parent(&ifname) {
+                Some((parent, vlan)) => (parent.name.clone(), Some(vlan)),
+                None => (ifname, None),
+            };
+
+        network_options.address = cmdline.address;
+        network_options.gateway = cmdline.gateway;
+        network_options.address6 = None;
+        network_options.gateway6 = None;
+        network_options.dns_servers = cmdline.dns_servers;
+    }
+    match &network_options.bond {
+        Some(bond) => info!(
+            "Network interfaces used are '{}', bonded in '{}' mode",
+            bond.slaves.join("', '"),
+            bond.mode
+        ),
+        None => info!("Network interface used is '{}'", &network_options.ifname),
+    }
+    if let Some(vlan) = network_options.vlan {
+        info!("Management traffic is tagged with VLAN {vlan}");
+    }
+    if let Some(mtu) = network_options.mtu {
+        info!("Management interface MTU is {mtu}");
+    }
+    if let Some(bridge) = &network_options.bridge {
+        if bridge.vlan_aware {
+            info!(
+                "Management bridge is '{}', VLAN-aware for VLANs {}",
+                bridge.name, bridge.vids
+            );
+        } else {
+            info!("Management bridge is '{}'", bridge.name);
+        }
     }
-    info!("Network interface used is '{}'", &network_options.ifname);
     Ok(network_options)
 }
 
+/// Selects the management interface among the candidates according to the selection policy.
+/// Between otherwise equal interfaces, the first candidate is selected.
+fn select_nic(
+    selection: &NicSelection,
+    candidates: &[String],
+    network: &NetworkInfo,
+) -> Result<String> {
+    // VLAN interfaces of the installation environment are configured through the VLAN ID instead
+    let interfaces: Vec<_> = candidates
+        .iter()
+        .filter(|name| network.vlan_parent(name).is_none())
+        .filter_map(|name| network.interfaces.get(name))
+        .collect();
+
+    let selected = match selection {
+        NicSelection::FirstWithCarrier => interfaces.iter().find(|iface| iface.has_carrier()),
+        NicSelection::DhcpLease => {
+            let dhcp = network.dhcp_interface().map(|iface| &iface.name);
+            interfaces.iter().find(|iface| Some(&iface.name) == dhcp)
+        }
+        NicSelection::Mac { macs } => macs.iter().find_map(|mac| {
+            interfaces
+                .iter()
+                .find(|iface| iface.mac.eq_ignore_ascii_case(mac))
+        }),
+        NicSelection::FastestLink => interfaces
+            .iter()
+            .filter(|iface| iface.has_carrier())
+            .filter_map(|iface| Some((iface, iface.speed?)))
+            .fold(None, |fastest, (iface, speed)| match fastest {
+                Some((_, fastest_speed)) if fastest_speed >= speed => fastest,
+                _ => Some((iface, speed)),
+            })
+            .map(|(iface, _)| iface),
+    };
+
+    selected.map(|iface| iface.name.clone()).ok_or_else(|| {
+        format_err!(
+            "no network interface found by policy '{selection}' among '{}'",
+            candidates.join("', '")
+        )
+    })
+}
+
 pub fn get_single_udev_index(
     filter: &BTreeMap<String, String>,
     udev_list: &BTreeMap<String, BTreeMap<String, String>>,
@@ -47,16 +216,14 @@ pub fn get_single_udev_index(
     if filter.is_empty() {
         bail!("no filter defined");
     }
+    let filter = parse_filter(filter)?;
     let mut dev_index: Option<String> = None;
     'outer: for (dev, dev_values) in udev_list {
-        for (filter_key, filter_value) in filter {
-            let filter_pattern =
-                Pattern::new(filter_value).context("invalid glob in disk selection")?;
-            for (udev_key, udev_value) in dev_values {
-                if udev_key == filter_key && filter_pattern.matches(udev_value) {
-                    dev_index = Some(dev.clone());
-                    break 'outer; // take first match
-                }
+        for (filter_key, expression) in &filter {
+            let value = dev_values.get(*filter_key).map(String::as_str);
+            if expression.matches(value) == Some(true) {
+                dev_index = Some(dev.clone());
+                break 'outer; // take first match
             }
         }
     }
@@ -102,6 +269,15 @@ struct IpLinksUdevInfo {
     ifname: String,
 }
 
+/// Generates the JSON schema describing the serialized form of `T`, as used for the payloads the
+/// installer sends to remote services.
+pub fn payload_json_schema<T: JsonSchema>() -> Schema {
+    SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<T>()
+}
+
 /// Returns vec of usable NICs
 pub fn get_nic_list() -> Result<Vec<String>> {
     let ip_output = Command::new("/usr/sbin/ip")
@@ -126,19 +302,17 @@ pub fn get_matched_udev_indexes(
     udev_list: &BTreeMap<String, BTreeMap<String, String>>,
     match_all: bool,
 ) -> Result<Vec<String>> {
+    let filter = parse_filter(filter)?;
     let mut matches = vec![];
     for (dev, dev_values) in udev_list {
         let mut did_match_once = false;
         let mut did_match_all = true;
-        for (filter_key, filter_value) in filter {
-            let filter_pattern =
-                Pattern::new(filter_value).context("invalid glob in disk selection")?;
-            for (udev_key, udev_value) in dev_values {
-                if udev_key == filter_key && filter_pattern.matches(udev_value) {
-                    did_match_once = true;
-                } else if udev_key == filter_key {
-                    did_match_all = false;
-                }
+        for (filter_key, expression) in &filter {
+            let value = dev_values.get(*filter_key).map(String::as_str);
+            match expression.matches(value) {
+                Some(true) => did_match_once = true,
+                // Devices without the property do not match, not even negated expressions
+                Some(false) | None => did_match_all = false,
             }
         }
         if (match_all && did_match_all) || (!match_all && did_match_once) {
@@ -159,11 +333,59 @@ fn set_disks(
     config: &mut InstallConfig,
 ) -> Result<()> {
     match config.filesys {
-        FsType::Ext4 | FsType::Xfs => set_single_disk(answer, udev_info, runtime_info, config),
+        FsType::Ext4 | FsType::Xfs => set_single_disk(answer, udev_info, runtime_info, config)?,
         FsType::Zfs(_) | FsType::Btrfs(_) => {
-            set_selected_disks(answer, udev_info, runtime_info, config)
+            set_selected_disks(answer, udev_info, runtime_info, config)?
+        }
+    }
+    verify_disk_wipe_method(udev_info, runtime_info, config)
+}
+
+/// Fails if the wipe method is tied to a transport, e.g. NVMe, and one of the selected disks is
+/// not known to be attached over it, or if the selected disks cannot be wiped using it otherwise.
+fn verify_disk_wipe_method(
+    udev_info: &UdevInfo,
+    runtime_info: &RuntimeInfo,
+    config: &InstallConfig,
+) -> Result<()> {
+    let Some(method) = config.wipe else {
+        return Ok(());
+    };
+    let required = match method {
+        DiskWipeMethod::NvmeFormat | DiskWipeMethod::NvmeSanitize => DiskTransport::Nvme,
+        DiskWipeMethod::AtaSecureErase => DiskTransport::Sata,
+        DiskWipeMethod::Discard | DiskWipeMethod::Zero => return Ok(()),
+    };
+
+    let selected: Vec<Disk> = runtime_info
+        .disks
+        .iter()
+        .filter(|disk| {
+            config.target_hd.as_ref() == Some(&disk.path)
+                || config.disk_selection.contains_key(&disk.index)
+        })
+        .cloned()
+        .collect();
+
+    for disk in &selected {
+        let transport = udev_info
+            .disk_attributes
+            .get(&disk.index)
+            .and_then(|attributes| attributes.transport);
+        match transport {
+            Some(transport) if transport == required => {}
+            Some(transport) => bail!(
+                "Cannot wipe disk '{}' using '{method}', as it is attached over {transport}",
+                disk.path
+            ),
+            None => bail!(
+                "Cannot wipe disk '{}' using '{method}', as its transport is unknown",
+                disk.path
+            ),
         }
     }
+
+    check_disk_wipe_method(method, &selected, &runtime_info.disks).map_err(|err| format_err!(err))
 }
 
 fn set_single_disk(
@@ -180,12 +402,16 @@ fn set_single_disk(
                 .iter()
                 .find(|item| item.path.ends_with(disk_name.as_str()));
             match disk {
-                Some(disk) => config.target_hd = Some(disk.path.clone()),
+                Some(disk) => {
+                    check_disk_installable(answer, udev_info, disk)?;
+                    config.target_hd = Some(disk.path.clone());
+                }
                 None => bail!("disk in 'disk_selection' not found"),
             }
         }
         answer::DiskSelection::Filter(filter) => {
-            let disk_index = get_single_udev_index(filter, &udev_info.disks)?;
+            let disks = installable_disk_properties(answer, udev_info, runtime_info);
+            let disk_index = get_single_udev_index(filter, &disks)?;
             let disk = runtime_info
                 .disks
                 .iter()
@@ -197,6 +423,48 @@ fn set_single_disk(
     Ok(())
 }
 
+/// Returns the filter properties of all disks which may be installed to, keyed by their index.
+/// Disks holding the installation medium or the answer file, as well as removable disks unless
+/// explicitly allowed, are dropped.
+fn installable_disk_properties(
+    answer: &Answer,
+    udev_info: &UdevInfo,
+    runtime_info: &RuntimeInfo,
+) -> BTreeMap<String, UdevProperties> {
+    let mut disks = disk_properties(udev_info, &runtime_info.disks);
+    disks.retain(|index, props| {
+        let Some(reason) = disk_installation_blocker(answer, udev_info, index) else {
+            return true;
+        };
+        let name = props.get("DEVNAME").map_or(index.as_str(), String::as_str);
+        info!("Not considering disk '{name}' for installation, as {reason}");
+        false
+    });
+    disks
+}
+
+/// Fails if the explicitly selected disk must not be installed to.
+fn check_disk_installable(answer: &Answer, udev_info: &UdevInfo, disk: &Disk) -> Result<()> {
+    match disk_installation_blocker(answer, udev_info, &disk.index) {
+        Some(reason) => bail!("Cannot install to disk '{}', as {reason}", disk.path),
+        None => Ok(()),
+    }
+}
+
+/// Returns the reason why nothing must be installed to the disk, if any. Disks without recorded
+/// attributes might be removable, so they are only acceptable if removable disks are.
+fn disk_installation_blocker(
+    answer: &Answer,
+    udev_info: &UdevInfo,
+    index: &str,
+) -> Option<&'static str> {
+    match udev_info.disk_attributes.get(index) {
+        Some(attributes) => attributes.installation_blocker(answer.disks.allow_removable),
+        None if answer.disks.allow_removable => None,
+        None => Some("its attributes are unknown and 'allow_removable' is not set"),
+    }
+}
+
 fn set_selected_disks(
     answer: &Answer,
     udev_info: &UdevInfo,
@@ -212,6 +480,7 @@ fn set_selected_disks(
                     .iter()
                     .find(|item| item.path.ends_with(disk_name.as_str()));
                 if let Some(disk) = disk {
+                    check_disk_installable(answer, udev_info, disk)?;
                     config
                         .disk_selection
                         .insert(disk.index.clone(), disk.index.clone());
@@ -227,7 +496,7 @@ fn set_selected_disks(
                 .unwrap_or(answer::FilterMatch::Any);
             let selected_disk_indexes = get_matched_udev_indexes(
                 filter,
-                &udev_info.disks,
+                &installable_disk_properties(answer, udev_info, runtime_info),
                 filter_match == answer::FilterMatch::All,
             )?;
 
@@ -293,58 +562,28 @@ fn verify_filesystem_settings(answer: &Answer, setup_info: &SetupInfo) -> Result
 
 pub fn verify_locale_settings(answer: &Answer, locales: &LocaleInfo) -> Result<()> {
     info!("Verifying locale settings");
-    if !locales
-        .countries
-        .keys()
-        .any(|i| i == &answer.global.country)
-    {
-        bail!("country code '{}' is not valid", &answer.global.country);
-    }
-    if !locales
-        .kmap
-        .keys()
-        .any(|i| i == &answer.global.keyboard.to_string())
-    {
-        bail!("keyboard layout '{}' is not valid", &answer.global.keyboard);
-    }
-
-    if !locales
-        .cczones
-        .iter()
-        .any(|(_, zones)| zones.contains(&answer.global.timezone))
-        && answer.global.timezone != "UTC"
-    {
-        bail!("timezone '{}' is not valid", &answer.global.timezone);
-    }
-
-    Ok(())
+    first_diagnostic_as_error(check_locale_settings(&answer.global, locales))
 }
 
 pub fn verify_email_and_root_password_settings(answer: &Answer) -> Result<()> {
     info!("Verifying email and root password settings");
-
-    email_validate(&answer.global.mailto).with_context(|| answer.global.mailto.clone())?;
-
-    if answer.global.root_password.is_some() && answer.global.root_password_hashed.is_some() {
-        bail!("`global.root_password` and `global.root_password_hashed` cannot be set at the same time");
-    } else if answer.global.root_password.is_none() && answer.global.root_password_hashed.is_none()
-    {
-        bail!("One of `global.root_password` or `global.root_password_hashed` must be set");
-    } else {
-        Ok(())
-    }
+    first_diagnostic_as_error(check_email_and_root_password_settings(&answer.global))
 }
 
 pub fn verify_first_boot_settings(answer: &Answer) -> Result<()> {
     info!("Verifying first boot settings");
 
-    if let Some(first_boot) = &answer.first_boot {
-        if first_boot.source == FirstBootHookSourceMode::FromUrl && first_boot.url.is_none() {
-            bail!("first-boot executable source set to URL, but none specified!");
-        }
+    match &answer.first_boot {
+        Some(first_boot) => first_diagnostic_as_error(check_first_boot_settings(first_boot)),
+        None => Ok(()),
     }
+}
 
-    Ok(())
+fn first_diagnostic_as_error(diagnostics: Vec<Diagnostic>) -> Result<()> {
+    match diagnostics.into_iter().next() {
+        Some(diag) => bail!("{}", diag.message),
+        None => Ok(()),
+    }
 }
 
 pub fn parse_answer(
@@ -368,6 +607,10 @@ pub fn parse_answer(
     verify_email_and_root_password_settings(answer)?;
     verify_first_boot_settings(answer)?;
 
+    let bridge_opts = network_settings
+        .bridge_options()
+        .map(InstallBridgeOption::from);
+
     let mut config = InstallConfig {
         autoreboot: 1_usize,
         filesys: filesystem,
@@ -380,7 +623,12 @@ pub fn parse_answer(
         btrfs_opts: None,
         target_hd: None,
         disk_selection: BTreeMap::new(),
-        existing_storage_auto_rename: 1,
+        // Existing storage must never be renamed silently if the answer refuses existing data
+        existing_storage_auto_rename: usize::from(
+            answer.disks.existing_data != Some(ExistingDataPolicy::Refuse),
+        ),
+        existing_data: answer.disks.existing_data,
+        wipe: answer.disks.wipe,
 
         country: answer.global.country.clone(),
         timezone: answer.global.timezone.clone(),
@@ -394,12 +642,22 @@ pub fn parse_answer(
         root_ssh_keys: answer.global.root_ssh_keys.clone(),
 
         mngmt_nic: network_settings.ifname,
+        vlan_id: network_settings.vlan,
+        bond_opts: network_settings.bond.map(InstallBondOption::from),
+        mtu: network_settings.mtu,
+        bridge_opts,
 
         hostname: network_settings.fqdn.host().unwrap().to_string(),
         domain: network_settings.fqdn.domain(),
         cidr: network_settings.address,
         gateway: network_settings.gateway,
-        dns: network_settings.dns_server,
+        cidr6: network_settings.address6,
+        gateway6: network_settings.gateway6,
+        // Safety: Either taken from the answer, which requires at least one DNS server, or the
+        // defaults, which always contain one.
+        dns: network_settings.dns_servers[0],
+        additional_dns: network_settings.dns_servers[1..].to_vec(),
+        search_domains: network_settings.search_domains,
 
         first_boot: InstallFirstBootSetup::default(),
     };
@@ -0,0 +1,1024 @@
+//! Validation of answer files, which collects all problems in one pass instead of stopping at the
+//! first one, each located by its TOML key path and position in the file. Within a section, all
+//! invalid values are reported, but required keys are only checked once all values are valid.
+//!
+//! Multi-host answer files are checked by validating the answer of every host, i.e. the base
+//! merged with the respective host section.
+//!
+//! JSON and YAML answer files are converted to TOML first. Problems found in them are only
+//! identified by their key path, as positions in the converted file would be misleading.
+//!
+//! Additionally, answer files can be linted for settings which are valid, but most likely not
+//! what was intended.
+
+use clap::ValueEnum;
+use serde::{
+    de::{DeserializeOwned, IgnoredAny, MapAccess, Visitor},
+    Deserializer, Serialize,
+};
+use std::{fmt, marker::PhantomData, ops::Range};
+use toml_edit::{DocumentMut, ImDocument, Item, TableLike};
+
+use crate::{
+    answer::{
+        Answer, AnswerFormat, DiskSetup, FirstBootHookInfo, FirstBootHookSourceMode, FsOptions,
+        Global, NetworkInAnswer, PostNotificationHookInfo,
+    },
+    multihost::{apply_host, HostMatch, HOST_SECTION},
+    utils::FetchAnswerFrom,
+};
+use proxmox_installer_common::{
+    options::{default_zfs_arc_max, email_validate, ZfsRaidLevel},
+    setup::{LocaleInfo, RuntimeInfo, SetupInfo},
+    EMAIL_DEFAULT_PLACEHOLDER,
+};
+
+/// A single problem found in an answer file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// Dotted TOML key path of the offending value, e.g. `disk-setup.zfs.raid`. Empty if the
+    /// problem concerns the file as a whole.
+    pub path: String,
+    /// Line in the answer file, starting at 1, if the problem could be located.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Column in the answer file, starting at 1, if the problem could be located.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "line {line}, column {column}: ")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "'{}': ", self.path)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// Identifiers of all lints, which can be used to suppress them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintId {
+    /// The root password is given in plain text.
+    PlainRootPassword,
+    /// No SSH keys are configured for root.
+    NoRootSshKeys,
+    /// The ZFS ARC maximum size differs from the default for the target system.
+    FixedZfsArcMax,
+    /// Multiple ZFS data copies are configured on top of a redundant RAID level.
+    ZfsCopiesOnRaid,
+    /// The configured disk size is below the recommended minimum of the product, only checked if
+    /// the product configuration is known.
+    SmallHdsize,
+    /// The answer file is fetched via HTTP, but the system does not reboot on errors.
+    NoRebootOnError,
+    /// The notification email address uses a placeholder domain.
+    PlaceholderMailto,
+}
+
+serde_plain::derive_display_from_serialize!(LintId);
+
+/// A warning about a valid, but suspicious setting in an answer file.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Lint {
+    pub id: LintId,
+    #[serde(flatten)]
+    pub diagnostic: Diagnostic,
+}
+
+/// Additional information about the installation, which enables more lints if known.
+#[derive(Default)]
+pub struct LintContext<'a> {
+    /// Runtime environment of the target system.
+    pub runtime_info: Option<&'a RuntimeInfo>,
+    /// Information about the product and ISO to install from.
+    pub setup_info: Option<&'a SetupInfo>,
+    /// How the automated installer retrieves the answer file.
+    pub fetch_from: Option<FetchAnswerFrom>,
+}
+
+/// Domains reserved for documentation and testing purposes, see RFC 2606.
+const PLACEHOLDER_DOMAINS: &[&str] = &["example.com", "example.net", "example.org"];
+
+const REQUIRED_SECTIONS: &[&str] = &["global", "network", "disk-setup"];
+const OPTIONAL_SECTIONS: &[&str] = &["post-installation-webhook", "first-boot"];
+
+/// Validates the content of an answer file and collects all problems found, sorted by their
+/// position in the file.
+///
+/// Besides the structure of the file, this also runs all checks which do not depend on the
+/// target system. The locale settings are only checked if `locales` is given.
+pub fn validate_answer(
+    content: &str,
+    format: AnswerFormat,
+    locales: Option<&LocaleInfo>,
+) -> Result<Answer, Vec<Diagnostic>> {
+    if format == AnswerFormat::Toml {
+        return validate_toml_answer(content, locales);
+    }
+
+    let content = format.to_toml(content).map_err(|diag| vec![diag])?;
+    validate_toml_answer(&content, locales).map_err(|diagnostics| {
+        diagnostics
+            .into_iter()
+            .map(|diag| Diagnostic::new(diag.path, diag.message))
+            .collect()
+    })
+}
+
+fn validate_toml_answer(
+    content: &str,
+    locales: Option<&LocaleInfo>,
+) -> Result<Answer, Vec<Diagnostic>> {
+    let doc = match ImDocument::parse(content) {
+        Ok(doc) => doc,
+        Err(err) => {
+            let mut diag = Diagnostic::new("", err.message().trim());
+            set_position(&mut diag, content, err.span());
+            return Err(vec![diag]);
+        }
+    };
+
+    if doc.contains_key(HOST_SECTION) {
+        return validate_multihost_answer(content, &doc, locales);
+    }
+
+    let mut diagnostics = Vec::new();
+
+    for (key, _) in doc.as_table().iter() {
+        if !REQUIRED_SECTIONS.contains(&key) && !OPTIONAL_SECTIONS.contains(&key) {
+            let expected = [REQUIRED_SECTIONS, OPTIONAL_SECTIONS].concat().join("', '");
+            diagnostics.push(Diagnostic::new(
+                key,
+                format!("unknown section, expected one of '{expected}'"),
+            ));
+        }
+    }
+    for section in REQUIRED_SECTIONS {
+        if !doc.as_table().contains_key(section) {
+            diagnostics.push(Diagnostic::new(*section, "missing section"));
+        }
+    }
+
+    match parse_section::<Global>(content, "global") {
+        Ok(Some(global)) => {
+            if let Some(locales) = locales {
+                diagnostics.extend(check_locale_settings(&global, locales));
+            }
+            diagnostics.extend(check_email_and_root_password_settings(&global));
+        }
+        Ok(None) => {}
+        Err(diags) => diagnostics.extend(diags),
+    }
+
+    match parse_section::<NetworkInAnswer>(content, "network") {
+        Ok(Some(network)) => diagnostics.extend(section_problems("network", network.check())),
+        Ok(None) => {}
+        Err(diags) => diagnostics.extend(diags),
+    }
+
+    match parse_section::<DiskSetup>(content, "disk-setup") {
+        Ok(Some(disks)) => diagnostics.extend(section_problems("disk-setup", disks.check())),
+        Ok(None) => {}
+        Err(diags) => diagnostics.extend(diags),
+    }
+
+    if let Err(diags) =
+        parse_section::<PostNotificationHookInfo>(content, "post-installation-webhook")
+    {
+        diagnostics.extend(diags);
+    }
+
+    match parse_section::<FirstBootHookInfo>(content, "first-boot") {
+        Ok(Some(first_boot)) => diagnostics.extend(check_first_boot_settings(&first_boot)),
+        Ok(None) => {}
+        Err(diags) => diagnostics.extend(diags),
+    }
+
+    // Should not fail anymore at this point, but better be safe than sorry.
+    let result = if diagnostics.is_empty() {
+        match toml::from_str(content) {
+            Ok(answer) => return Ok(answer),
+            Err(err) => {
+                let mut diag = Diagnostic::new("", err.message());
+                set_position(&mut diag, content, err.span());
+                vec![diag]
+            }
+        }
+    } else {
+        diagnostics
+    };
+
+    let mut diagnostics: Vec<Diagnostic> = result
+        .into_iter()
+        .map(|mut diag| {
+            if diag.line.is_none() {
+                locate(&mut diag, doc.as_table(), content);
+            }
+            diag
+        })
+        .collect();
+
+    diagnostics.sort_by_key(|diag| (diag.line.unwrap_or(usize::MAX), diag.column));
+    Err(diagnostics)
+}
+
+/// Validates the answer of every host of a multi-host answer file. Problems occurring only for
+/// some of the hosts are marked with their numbers and located in the host section if the
+/// offending value is set there. Returns the answer of the first host if all of them are valid.
+fn validate_multihost_answer(
+    content: &str,
+    doc: &ImDocument<&str>,
+    locales: Option<&LocaleInfo>,
+) -> Result<Answer, Vec<Diagnostic>> {
+    let invalid_hosts = || {
+        let mut diag = Diagnostic::new(HOST_SECTION, "expected a non-empty array of tables");
+        locate(&mut diag, doc.as_table(), content);
+        vec![diag]
+    };
+
+    let host_items: Vec<(&dyn TableLike, Option<Range<usize>>)> = match doc.get(HOST_SECTION) {
+        Some(Item::ArrayOfTables(hosts)) => hosts
+            .iter()
+            .map(|host| (host as &dyn TableLike, host.span()))
+            .collect(),
+        Some(Item::Value(toml_edit::Value::Array(hosts))) => hosts
+            .iter()
+            .map(|host| {
+                host.as_inline_table()
+                    .map(|table| (table as &dyn TableLike, table.span()))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(invalid_hosts)?,
+        _ => return Err(invalid_hosts()),
+    };
+
+    let mut base: toml::Table = toml::from_str(content).map_err(|err| {
+        let mut diag = Diagnostic::new("", err.message());
+        set_position(&mut diag, content, err.span());
+        vec![diag]
+    })?;
+    let hosts: Vec<toml::Table> = base
+        .remove(HOST_SECTION)
+        .and_then(|hosts| hosts.try_into().ok())
+        .filter(|hosts: &Vec<toml::Table>| !hosts.is_empty())
+        .ok_or_else(invalid_hosts)?;
+
+    let mut first_answer = None;
+    let mut collected: Vec<(Diagnostic, Vec<usize>)> = Vec::new();
+
+    for (index, (host, (host_item, host_span))) in hosts.iter().zip(host_items).enumerate() {
+        let mut diagnostics = Vec::new();
+
+        if let Err(message) = HostMatch::from_host(host) {
+            let mut diag = Diagnostic::new(format!("{HOST_SECTION}.match"), message);
+            set_position(&mut diag, content, host_span);
+            diagnostics.push(diag);
+        }
+
+        let result = toml::to_string(&apply_host(&base, host))
+            .map_err(|err| vec![Diagnostic::new("", err.to_string())])
+            .and_then(|merged| validate_toml_answer(&merged, locales));
+
+        match result {
+            Ok(answer) => {
+                first_answer.get_or_insert(answer);
+            }
+            Err(errors) => {
+                for error in errors {
+                    let mut diag = Diagnostic::new(error.path, error.message);
+                    if contains_path(host_item, &diag.path) {
+                        locate(&mut diag, host_item, content);
+                    } else {
+                        locate(&mut diag, doc.as_table(), content);
+                    }
+                    diagnostics.push(diag);
+                }
+            }
+        }
+
+        for diag in diagnostics {
+            match collected.iter_mut().find(|(known, _)| *known == diag) {
+                Some((_, indices)) => indices.push(index),
+                None => collected.push((diag, vec![index])),
+            }
+        }
+    }
+
+    if collected.is_empty() {
+        if let Some(answer) = first_answer {
+            return Ok(answer);
+        }
+    }
+
+    let mut diagnostics: Vec<Diagnostic> = collected
+        .into_iter()
+        .map(|(mut diag, indices)| {
+            if indices.len() < hosts.len() {
+                let numbers: Vec<String> = indices.iter().map(|i| format!("#{}", i + 1)).collect();
+                diag.message = format!("{} (host {})", diag.message, numbers.join(", "));
+            }
+            diag
+        })
+        .collect();
+
+    diagnostics.sort_by_key(|diag| (diag.line.unwrap_or(usize::MAX), diag.column));
+    Err(diagnostics)
+}
+
+/// Checks the locale settings against the locales known to the installer.
+pub fn check_locale_settings(global: &Global, locales: &LocaleInfo) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if !locales.countries.keys().any(|i| i == &global.country) {
+        diagnostics.push(Diagnostic::new(
+            "global.country",
+            format!("country code '{}' is not valid", &global.country),
+        ));
+    }
+    if !locales
+        .kmap
+        .keys()
+        .any(|i| i == &global.keyboard.to_string())
+    {
+        diagnostics.push(Diagnostic::new(
+            "global.keyboard",
+            format!("keyboard layout '{}' is not valid", &global.keyboard),
+        ));
+    }
+
+    if !locales
+        .cczones
+        .iter()
+        .any(|(_, zones)| zones.contains(&global.timezone))
+        && global.timezone != "UTC"
+    {
+        diagnostics.push(Diagnostic::new(
+            "global.timezone",
+            format!("timezone '{}' is not valid", &global.timezone),
+        ));
+    }
+
+    diagnostics
+}
+
+pub fn check_email_and_root_password_settings(global: &Global) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let Err(err) = email_validate(&global.mailto) {
+        diagnostics.push(Diagnostic::new(
+            "global.mailto",
+            format!("{}: {err}", global.mailto),
+        ));
+    }
+
+    if global.root_password.is_some() && global.root_password_hashed.is_some() {
+        diagnostics.push(Diagnostic::new(
+            "global.root_password_hashed",
+            "`global.root_password` and `global.root_password_hashed` cannot be set at the same time",
+        ));
+    } else if global.root_password.is_none() && global.root_password_hashed.is_none() {
+        diagnostics.push(Diagnostic::new(
+            "global",
+            "One of `global.root_password` or `global.root_password_hashed` must be set",
+        ));
+    }
+
+    diagnostics
+}
+
+pub fn check_first_boot_settings(first_boot: &FirstBootHookInfo) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if first_boot.source == FirstBootHookSourceMode::FromUrl && first_boot.url.is_none() {
+        diagnostics.push(Diagnostic::new(
+            "first-boot.url",
+            "first-boot executable source set to URL, but none specified!",
+        ));
+    }
+
+    diagnostics
+}
+
+/// Lints a valid answer file for settings which are legal, but most likely not intended. Returns
+/// the warnings sorted by their position in the file, which is only known for TOML answer files.
+pub fn lint_answer(
+    content: &str,
+    format: AnswerFormat,
+    answer: &Answer,
+    context: &LintContext,
+) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let mut warn = |id, path: &str, message: String| {
+        lints.push(Lint {
+            id,
+            diagnostic: Diagnostic::new(path, message),
+        })
+    };
+
+    let global = &answer.global;
+
+    if global.root_password.is_some() {
+        warn(
+            LintId::PlainRootPassword,
+            "global.root_password",
+            "root password is stored in plain text, consider using 'root_password_hashed' instead"
+                .to_owned(),
+        );
+    }
+
+    if global.root_ssh_keys.is_empty() {
+        warn(
+            LintId::NoRootSshKeys,
+            "global.root_ssh_keys",
+            "no SSH keys are configured for root, logging in is only possible with the password"
+                .to_owned(),
+        );
+    }
+
+    if let Some((_, domain)) = global.mailto.rsplit_once('@') {
+        let domain = domain.to_lowercase();
+        let placeholder_domain = EMAIL_DEFAULT_PLACEHOLDER
+            .rsplit_once('@')
+            .map(|(_, domain)| domain);
+
+        if Some(domain.as_str()) == placeholder_domain
+            || domain.ends_with(".invalid")
+            || domain.ends_with(".example")
+            || PLACEHOLDER_DOMAINS.contains(&domain.as_str())
+        {
+            warn(
+                LintId::PlaceholderMailto,
+                "global.mailto",
+                format!(
+                    "'{}' uses a placeholder domain, notifications will not be delivered",
+                    global.mailto
+                ),
+            );
+        }
+    }
+
+    if context.fetch_from == Some(FetchAnswerFrom::Http) && !global.reboot_on_error {
+        warn(
+            LintId::NoRebootOnError,
+            "global.reboot_on_error",
+            "answer file is fetched via HTTP, but 'reboot_on_error' is not enabled, a failed \
+             installation will not be retried automatically"
+                .to_owned(),
+        );
+    }
+
+    let (fs_key, hdsize) = match &answer.disks.fs_options {
+        FsOptions::LVM(opts) => ("lvm", opts.hdsize),
+        FsOptions::ZFS(opts) => ("zfs", opts.hdsize),
+        FsOptions::BTRFS(opts) => ("btrfs", opts.hdsize),
+    };
+
+    let min_hdsize = context
+        .setup_info
+        .and_then(|info| info.config.recommended_min_hdsize);
+    if let (Some(hdsize), Some(min_hdsize)) = (hdsize, min_hdsize) {
+        if hdsize < min_hdsize {
+            warn(
+                LintId::SmallHdsize,
+                &format!("disk-setup.{fs_key}.hdsize"),
+                format!(
+                    "'hdsize' of {hdsize} GB is below the recommended minimum of {min_hdsize} GB, \
+                     the installation might not be successful"
+                ),
+            );
+        }
+    }
+
+    if let FsOptions::ZFS(opts) = &answer.disks.fs_options {
+        let copies = opts.copies.unwrap_or(1);
+        if let Some(raid) = opts.raid.filter(|raid| *raid != ZfsRaidLevel::Raid0) {
+            if copies > 1 {
+                warn(
+                    LintId::ZfsCopiesOnRaid,
+                    "disk-setup.zfs.copies",
+                    format!(
+                        "'copies' is set to {copies} on a {raid} setup, which already provides \
+                         redundancy, this multiplies the used space"
+                    ),
+                );
+            }
+        }
+
+        if let (Some(arc_max), Some(runtime_info), Some(setup_info)) =
+            (opts.arc_max, context.runtime_info, context.setup_info)
+        {
+            let default = default_zfs_arc_max(setup_info.config.product, runtime_info.total_memory);
+            if arc_max != default {
+                warn(
+                    LintId::FixedZfsArcMax,
+                    "disk-setup.zfs.arc_max",
+                    format!(
+                        "'arc_max' is set to {arc_max} MiB, while the default for the target \
+                         system would be {default} MiB"
+                    ),
+                );
+            }
+        }
+    }
+
+    if format == AnswerFormat::Toml {
+        if let Ok(doc) = ImDocument::parse(content) {
+            for lint in &mut lints {
+                locate(&mut lint.diagnostic, doc.as_table(), content);
+            }
+        }
+    }
+
+    lints.sort_by_key(|lint| {
+        (
+            lint.diagnostic.line.unwrap_or(usize::MAX),
+            lint.diagnostic.column,
+        )
+    });
+    lints
+}
+
+fn section_problems<M: Into<String>>(section: &str, problems: Vec<(&str, M)>) -> Vec<Diagnostic> {
+    problems
+        .into_iter()
+        .map(|(key, message)| {
+            let path = if key.is_empty() {
+                section.to_owned()
+            } else {
+                format!("{section}.{key}")
+            };
+            Diagnostic::new(path, message)
+        })
+        .collect()
+}
+
+/// Deserializes a single top-level section of the answer file, ignoring all other sections.
+/// Returns `Ok(None)` if the section is not present at all.
+///
+/// Deserialization stops at the first invalid value, so to collect all of them, the offending
+/// key is removed and the section parsed again, until either no problems are left or a problem
+/// cannot be attributed to a single key within the section.
+fn parse_section<T: DeserializeOwned>(
+    content: &str,
+    section: &str,
+) -> Result<Option<T>, Vec<Diagnostic>> {
+    let mut diagnostics = Vec::new();
+    let mut removed_keys = Vec::new();
+    let mut content = content.to_owned();
+
+    loop {
+        let mut diag = match parse_section_once::<T>(&content, section) {
+            Ok(result) if diagnostics.is_empty() => return Ok(result),
+            Ok(_) => return Err(diagnostics),
+            Err(diag) => diag,
+        };
+
+        // Required keys only go missing because of their invalid values reported before
+        if removed_keys
+            .iter()
+            .any(|key| diag.message == format!("missing field `{key}`"))
+        {
+            return Err(diagnostics);
+        }
+        // Positions within the modified content do not match the original one
+        if !diagnostics.is_empty() {
+            diag.line = None;
+            diag.column = None;
+        }
+
+        let next = diag
+            .path
+            .strip_prefix(section)
+            .is_some_and(|rest| rest.starts_with('.'))
+            .then(|| remove_key(&content, &diag.path))
+            .flatten();
+        let key = diag.path.rsplit('.').next().unwrap_or_default().to_owned();
+        diagnostics.push(diag);
+
+        match next {
+            Some(next) => {
+                removed_keys.push(key);
+                content = next;
+            }
+            None => return Err(diagnostics),
+        }
+    }
+}
+
+/// Returns the content with the value at the given key path removed, if present.
+fn remove_key(content: &str, path: &str) -> Option<String> {
+    let mut doc: DocumentMut = content.parse().ok()?;
+    let (parent, key) = path.rsplit_once('.')?;
+
+    let mut table: &mut dyn TableLike = doc.as_table_mut();
+    for key in parent.split('.') {
+        table = table.get_mut(key)?.as_table_like_mut()?;
+    }
+    table.remove(key)?;
+
+    Some(doc.to_string())
+}
+
+fn parse_section_once<T: DeserializeOwned>(
+    content: &str,
+    section: &str,
+) -> Result<Option<T>, Diagnostic> {
+    struct SectionVisitor<'a, T> {
+        section: &'a str,
+        _marker: PhantomData<T>,
+    }
+
+    impl<'de, T: DeserializeOwned> Visitor<'de> for SectionVisitor<'_, T> {
+        type Value = Option<T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a table")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut result = None;
+            while let Some(key) = map.next_key::<String>()? {
+                if key == self.section {
+                    result = Some(map.next_value::<T>()?);
+                } else {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+            Ok(result)
+        }
+    }
+
+    let visitor = SectionVisitor {
+        section,
+        _marker: PhantomData,
+    };
+
+    toml::Deserializer::new(content)
+        .deserialize_map(visitor)
+        .map_err(|err| {
+            let mut diag = Diagnostic::new("", err.message());
+            set_position(&mut diag, content, err.span());
+            if let Some(span) = err.span() {
+                diag.path = ImDocument::parse(content)
+                    .ok()
+                    .and_then(|doc| path_at_offset(doc.as_table(), span.start, ""))
+                    .unwrap_or_else(|| section.to_owned());
+            }
+            diag
+        })
+}
+
+/// Finds the position of the diagnostic in the file by its key path, starting at the given table.
+/// If the key itself is not present, e.g. for missing fields, the closest parent present is used.
+fn locate(diag: &mut Diagnostic, root: &dyn TableLike, content: &str) {
+    let mut table = root;
+    let mut span = None;
+
+    for key in diag.path.split('.').filter(|key| !key.is_empty()) {
+        let Some((key, item)) = table.get_key_value(key) else {
+            break;
+        };
+
+        span = key.span().or_else(|| item.span()).or(span);
+        match item.as_table_like() {
+            Some(inner) => table = inner,
+            None => break,
+        }
+    }
+
+    set_position(diag, content, span);
+}
+
+/// Returns whether the full key path is present, starting at the given table.
+fn contains_path(root: &dyn TableLike, path: &str) -> bool {
+    let mut item = None;
+    let mut table = Some(root);
+
+    for key in path.split('.').filter(|key| !key.is_empty()) {
+        item = table.and_then(|table| table.get(key));
+        table = item.and_then(Item::as_table_like);
+    }
+
+    item.is_some()
+}
+
+/// Returns the key path of the innermost value containing the given byte offset.
+fn path_at_offset(table: &dyn TableLike, offset: usize, prefix: &str) -> Option<String> {
+    for (key, item) in table.iter() {
+        let path = if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        if let Some(inner) = item.as_table_like() {
+            if let Some(path) = path_at_offset(inner, offset, &path) {
+                return Some(path);
+            }
+        }
+
+        let key_span = table.get_key_value(key).and_then(|(key, _)| key.span());
+        let contains = |span: Option<Range<usize>>| span.is_some_and(|s| s.contains(&offset));
+        if contains(key_span) || (!matches!(item, Item::Table(_)) && contains(item.span())) {
+            return Some(path);
+        }
+    }
+
+    None
+}
+
+fn set_position(diag: &mut Diagnostic, content: &str, span: Option<Range<usize>>) {
+    let Some(span) = span else {
+        return;
+    };
+
+    let before = &content[..span.start.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|pos| pos + 1).unwrap_or(0);
+
+    diag.line = Some(line);
+    diag.column = Some(before[line_start..].chars().count() + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_ANSWER: &str = r#"
+[global]
+keyboard = "de"
+country = "at"
+fqdn = "pveauto.testinstall"
+mailto = "mail@no.invalid"
+timezone = "Europe/Vienna"
+root_password = "12345678"
+
+[network]
+source = "from-dhcp"
+
+[disk-setup]
+filesystem = "ext4"
+disk_list = ["sda"]
+"#;
+
+    #[test]
+    fn valid_answer() {
+        assert!(validate_answer(VALID_ANSWER, AnswerFormat::Toml, None).is_ok());
+    }
+
+    #[test]
+    fn collects_all_problems() {
+        let content = VALID_ANSWER
+            .replace(r#"keyboard = "de""#, r#"keyboard = "xx""#)
+            .replace(
+                r#"source = "from-dhcp""#,
+                "source = \"from-dhcp\"\ndns = \"10.0.0.1\"",
+            )
+            .replace(r#"disk_list = ["sda"]"#, r#"disk_list = ["sda", "sdb"]"#);
+
+        let diagnostics = validate_answer(&content, AnswerFormat::Toml, None).unwrap_err();
+        let found: Vec<(&str, Option<usize>)> = diagnostics
+            .iter()
+            .map(|diag| (diag.path.as_str(), diag.line))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                ("global.keyboard", Some(3)),
+                ("network.dns", Some(12)),
+                ("disk-setup.disk_list", Some(16)),
+            ]
+        );
+        assert_eq!(diagnostics[0].column, Some(12));
+    }
+
+    #[test]
+    fn collects_all_problems_within_section() {
+        let content = VALID_ANSWER
+            .replace(r#"keyboard = "de""#, r#"keyboard = "xx""#)
+            .replace(r#"country = "at""#, "country = 43")
+            .replace(
+                r#"timezone = "Europe/Vienna""#,
+                "timezone = \"Europe/Vienna\"\nfoo = 1",
+            );
+
+        let diagnostics = validate_answer(&content, AnswerFormat::Toml, None).unwrap_err();
+        let found: Vec<(&str, Option<usize>)> = diagnostics
+            .iter()
+            .map(|diag| (diag.path.as_str(), diag.line))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                ("global.keyboard", Some(3)),
+                ("global.country", Some(4)),
+                ("global.foo", Some(8)),
+            ]
+        );
+    }
+
+    #[test]
+    fn semantic_problems() {
+        let content = VALID_ANSWER
+            .replace("mail@no.invalid", "mail@example.invalid")
+            .replace(r#"filesystem = "ext4""#, r#"filesystem = "zfs""#);
+
+        let diagnostics = validate_answer(&content, AnswerFormat::Toml, None).unwrap_err();
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].path, "global.mailto");
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(6), Some(1))
+        );
+
+        // Missing keys are located at their parent section
+        assert_eq!(diagnostics[1].path, "disk-setup.zfs.raid");
+        assert_eq!(diagnostics[1].line, Some(13));
+    }
+
+    #[test]
+    fn lints() {
+        let content = VALID_ANSWER
+            .replace("mail@no.invalid", "root@example.com")
+            .replace(
+                r#"disk_list = ["sda"]"#,
+                "disk_list = [\"sda\"]\nlvm.hdsize = 4",
+            );
+        let answer = validate_answer(&content, AnswerFormat::Toml, None).unwrap();
+
+        let setup_info = SetupInfo::mocked();
+        let context = LintContext {
+            fetch_from: Some(FetchAnswerFrom::Http),
+            setup_info: Some(&setup_info),
+            ..Default::default()
+        };
+        let ids: Vec<LintId> = lint_answer(&content, AnswerFormat::Toml, &answer, &context)
+            .iter()
+            .map(|lint| lint.id)
+            .collect();
+
+        assert_eq!(
+            ids,
+            vec![
+                // Not present in the file, thus located at their section
+                LintId::NoRootSshKeys,
+                LintId::NoRebootOnError,
+                LintId::PlaceholderMailto,
+                LintId::PlainRootPassword,
+                LintId::SmallHdsize,
+            ]
+        );
+    }
+
+    #[test]
+    fn zfs_lints() {
+        let content = VALID_ANSWER
+            .replace("mail@no.invalid", "root@proxmox.com")
+            .replace(
+                r#"root_password = "12345678""#,
+                r#"root_password_hashed = "x""#,
+            )
+            .replace(r#"filesystem = "ext4""#, r#"filesystem = "zfs""#)
+            .replace(
+                r#"disk_list = ["sda"]"#,
+                "disk_list = [\"sda\", \"sdb\"]\nzfs.raid = \"raid1\"\nzfs.copies = 2",
+            );
+        let answer = validate_answer(&content, AnswerFormat::Toml, None).unwrap();
+
+        let lints = lint_answer(
+            &content,
+            AnswerFormat::Toml,
+            &answer,
+            &LintContext::default(),
+        );
+        let ids: Vec<LintId> = lints.iter().map(|lint| lint.id).collect();
+
+        assert_eq!(ids, vec![LintId::NoRootSshKeys, LintId::ZfsCopiesOnRaid]);
+        assert_eq!(lints[1].diagnostic.path, "disk-setup.zfs.copies");
+        assert_eq!(lints[1].diagnostic.line, Some(17));
+    }
+
+    #[test]
+    fn syntax_error() {
+        let diagnostics = validate_answer("[global\n", AnswerFormat::Toml, None).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(1));
+    }
+
+    const VALID_JSON_ANSWER: &str = r#"{
+  "global": {
+    "keyboard": "de",
+    "country": "at",
+    "fqdn": "pveauto.testinstall",
+    "mailto": "mail@no.invalid",
+    "timezone": "Europe/Vienna",
+    "root_password": "12345678",
+    "root_password_hashed": null
+  },
+  "network": { "source": "from-dhcp" },
+  "disk-setup": { "filesystem": "ext4", "disk_list": ["sda"] }
+}"#;
+
+    const VALID_YAML_ANSWER: &str = r#"
+global:
+  keyboard: de
+  country: at
+  fqdn: pveauto.testinstall
+  mailto: mail@no.invalid
+  timezone: Europe/Vienna
+  root_password: "12345678"
+network:
+  source: from-dhcp
+disk-setup:
+  filesystem: ext4
+  disk_list: [sda]
+"#;
+
+    #[test]
+    fn json_and_yaml_answers() {
+        for (content, format) in [
+            (VALID_JSON_ANSWER, AnswerFormat::Json),
+            (VALID_YAML_ANSWER, AnswerFormat::Yaml),
+        ] {
+            assert_eq!(AnswerFormat::detect(content), format);
+
+            let answer = validate_answer(content, format, None).unwrap();
+            assert_eq!(answer.global.fqdn.to_string(), "pveauto.testinstall");
+            assert_eq!(answer.global.root_password.as_deref(), Some("12345678"));
+
+            let content = content.replace("timezone", "time_zone");
+            let diagnostics = validate_answer(&content, format, None).unwrap_err();
+            let paths: Vec<&str> = diagnostics.iter().map(|d| d.path.as_str()).collect();
+            // The misspelled key is reported, as well as the then missing one
+            assert_eq!(paths, vec!["global", "global.time_zone"]);
+            assert_eq!(diagnostics[0].message, "missing field `timezone`");
+            assert_eq!(diagnostics[0].line, None);
+        }
+
+        let diagnostics =
+            validate_answer("{\n  \"global\": }", AnswerFormat::Json, None).unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, Some(2));
+
+        assert_eq!(AnswerFormat::detect(VALID_ANSWER), AnswerFormat::Toml);
+        assert_eq!(
+            AnswerFormat::from_content_type("application/yaml; charset=utf-8"),
+            Some(AnswerFormat::Yaml)
+        );
+        assert_eq!(AnswerFormat::from_content_type("text/plain"), None);
+    }
+
+    #[test]
+    fn multihost_answer() {
+        let content = VALID_ANSWER.replace(r#"fqdn = "pveauto.testinstall""#, "")
+            + r#"
+[[host]]
+match.dmi.system.serial = "ABC123"
+global.fqdn = "node1.testinstall"
+
+[[host]]
+match.mac = "bc:24:11:00:00:02"
+global.fqdn = "node2.testinstall"
+disk-setup.filesystem = "ntfs"
+"#;
+
+        let answer = validate_answer(&content, AnswerFormat::Toml, None).unwrap_err();
+        let messages: Vec<(&str, Option<usize>, &str)> = answer
+            .iter()
+            .map(|d| (d.path.as_str(), d.line, d.message.as_str()))
+            .collect();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].0, "disk-setup.filesystem");
+        assert_eq!(messages[0].1, Some(24));
+        assert!(messages[0].2.ends_with("(host #2)"));
+
+        let content = content.replace(r#"disk-setup.filesystem = "ntfs""#, "");
+        let answer = validate_answer(&content, AnswerFormat::Toml, None).unwrap();
+        assert_eq!(answer.global.fqdn.to_string(), "node1.testinstall");
+
+        let content = content.replace(r#"match.mac = "bc:24:11:00:00:02""#, "");
+        let diagnostics = validate_answer(&content, AnswerFormat::Toml, None).unwrap_err();
+        assert_eq!(diagnostics[0].path, "host.match");
+        assert!(diagnostics[0].message.ends_with("(host #2)"));
+    }
+}
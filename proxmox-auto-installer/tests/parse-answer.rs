@@ -64,7 +64,177 @@ fn run_named_test(name: &str) {
     pretty_assertions::assert_eq!(config, compare);
 }
 
+fn run_named_fail_parse_test(name: &str) {
+    let resource_path = get_test_resource_path().unwrap();
+    let (setup_info, locales, runtime_info, udev_info) = setup_test_basic(&resource_path);
+
+    let answer_path = resource_path.join(format!("parse_answer_fail/{name}.toml"));
+
+    let answer = get_answer(&answer_path).unwrap();
+    let err = match parse_answer(&answer, &udev_info, &runtime_info, &locales, &setup_info) {
+        Ok(_) => panic!("parsing answer file '{name}' unexpectedly succeeded"),
+        Err(err) => err,
+    };
+
+    let json_path = resource_path.join(format!("parse_answer_fail/{name}.json"));
+    let compare_raw = fs::read_to_string(&json_path).unwrap();
+    let compare: Value = serde_json::from_str(&compare_raw).unwrap();
+
+    pretty_assertions::assert_eq!(err.to_string(), compare["error"].as_str().unwrap());
+}
+
+/// Parses the named answer file, with the udev information of the test system modified by the
+/// given closure first.
+fn parse_with_udev_info(name: &str, modify: impl FnOnce(&mut UdevInfo)) -> Result<Value, String> {
+    let resource_path = get_test_resource_path().unwrap();
+    let (setup_info, locales, runtime_info, mut udev_info) = setup_test_basic(&resource_path);
+
+    modify(&mut udev_info);
+
+    let answer = get_answer(resource_path.join(format!("parse_answer/{name}.toml"))).unwrap();
+    parse_answer(&answer, &udev_info, &runtime_info, &locales, &setup_info)
+        .map(|config| serde_json::to_value(config).unwrap())
+        .map_err(|err| err.to_string())
+}
+
+/// Parses the named answer file, with the given disks of the test system marked as holding the
+/// installation medium and the answer file partition respectively.
+fn parse_with_installation_media(
+    name: &str,
+    boot_medium: &str,
+    answer_partition: &str,
+) -> Result<Value, String> {
+    parse_with_udev_info(name, |udev_info| {
+        let attributes = udev_info.disk_attributes.get_mut(boot_medium).unwrap();
+        attributes.is_boot_medium = Some(true);
+        let attributes = udev_info.disk_attributes.get_mut(answer_partition).unwrap();
+        attributes.has_answer_partition = Some(true);
+    })
+}
+
 mod tests {
+    use std::collections::BTreeMap;
+
+    use proxmox_auto_installer::utils::get_matched_udev_indexes;
+
+    use super::{parse_with_installation_media, parse_with_udev_info};
+
+    #[test]
+    fn filters_skip_installation_media() {
+        // Matches all disks but 4 and 5, of which 0 and 6 must not be installed to
+        let config = parse_with_installation_media("disk_match_any", "6", "0").unwrap();
+        let selected: Vec<&String> = config["disk_selection"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        assert_eq!(selected, ["1", "2", "3", "7", "8", "9"]);
+    }
+
+    #[test]
+    fn disk_list_rejects_installation_media() {
+        // Selects '/dev/sda', i.e. disk 6
+        let err = parse_with_installation_media("minimal", "6", "0").unwrap_err();
+        assert_eq!(
+            err,
+            "Cannot install to disk '/dev/sda', as it holds the installation medium"
+        );
+
+        let err = parse_with_installation_media("minimal", "0", "6").unwrap_err();
+        assert_eq!(
+            err,
+            "Cannot install to disk '/dev/sda', as it holds the answer file partition"
+        );
+
+        // Selects '/dev/sda' and '/dev/sdb', i.e. disks 6 and 7
+        let err = parse_with_installation_media("btrfs", "0", "7").unwrap_err();
+        assert_eq!(
+            err,
+            "Cannot install to disk '/dev/sdb', as it holds the answer file partition"
+        );
+    }
+
+    #[test]
+    fn disk_wipe_requires_known_transport() {
+        // Selects '/dev/nvme4n1', i.e. disk 4
+        let err = parse_with_udev_info("disk_wipe", |udev_info| {
+            udev_info.disk_attributes.get_mut("4").unwrap().transport = None;
+        })
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "Cannot wipe disk '/dev/nvme4n1' using 'nvme-sanitize', as its transport is unknown"
+        );
+    }
+
+    #[test]
+    fn disks_without_attributes_are_not_installable() {
+        // Selects '/dev/sda', i.e. disk 6
+        let err = parse_with_udev_info("minimal", |udev_info| {
+            udev_info.disk_attributes.remove("6");
+        })
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "Cannot install to disk '/dev/sda', as its attributes are unknown and \
+             'allow_removable' is not set"
+        );
+
+        // Matches all disks but 4 and 5
+        let config = parse_with_udev_info("disk_match_any", |udev_info| {
+            udev_info.disk_attributes.remove("0");
+            udev_info.disk_attributes.remove("7");
+        })
+        .unwrap();
+        let selected: Vec<&String> = config["disk_selection"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .collect();
+        assert_eq!(selected, ["1", "2", "3", "6", "8", "9"]);
+
+        // Allows removable disks, so disks without attributes are acceptable as well
+        let config = parse_with_udev_info("disk_match_removable_allowed", |udev_info| {
+            udev_info.disk_attributes.clear();
+        });
+        assert!(config.is_ok());
+    }
+
+    #[test]
+    fn filters_never_match_missing_properties() {
+        let devices = BTreeMap::from([
+            (
+                "0".to_owned(),
+                BTreeMap::from([
+                    ("DEVNAME".to_owned(), "/dev/sda".to_owned()),
+                    ("ID_MODEL".to_owned(), "SAMSUNG MZ7KM240".to_owned()),
+                    ("size".to_owned(), "240057409536".to_owned()),
+                ]),
+            ),
+            (
+                "1".to_owned(),
+                BTreeMap::from([("DEVNAME".to_owned(), "/dev/sdb".to_owned())]),
+            ),
+        ]);
+        let filter = |key: &str, value: &str| {
+            BTreeMap::from([
+                ("DEVNAME".to_owned(), "/dev/sd*".to_owned()),
+                (key.to_owned(), value.to_owned()),
+            ])
+        };
+
+        let matched = get_matched_udev_indexes(&filter("size", ">100G"), &devices, true);
+        assert_eq!(matched.unwrap(), ["0"]);
+
+        let matched = get_matched_udev_indexes(&filter("ID_MODEL", "!*SATADOM*"), &devices, true);
+        assert_eq!(matched.unwrap(), ["0"]);
+
+        // Under 'any', the missing property does not count as a match either
+        let filter = BTreeMap::from([("ID_MODEL".to_owned(), "!*SATADOM*".to_owned())]);
+        let matched = get_matched_udev_indexes(&filter, &devices, false);
+        assert_eq!(matched.unwrap(), ["0"]);
+    }
+
     mod parse_answer {
         use super::super::run_named_test;
 
@@ -79,18 +249,52 @@ mod tests {
         }
 
         declare_named_tests!(
+            bond,
+            bridge,
+            bridge_vlan1,
             btrfs,
             btrfs_raid_level_uppercase,
             disk_match,
             disk_match_all,
+            disk_match_attributes,
             disk_match_any,
+            disk_match_expressions,
+            disk_match_removable,
+            disk_match_removable_allowed,
+            disk_wipe,
+            dual_stack,
+            dual_stack_bond_vlan,
+            existing_data_refuse,
+            existing_data_wipe,
             first_boot,
             hashed_root_password,
             minimal,
+            network_from_cmdline,
             nic_matching,
+            nic_selection_fastest,
+            nic_selection_mac,
             specific_nic,
+            vlan,
             zfs,
             zfs_raid_level_uppercase,
         );
     }
+    mod parse_answer_fail {
+        use super::super::run_named_fail_parse_test;
+
+        macro_rules! declare_named_fail_parse_tests {
+            ($name:ident, $( $rest:ident ),* $(,)?) => { declare_named_fail_parse_tests!($name); declare_named_fail_parse_tests!($( $rest ),+); };
+            ($name:ident) => {
+                #[test]
+                fn $name() {
+                    run_named_fail_parse_test(&stringify!($name));
+                }
+            };
+        }
+
+        declare_named_fail_parse_tests!(
+            disk_wipe_ata_secure_erase_nvme,
+            disk_wipe_nvme_format_sata,
+        );
+    }
 }
@@ -1,12 +1,15 @@
 use anyhow::{bail, Result};
 use log::info;
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::{
     fs::{self, read_to_string},
     process::Command,
 };
 
-use proxmox_auto_installer::{sysinfo::SysInfo, utils::HttpOptions};
+use proxmox_auto_installer::{
+    answer::AnswerFormat, include::AnswerSource, sysinfo::SysInfo, utils::HttpOptions,
+};
 
 static ANSWER_URL_SUBDOMAIN: &str = "proxmox-auto-installer";
 static ANSWER_CERT_FP_SUBDOMAIN: &str = "proxmox-auto-installer-cert-fingerprint";
@@ -29,8 +32,27 @@ static DHCP_URL_OPTION: &str = "proxmox-auto-installer-manifest-url";
 static DHCP_CERT_FP_OPTION: &str = "proxmox-auto-installer-cert-fingerprint";
 static DHCP_LEASE_FILE: &str = "/var/lib/dhcp/dhclient.leases";
 
+// For IPv6-only networks, the same options are requested via DHCPv6, using the otherwise
+// unassigned option codes 250 and 251 [0].
+//
+// e.g. /etc/dhcp/dhclient.conf:
+// ```
+// option dhcp6.proxmox-auto-installer-manifest-url code 250 = text;
+// option dhcp6.proxmox-auto-installer-cert-fingerprint code 251 = text;
+// also request dhcp6.proxmox-auto-installer-manifest-url, dhcp6.proxmox-auto-installer-cert-fingerprint;
+// ```
+//
+// The results will end up in the /var/lib/dhcp/dhclient6.leases file.
+//
+// [0] https://www.iana.org/assignments/dhcpv6-parameters/dhcpv6-parameters.xhtml
+static DHCP6_URL_OPTION: &str = "dhcp6.proxmox-auto-installer-manifest-url";
+static DHCP6_CERT_FP_OPTION: &str = "dhcp6.proxmox-auto-installer-cert-fingerprint";
+static DHCP6_LEASE_FILE: &str = "/var/lib/dhcp/dhclient6.leases";
+
+static RESOLV_CONF: &str = "/etc/resolv.conf";
+
 /// Metadata of the HTTP POST payload, such as schema version of the document.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 struct HttpFetchInfoSchema {
     /// major.minor version describing the schema version of this document, in a semanticy-version
@@ -40,11 +62,12 @@ struct HttpFetchInfoSchema {
     /// field.
     /// minor: Incremented when adding functionality in a backwards-compatible matter, e.g.
     /// adding a new field.
+    #[schemars(extend("const" = HttpFetchInfoSchema::SCHEMA_VERSION))]
     version: String,
 }
 
 impl HttpFetchInfoSchema {
-    const SCHEMA_VERSION: &str = "1.0";
+    const SCHEMA_VERSION: &str = "1.1";
 }
 
 impl Default for HttpFetchInfoSchema {
@@ -59,7 +82,12 @@ impl Default for HttpFetchInfoSchema {
 ///
 /// NOTE: The format is versioned through `schema.version` (`$schema.version` in the
 /// resulting JSON), ensure you update it when this struct or any of its members gets modified.
-#[derive(Serialize)]
+/// The published JSON schema in `schema/http-fetch-payload.json` is checked against this struct by the
+/// tests below.
+#[derive(Serialize, JsonSchema)]
+#[schemars(
+    description = "All data sent as request payload with the answerfile fetch POST request."
+)]
 #[serde(rename_all = "kebab-case")]
 struct HttpFetchPayload {
     /// Metadata for the answerfile fetch payload
@@ -95,11 +123,17 @@ pub struct FetchFromHTTP;
 impl FetchFromHTTP {
     /// Will try to fetch the answer.toml by sending a HTTP POST request. The URL can be configured
     /// either via DHCP or DNS or preconfigured in the ISO.
-    /// If the URL is not defined in the ISO, it will first check DHCP options. The SSL certificate
+    /// If the URL is not defined in the ISO, it will first check DHCP options, of DHCPv4 and then
+    /// DHCPv6, and DNS TXT records of the search domains afterwards. The SSL certificate
     /// needs to be either trusted by the root certs or a SHA256 fingerprint needs to be provided.
     /// The SHA256 SSL fingerprint can either be defined in the ISO, as DHCP option, or as DNS TXT
     /// record. If provided, the fingerprint provided in the ISO has preference.
-    pub fn get_answer(settings: &HttpOptions) -> Result<String> {
+    ///
+    /// Returns the answer file together with its format, if the server declared a known one via
+    /// the `Content-Type` header, and its location.
+    pub fn get_answer(
+        settings: &HttpOptions,
+    ) -> Result<(String, Option<AnswerFormat>, AnswerSource)> {
         let mut fingerprint: Option<String> = match settings.cert_fingerprint.clone() {
             Some(fp) => {
                 info!("SSL fingerprint provided through ISO.");
@@ -130,33 +164,168 @@ impl FetchFromHTTP {
         let payload = HttpFetchPayload::as_json()?;
 
         info!("Sending POST request to '{answer_url}'.");
-        let answer =
-            proxmox_installer_common::http::post(&answer_url, fingerprint.as_deref(), payload)?;
-        Ok(answer)
+        let (answer, content_type) = proxmox_installer_common::http::post_with_content_type(
+            &answer_url,
+            fingerprint.as_deref(),
+            payload,
+        )?;
+
+        let format = content_type
+            .as_deref()
+            .and_then(AnswerFormat::from_content_type);
+        match (&content_type, format) {
+            (Some(content_type), None) => {
+                info!("Unknown content type '{content_type}', detecting answer file format.")
+            }
+            (_, Some(format)) => info!("Received answer file in {format} format."),
+            (None, None) => {}
+        }
+
+        let source = AnswerSource::Url {
+            url: answer_url,
+            fingerprint,
+        };
+        Ok((answer, format, source))
     }
 
-    /// Fetches search domain from resolv.conf file
-    fn get_search_domain() -> Result<String> {
+    /// Returns the DNS search domains, along with the DNS servers to query explicitly.
+    ///
+    /// Both are taken from resolv.conf if possible. If it lacks either of them, e.g. in
+    /// IPv6-only networks without DHCPv6, they are taken from the DNSSL and RDNSS options of
+    /// router advertisements instead. The DNS servers are only returned in the latter case, as
+    /// the ones from resolv.conf are used anyway.
+    fn get_dns_config() -> Result<(Vec<String>, Vec<String>)> {
         info!("Retrieving default search domain.");
-        for line in read_to_string("/etc/resolv.conf")?.lines() {
-            if let Some((key, value)) = line.split_once(' ') {
-                if key == "search" {
-                    return Ok(value.trim().into());
+        let resolv_conf = read_to_string(RESOLV_CONF).unwrap_or_default();
+        let (search_domains, dns_servers) = Self::parse_resolv_conf(&resolv_conf);
+        if !search_domains.is_empty() && !dns_servers.is_empty() {
+            return Ok((search_domains, Vec::new()));
+        }
+
+        info!("Incomplete DNS configuration in resolv.conf, checking router advertisements.");
+        let (ra_search_domains, ra_dns_servers) = Self::query_router_advertisements();
+        let search_domains = if search_domains.is_empty() {
+            ra_search_domains
+        } else {
+            search_domains
+        };
+        let dns_servers = if dns_servers.is_empty() {
+            ra_dns_servers
+        } else {
+            Vec::new()
+        };
+
+        if search_domains.is_empty() {
+            bail!("Could not find search domain in resolv.conf or router advertisements.");
+        }
+        Ok((search_domains, dns_servers))
+    }
+
+    /// Parses the search domains and DNS servers from the contents of resolv.conf.
+    fn parse_resolv_conf(content: &str) -> (Vec<String>, Vec<String>) {
+        let mut search_domains = Vec::new();
+        let mut dns_servers = Vec::new();
+        for line in content.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("search") => search_domains = fields.map(String::from).collect(),
+                Some("nameserver") => dns_servers.extend(fields.next().map(String::from)),
+                _ => {}
+            }
+        }
+        (search_domains, dns_servers)
+    }
+
+    /// Solicits router advertisements on all network interfaces and returns the search domains
+    /// and DNS servers announced in them.
+    fn query_router_advertisements() -> (Vec<String>, Vec<String>) {
+        let mut search_domains = Vec::new();
+        let mut dns_servers = Vec::new();
+
+        let mut interfaces: Vec<String> = fs::read_dir("/sys/class/net")
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name != "lo")
+            .collect();
+        interfaces.sort();
+
+        for iface in interfaces {
+            let output = match Command::new("rdisc6")
+                .args(["-1", "-r", "2", "-w", "1000"])
+                .arg(&iface)
+                .output()
+            {
+                Ok(output) if output.status.success() => output,
+                Ok(_) => continue,
+                Err(err) => {
+                    info!("Could not solicit router advertisements: {err}");
+                    break;
+                }
+            };
+
+            let (domains, servers) =
+                Self::parse_router_advertisement(&String::from_utf8_lossy(&output.stdout), &iface);
+            for domain in domains {
+                if !search_domains.contains(&domain) {
+                    search_domains.push(domain);
+                }
+            }
+            for server in servers {
+                if !dns_servers.contains(&server) {
+                    dns_servers.push(server);
+                }
+            }
+        }
+
+        if !search_domains.is_empty() {
+            info!(
+                "Found search domains via router advertisements: '{}'",
+                search_domains.join("', '")
+            );
+        }
+        (search_domains, dns_servers)
+    }
+
+    /// Parses the DNSSL and RDNSS options from the output of `rdisc6` for an interface.
+    /// Link-local DNS servers are scoped to the interface, so that they can be queried directly.
+    fn parse_router_advertisement(output: &str, iface: &str) -> (Vec<String>, Vec<String>) {
+        let mut search_domains = Vec::new();
+        let mut dns_servers = Vec::new();
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key.trim() {
+                "DNS search list" => {
+                    search_domains.extend(value.split_whitespace().map(String::from))
+                }
+                "Recursive DNS server" => {
+                    dns_servers.extend(value.split_whitespace().map(|server| {
+                        if server.starts_with("fe80:") {
+                            format!("{server}%{iface}")
+                        } else {
+                            server.to_owned()
+                        }
+                    }))
                 }
+                _ => {}
             }
         }
-        bail!("Could not find search domain in resolv.conf.");
+        (search_domains, dns_servers)
     }
 
-    /// Runs a TXT DNS query on the domain provided
-    fn query_txt_record(query: String) -> Result<String> {
+    /// Runs a TXT DNS query on the domain provided, using the given DNS server instead of the
+    /// ones from resolv.conf if set.
+    fn query_txt_record(query: String, dns_server: Option<&str>) -> Result<String> {
         info!("Querying TXT record for '{query}'");
         let url: String;
-        match Command::new("dig")
-            .args(["txt", "+short"])
-            .arg(&query)
-            .output()
-        {
+        let mut command = Command::new("dig");
+        command.args(["txt", "+short"]);
+        if let Some(dns_server) = dns_server {
+            command.arg(format!("@{dns_server}"));
+        }
+        match command.arg(&query).output() {
             Ok(output) => {
                 if output.status.success() {
                     url = String::from_utf8(output.stdout)?
@@ -179,39 +348,101 @@ impl FetchFromHTTP {
         Ok(url)
     }
 
+    /// Runs a TXT DNS query on the domain provided, trying each of the given DNS servers in turn,
+    /// or the ones from resolv.conf if none are given.
+    fn query_txt_record_from(query: String, dns_servers: &[String]) -> Result<String> {
+        if dns_servers.is_empty() {
+            return Self::query_txt_record(query, None);
+        }
+
+        let mut last_err = None;
+        for dns_server in dns_servers {
+            match Self::query_txt_record(query.clone(), Some(dns_server)) {
+                Ok(record) => return Ok(record),
+                Err(err) => {
+                    info!("{err}");
+                    last_err = Some(err);
+                }
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
     /// Tries to fetch answer URL and SSL fingerprint info from DNS
     fn fetch_dns(mut fingerprint: Option<String>) -> Result<(String, Option<String>)> {
-        let search_domain = Self::get_search_domain()?;
+        let (search_domains, dns_servers) = Self::get_dns_config()?;
 
-        let answer_url =
-            match Self::query_txt_record(format!("{ANSWER_URL_SUBDOMAIN}.{search_domain}")) {
-                Ok(url) => url,
-                Err(err) => bail!("{err}"),
-            };
+        let mut result = None;
+        for search_domain in &search_domains {
+            match Self::query_txt_record_from(
+                format!("{ANSWER_URL_SUBDOMAIN}.{search_domain}"),
+                &dns_servers,
+            ) {
+                Ok(url) => {
+                    result = Some((url, search_domain));
+                    break;
+                }
+                Err(err) => info!("{err}"),
+            }
+        }
+        let Some((answer_url, search_domain)) = result else {
+            bail!("No DNS TXT record found for fetch URL.");
+        };
 
         if fingerprint.is_none() {
-            fingerprint =
-                match Self::query_txt_record(format!("{ANSWER_CERT_FP_SUBDOMAIN}.{search_domain}"))
-                {
-                    Ok(fp) => Some(fp),
-                    Err(err) => {
-                        info!("{err}");
-                        None
-                    }
-                };
+            fingerprint = match Self::query_txt_record_from(
+                format!("{ANSWER_CERT_FP_SUBDOMAIN}.{search_domain}"),
+                &dns_servers,
+            ) {
+                Ok(fp) => Some(fp),
+                Err(err) => {
+                    info!("{err}");
+                    None
+                }
+            };
         }
         Ok((answer_url, fingerprint))
     }
 
-    /// Tries to fetch answer URL and SSL fingerprint info from DHCP options
-    fn fetch_dhcp(mut fingerprint: Option<String>) -> Result<(String, Option<String>)> {
+    /// Tries to fetch answer URL and SSL fingerprint info from DHCP options, of a DHCPv4 lease
+    /// or, if there is none, a DHCPv6 lease.
+    fn fetch_dhcp(fingerprint: Option<String>) -> Result<(String, Option<String>)> {
         info!("Checking DHCP options.");
-        let leases = fs::read_to_string(DHCP_LEASE_FILE)?;
+        let v4 = fs::read_to_string(DHCP_LEASE_FILE)
+            .map(|leases| Self::parse_dhcp_leases(&leases, DHCP_URL_OPTION, DHCP_CERT_FP_OPTION));
+        let (answer_url, dhcp_fingerprint) = match v4 {
+            Ok((Some(url), fp)) => (url, fp),
+            _ => {
+                info!("Checking DHCPv6 options.");
+                match fs::read_to_string(DHCP6_LEASE_FILE).map(|leases| {
+                    Self::parse_dhcp_leases(&leases, DHCP6_URL_OPTION, DHCP6_CERT_FP_OPTION)
+                }) {
+                    Ok((Some(url), fp)) => (url, fp),
+                    _ => bail!("No DHCP option found for fetch URL."),
+                }
+            }
+        };
+        info!("Found URL for answer in DHCP option: '{answer_url}'");
+
+        let fingerprint = fingerprint.or(dhcp_fingerprint);
+        if let Some(fp) = fingerprint.clone() {
+            info!("Found SSL Fingerprint via DHCP: '{fp}'");
+        }
 
+        Ok((answer_url, fingerprint))
+    }
+
+    /// Parses the answer URL and SSL fingerprint options from the contents of a DHCP lease file.
+    fn parse_dhcp_leases(
+        leases: &str,
+        url_option: &str,
+        fp_option: &str,
+    ) -> (Option<String>, Option<String>) {
         let mut answer_url: Option<String> = None;
+        let mut fingerprint: Option<String> = None;
 
-        let url_match = format!("option {DHCP_URL_OPTION}");
-        let fp_match = format!("option {DHCP_CERT_FP_OPTION}");
+        let url_match = format!("option {url_option}");
+        let fp_match = format!("option {fp_option}");
 
         for line in leases.lines() {
             if answer_url.is_none() && line.trim().starts_with(url_match.as_str()) {
@@ -222,19 +453,7 @@ impl FetchFromHTTP {
             }
         }
 
-        let answer_url = match answer_url {
-            None => bail!("No DHCP option found for fetch URL."),
-            Some(url) => {
-                info!("Found URL for answer in DHCP option: '{url}'");
-                url
-            }
-        };
-
-        if let Some(fp) = fingerprint.clone() {
-            info!("Found SSL Fingerprint via DHCP: '{fp}'");
-        }
-
-        Ok((answer_url, fingerprint))
+        (answer_url, fingerprint)
     }
 
     /// Clean DHCP option string
@@ -243,3 +462,117 @@ impl FetchFromHTTP {
         value.map(|value| String::from(&value[1..value.len() - 2]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use proxmox_auto_installer::utils::payload_json_schema;
+
+    use super::{
+        FetchFromHTTP, HttpFetchInfoSchema, HttpFetchPayload, DHCP6_CERT_FP_OPTION,
+        DHCP6_URL_OPTION, DHCP_URL_OPTION,
+    };
+
+    fn published_schema_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("schema/http-fetch-payload.json")
+    }
+
+    #[test]
+    fn payload_schema_is_up_to_date() {
+        let path = published_schema_path();
+        let current = serde_json::to_value(payload_json_schema::<HttpFetchPayload>()).unwrap();
+        let published: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        if current == published {
+            return;
+        }
+
+        assert_ne!(
+            published["$defs"]["HttpFetchInfoSchema"]["properties"]["version"]["const"],
+            HttpFetchInfoSchema::SCHEMA_VERSION,
+            "HttpFetchPayload was modified without bumping HttpFetchInfoSchema::SCHEMA_VERSION"
+        );
+        panic!("schema version was bumped, update {path:?} by running 'make update-schemas'");
+    }
+
+    #[test]
+    #[ignore = "writes the published schema, run via 'make update-schemas'"]
+    fn update_payload_schema() {
+        let schema = serde_json::to_value(payload_json_schema::<HttpFetchPayload>()).unwrap();
+        let content = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        fs::write(published_schema_path(), content).unwrap();
+    }
+
+    #[test]
+    fn dhcp_options() {
+        let leases = r#"default-duid "\000\001\000\001.\216\305\311\274$\021\000\000\001";
+lease6 {
+  interface "eno1";
+  ia-na 0b:24:11:00 {
+    starts 1760000000;
+    iaaddr 2001:db8::10 {
+      starts 1760000000;
+      preferred-life 7200;
+      max-life 7200;
+    }
+  }
+  option dhcp6.name-servers 2001:db8::53;
+  option dhcp6.proxmox-auto-installer-manifest-url "https://[2001:db8::80]/answer";
+  option dhcp6.proxmox-auto-installer-cert-fingerprint "AB:CD:EF";
+}
+"#;
+        assert_eq!(
+            FetchFromHTTP::parse_dhcp_leases(leases, DHCP6_URL_OPTION, DHCP6_CERT_FP_OPTION),
+            (
+                Some("https://[2001:db8::80]/answer".to_owned()),
+                Some("AB:CD:EF".to_owned())
+            )
+        );
+        assert_eq!(
+            FetchFromHTTP::parse_dhcp_leases(leases, DHCP_URL_OPTION, DHCP6_CERT_FP_OPTION).0,
+            None
+        );
+    }
+
+    #[test]
+    fn dns_config() {
+        assert_eq!(
+            FetchFromHTTP::parse_resolv_conf(
+                "# generated\nsearch example.com example.org\nnameserver 2001:db8::53\n"
+            ),
+            (
+                vec!["example.com".to_owned(), "example.org".to_owned()],
+                vec!["2001:db8::53".to_owned()]
+            )
+        );
+
+        let output = "Soliciting ff02::2 (ff02::2) on eno1...
+
+Hop limit                 :           64 (      0x40)
+Stateful address conf.    :           No
+Stateful other conf.      :           No
+Router lifetime           :         1800 (0x00000708) seconds
+ Prefix                   : 2001:db8::/64
+  On-link                 :          Yes
+ Recursive DNS server     : 2001:db8::53
+ Recursive DNS server     : fe80::1
+  DNS server lifetime     :          600 (0x00000258) seconds
+ DNS search list          : example.com
+  DNS search list lifetime:          600 (0x00000258) seconds
+ Source link-layer address: BC:24:11:00:00:01
+ from fe80::1
+";
+        assert_eq!(
+            FetchFromHTTP::parse_router_advertisement(output, "eno1"),
+            (
+                vec!["example.com".to_owned()],
+                vec!["2001:db8::53".to_owned(), "fe80::1%eno1".to_owned()]
+            )
+        );
+    }
+}
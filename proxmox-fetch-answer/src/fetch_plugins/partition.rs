@@ -13,18 +13,18 @@ static DISK_BY_ID_PATH: &str = "/dev/disk/by-label";
 pub struct FetchFromPartition;
 
 impl FetchFromPartition {
-    /// Returns the contents of the answer file
-    pub fn get_answer(part_label: &str) -> Result<String> {
+    /// Returns the contents of the answer file, along with its path
+    pub fn get_answer(part_label: &str) -> Result<(String, PathBuf)> {
         info!("Checking for answer file on partition.");
 
         let mut mount_path = PathBuf::from(mount_proxmoxinst_part(part_label)?);
         mount_path.push(ANSWER_FILE);
-        let answer = fs::read_to_string(mount_path)
+        let answer = fs::read_to_string(&mount_path)
             .map_err(|err| format_err!("failed to read answer file - {err}"))?;
 
         info!("Found answer file on partition.");
 
-        Ok(answer)
+        Ok((answer, mount_path))
     }
 }
 
@@ -5,6 +5,8 @@ use anyhow::{bail, format_err, Result};
 use log::{error, info, LevelFilter};
 
 use proxmox_auto_installer::{
+    answer::AnswerFormat,
+    include::{resolve_includes, AnswerSource},
     log::AutoInstLogger,
     utils::{AutoInstSettings, FetchAnswerFrom, HttpOptions},
 };
@@ -12,6 +14,7 @@ use proxmox_auto_installer::{
 use fetch_plugins::{http::FetchFromHTTP, partition::FetchFromPartition};
 
 mod fetch_plugins;
+mod network;
 
 static LOGGER: AutoInstLogger = AutoInstLogger;
 static AUTOINST_MODE_FILE: &str = "/cdrom/auto-installer-mode.toml";
@@ -40,19 +43,23 @@ pub fn init_log() -> Result<()> {
         .map_err(|err| format_err!(err))
 }
 
-fn fetch_answer(install_settings: &AutoInstSettings) -> Result<String> {
+/// Fetches the answer file, along with its format if it was declared by the source and its
+/// location, which included answer files are resolved relative to.
+fn fetch_answer(
+    install_settings: &AutoInstSettings,
+) -> Result<(String, Option<AnswerFormat>, AnswerSource)> {
     info!("Fetching answer file in mode {:?}:", &install_settings.mode);
     match install_settings.mode {
         FetchAnswerFrom::Iso => {
             let answer_path = PathBuf::from("/cdrom/answer.toml");
-            match fs::read_to_string(answer_path) {
-                Ok(answer) => return Ok(answer),
+            match fs::read_to_string(&answer_path) {
+                Ok(answer) => return Ok((answer, None, AnswerSource::File(answer_path))),
                 Err(err) => info!("Fetching answer file from ISO failed: {err}"),
             }
         }
         FetchAnswerFrom::Partition => {
             match FetchFromPartition::get_answer(&install_settings.partition_label) {
-                Ok(answer) => return Ok(answer),
+                Ok((answer, path)) => return Ok((answer, None, AnswerSource::File(path))),
                 Err(err) => info!("Fetching answer file from partition failed: {err}"),
             }
         }
@@ -121,9 +128,24 @@ fn do_main() -> Result<()> {
             .map_err(|err| format_err!("Failed to parse '{AUTOINST_MODE_FILE}': {err}"))?
     };
 
-    let answer = fetch_answer(&install_settings).map_err(|err| format_err!("Aborting: {err}"))?;
+    network::setup_from_cmdline().map_err(|err| {
+        format_err!("Aborting: failed to set up network from kernel commandline: {err}")
+    })?;
+
+    let (answer, format, source) =
+        fetch_answer(&install_settings).map_err(|err| format_err!("Aborting: {err}"))?;
     info!("queried answer file for automatic installation successfully");
 
+    // The answer file is passed on in TOML, as the declared format would get lost otherwise
+    let format = format.unwrap_or_else(|| AnswerFormat::detect(&answer));
+    let answer = format
+        .to_toml(&answer)
+        .map_err(|diag| format_err!("Aborting: failed to parse {format} answer file: {diag}"))?;
+
+    let answer = resolve_includes(&answer, &source)
+        .map_err(|err| format_err!("Aborting: failed to include answer files: {err}"))?
+        .unwrap_or(answer);
+
     println!("{answer}");
 
     Ok(())
@@ -0,0 +1,134 @@
+use anyhow::{bail, format_err, Result};
+use log::info;
+use std::{
+    fs,
+    path::Path,
+    process::Command,
+    thread,
+    time::{Duration, Instant},
+};
+
+use proxmox_installer_common::cmdline::{self, CmdlineNetwork};
+
+/// How long to wait for any interface to get a link after bringing them up.
+const CARRIER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Brings up the static network configuration from the kernel commandline, if there is one, so
+/// that the answer file can be fetched without DHCP. The address is configured on the VLAN given
+/// on the kernel commandline, if any, instead of the untagged network.
+pub fn setup_from_cmdline() -> Result<()> {
+    let Some(network) = CmdlineNetwork::from_proc().map_err(|err| format_err!("{err}"))? else {
+        return Ok(());
+    };
+    let vlan = cmdline::vlan_from_proc().map_err(|err| format_err!("{err}"))?;
+
+    let mut ifname = match &network.interface {
+        Some(name) => {
+            ip(&["link", "set", "dev", name, "up"])?;
+            name.clone()
+        }
+        None => find_interface_with_carrier()?,
+    };
+
+    if let Some(vlan) = vlan {
+        let parent = ifname;
+        ifname = format!("{parent}.{vlan}");
+        if !Path::new("/sys/class/net").join(&ifname).exists() {
+            ip(&[
+                "link",
+                "add",
+                "link",
+                &parent,
+                "name",
+                &ifname,
+                "type",
+                "vlan",
+                "id",
+                &vlan.to_string(),
+            ])?;
+        }
+        ip(&["link", "set", "dev", &ifname, "up"])?;
+    }
+
+    info!(
+        "Configuring address {} with gateway {} on '{ifname}' from kernel commandline.",
+        network.address, network.gateway
+    );
+    let family = if network.address.is_ipv4() {
+        "-4"
+    } else {
+        "-6"
+    };
+    ip(&[
+        family,
+        "address",
+        "replace",
+        &network.address.to_string(),
+        "dev",
+        &ifname,
+    ])?;
+    ip(&[
+        family,
+        "route",
+        "replace",
+        "default",
+        "via",
+        &network.gateway.to_string(),
+        "dev",
+        &ifname,
+    ])?;
+
+    let resolv_conf: String = network
+        .dns_servers
+        .iter()
+        .map(|dns| format!("nameserver {dns}\n"))
+        .collect();
+    fs::write("/etc/resolv.conf", resolv_conf)?;
+
+    Ok(())
+}
+
+/// Brings up all physical interfaces and returns the first one with a link.
+fn find_interface_with_carrier() -> Result<String> {
+    let mut interfaces = Vec::new();
+    for entry in fs::read_dir("/sys/class/net")? {
+        let path = entry?.path();
+        if !path.join("device").exists() || path.join("wireless").exists() {
+            continue;
+        }
+        if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            interfaces.push(name.to_owned());
+        }
+    }
+    interfaces.sort();
+
+    for name in &interfaces {
+        ip(&["link", "set", "dev", name, "up"])?;
+    }
+
+    let start = Instant::now();
+    while start.elapsed() < CARRIER_TIMEOUT {
+        let carrier = interfaces.iter().find(|name| {
+            fs::read_to_string(format!("/sys/class/net/{name}/carrier"))
+                .is_ok_and(|carrier| carrier.trim() == "1")
+        });
+        if let Some(name) = carrier {
+            return Ok(name.clone());
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+
+    bail!("no network interface with a link found");
+}
+
+fn ip(args: &[&str]) -> Result<()> {
+    let output = Command::new("ip").args(args).output()?;
+    if !output.status.success() {
+        bail!(
+            "'ip {}' failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
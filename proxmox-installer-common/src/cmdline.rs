@@ -0,0 +1,258 @@
+//! Static network configuration passed on the kernel commandline, for sites without DHCP.
+//!
+//! The configuration can either be given as separate parameters:
+//!
+//! ```text
+//! ip=192.0.2.10/24 gateway=192.0.2.1 nameserver=192.0.2.53 nameserver=192.0.2.54
+//! ```
+//!
+//! or as a single `proxmox-net` parameter, which additionally allows to select the interface:
+//!
+//! ```text
+//! proxmox-net=ip=192.0.2.10/24,gateway=192.0.2.1,nameserver=192.0.2.53,interface=eno1
+//! ```
+//!
+//! If `proxmox-net` is given, the separate parameters are ignored. `ip=` parameters without a
+//! prefix length, e.g. `ip=dhcp` or the kernel's own `ip=<client-ip>:<server-ip>:...` format,
+//! are not considered a static configuration.
+//!
+//! Independent of that, `proxmox-vlan=<id>` makes the installation environment obtain its DHCP
+//! lease, or configure its static address, on the given tagged VLAN, e.g. for fetching the answer
+//! file.
+
+use std::net::IpAddr;
+
+use crate::net_checks::{check_addresses, check_dns_servers, check_gateways};
+use crate::options::VLAN_ID_RANGE;
+use crate::utils::CidrAddress;
+
+/// Name of the kernel commandline parameter holding the whole network configuration.
+pub const PROXMOX_NET_PARAM: &str = "proxmox-net";
+
+/// Name of the kernel commandline parameter holding the VLAN ID of the installation environment.
+pub const PROXMOX_VLAN_PARAM: &str = "proxmox-vlan";
+
+/// Reads the VLAN ID the installation environment uses from the kernel commandline of the
+/// running system. Returns `None` if there is none.
+pub fn vlan_from_proc() -> Result<Option<u16>, String> {
+    let cmdline = std::fs::read_to_string("/proc/cmdline")
+        .map_err(|err| format!("Failed to read kernel commandline: {err}"))?;
+    parse_vlan(&cmdline)
+}
+
+/// Parses the VLAN ID the installation environment uses from a kernel commandline. Returns
+/// `None` if there is none.
+///
+/// # Arguments
+///
+/// * `cmdline` - The kernel commandline, e.g. the contents of `/proc/cmdline`.
+pub fn parse_vlan(cmdline: &str) -> Result<Option<u16>, String> {
+    let Some(value) = cmdline
+        .split_whitespace()
+        .filter_map(|param| param.split_once('='))
+        .filter(|(key, _)| *key == PROXMOX_VLAN_PARAM)
+        .map(|(_, value)| value)
+        .next_back()
+    else {
+        return Ok(None);
+    };
+
+    value
+        .parse::<u16>()
+        .ok()
+        .filter(|vlan| VLAN_ID_RANGE.contains(vlan))
+        .map(Some)
+        .ok_or_else(|| {
+            format!(
+                "Invalid VLAN ID '{value}' for '{PROXMOX_VLAN_PARAM}', must be between {} and {}.",
+                VLAN_ID_RANGE.start(),
+                VLAN_ID_RANGE.end()
+            )
+        })
+}
+
+/// Static network configuration of the installation environment, as read from the kernel
+/// commandline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CmdlineNetwork {
+    /// Interface to configure, the first one with a link is used if unset.
+    pub interface: Option<String>,
+    pub address: CidrAddress,
+    pub gateway: IpAddr,
+    /// DNS servers in order of preference.
+    pub dns_servers: Vec<IpAddr>,
+}
+
+impl CmdlineNetwork {
+    /// Reads the static network configuration from the kernel commandline of the running system.
+    /// Returns `None` if there is none.
+    pub fn from_proc() -> Result<Option<Self>, String> {
+        let cmdline = std::fs::read_to_string("/proc/cmdline")
+            .map_err(|err| format!("Failed to read kernel commandline: {err}"))?;
+        Self::parse(&cmdline)
+    }
+
+    /// Parses the static network configuration from a kernel commandline. Returns `None` if
+    /// there is none.
+    ///
+    /// # Arguments
+    ///
+    /// * `cmdline` - The kernel commandline, e.g. the contents of `/proc/cmdline`.
+    pub fn parse(cmdline: &str) -> Result<Option<Self>, String> {
+        let params: Vec<(&str, &str)> = cmdline
+            .split_whitespace()
+            .filter_map(|param| param.split_once('='))
+            .collect();
+
+        let proxmox_net = params
+            .iter()
+            .rev()
+            .find(|(key, _)| *key == PROXMOX_NET_PARAM)
+            .copied();
+        let options: Vec<(&str, &str)> = match proxmox_net {
+            Some((_, value)) => value
+                .split(',')
+                .map(|option| {
+                    option.split_once('=').ok_or_else(|| {
+                        format!("Invalid option '{option}' in '{PROXMOX_NET_PARAM}' parameter.")
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            None => params
+                .into_iter()
+                .filter(|(key, value)| *key != "ip" || value.contains('/'))
+                .collect(),
+        };
+
+        let mut interface = None;
+        let mut address = None;
+        let mut gateway = None;
+        let mut dns_servers = Vec::new();
+
+        for (key, value) in options {
+            let parse_ip = || {
+                value
+                    .parse::<IpAddr>()
+                    .map_err(|_| format!("Invalid address '{value}' for '{key}'."))
+            };
+
+            match key {
+                "ip" => {
+                    address = Some(value.parse::<CidrAddress>().map_err(|_| {
+                        format!("'{value}' is not a valid address in CIDR notation.")
+                    })?)
+                }
+                "gateway" => gateway = Some(parse_ip()?),
+                "nameserver" => dns_servers.push(parse_ip()?),
+                "interface" if proxmox_net.is_some() => interface = Some(value.to_owned()),
+                _ if proxmox_net.is_some() => {
+                    return Err(format!(
+                        "Unknown option '{key}' in '{PROXMOX_NET_PARAM}' parameter."
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        let Some(address) = address else {
+            if gateway.is_some() || interface.is_some() {
+                return Err("Static network configuration is missing the address ('ip').".into());
+            }
+            // Other parameters, e.g. `nameserver`, are commonly used for other purposes too
+            return Ok(None);
+        };
+        let gateway =
+            gateway.ok_or("Static network configuration is missing the gateway ('gateway').")?;
+        if dns_servers.is_empty() {
+            return Err(
+                "Static network configuration is missing a DNS server ('nameserver').".into(),
+            );
+        }
+
+        let addresses = [address.clone()];
+        let problems: Vec<String> = check_addresses(&addresses)
+            .into_iter()
+            .chain(check_gateways(&addresses, &[gateway]))
+            .chain(check_dns_servers(&addresses, &dns_servers))
+            .collect();
+        if !problems.is_empty() {
+            return Err(problems.join(" "));
+        }
+
+        Ok(Some(Self {
+            interface,
+            address,
+            gateway,
+            dns_servers,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn parse() {
+        let expected = CmdlineNetwork {
+            interface: None,
+            address: CidrAddress::new(Ipv4Addr::new(192, 0, 2, 10), 24).unwrap(),
+            gateway: Ipv4Addr::new(192, 0, 2, 1).into(),
+            dns_servers: vec![
+                Ipv4Addr::new(192, 0, 2, 53).into(),
+                Ipv4Addr::new(192, 0, 2, 54).into(),
+            ],
+        };
+
+        assert_eq!(
+            CmdlineNetwork::parse(
+                "BOOT_IMAGE=/boot/linux26 ro ip=192.0.2.10/24 gateway=192.0.2.1 \
+                 nameserver=192.0.2.53 nameserver=192.0.2.54 proxauto"
+            ),
+            Ok(Some(expected.clone()))
+        );
+        assert_eq!(
+            CmdlineNetwork::parse(
+                "ro ip=dhcp gateway=198.51.100.1 proxmox-net=ip=192.0.2.10/24,gateway=192.0.2.1,\
+                 nameserver=192.0.2.53,nameserver=192.0.2.54,interface=eno1"
+            ),
+            Ok(Some(CmdlineNetwork {
+                interface: Some("eno1".to_owned()),
+                ..expected
+            }))
+        );
+
+        assert_eq!(CmdlineNetwork::parse("ro quiet proxdebug"), Ok(None));
+        assert_eq!(
+            CmdlineNetwork::parse("ip=192.0.2.10::192.0.2.1:255.255.255.0::eth0:off"),
+            Ok(None)
+        );
+        assert_eq!(CmdlineNetwork::parse("nameserver=192.0.2.53"), Ok(None));
+
+        assert!(CmdlineNetwork::parse("ip=192.0.2.10/33 gateway=192.0.2.1").is_err());
+        assert!(CmdlineNetwork::parse("ip=192.0.2.10/24 nameserver=192.0.2.53").is_err());
+        assert!(CmdlineNetwork::parse("ip=192.0.2.10/24 gateway=192.0.2.1").is_err());
+        assert!(CmdlineNetwork::parse("gateway=192.0.2.1 nameserver=192.0.2.53").is_err());
+        assert!(CmdlineNetwork::parse(
+            "ip=192.0.2.10/24 gateway=198.51.100.1 nameserver=192.0.2.53"
+        )
+        .is_err());
+        assert!(CmdlineNetwork::parse("proxmox-net=ip=192.0.2.10/24,eno1").is_err());
+        assert!(CmdlineNetwork::parse("proxmox-net=ip=192.0.2.10/24,mtu=9000").is_err());
+    }
+
+    #[test]
+    fn vlan() {
+        assert_eq!(parse_vlan("ro quiet proxauto"), Ok(None));
+        assert_eq!(parse_vlan("ro proxmox-vlan=100 proxauto"), Ok(Some(100)));
+        assert_eq!(
+            parse_vlan("proxmox-vlan=100 proxmox-vlan=200"),
+            Ok(Some(200))
+        );
+
+        assert!(parse_vlan("proxmox-vlan=0").is_err());
+        assert!(parse_vlan("proxmox-vlan=4095").is_err());
+        assert!(parse_vlan("proxmox-vlan=abc").is_err());
+    }
+}
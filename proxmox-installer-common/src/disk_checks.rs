@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::options::{BtrfsRaidLevel, Disk, ZfsRaidLevel};
+use crate::options::{BtrfsRaidLevel, Disk, DiskWipeMethod, ZfsRaidLevel};
 use crate::setup::BootType;
 
 /// Checks a list of disks for duplicate entries, using their index as key.
@@ -135,6 +135,68 @@ pub fn check_btrfs_raid_config(level: BtrfsRaidLevel, disks: &[Disk]) -> Result<
     Ok(())
 }
 
+/// Checks whether the target disks can be wiped using the given method, as far as can be told
+/// from their device paths.
+///
+/// # Arguments
+///
+/// * `method` - The disk wipe method selected by the user.
+/// * `disks` - List of disks designated as installation targets.
+/// * `all_disks` - List of all disks of the system.
+pub fn check_disk_wipe_method(
+    method: DiskWipeMethod,
+    disks: &[Disk],
+    all_disks: &[Disk],
+) -> Result<(), String> {
+    // See also Proxmox/Sys/Block.pm:secure_wipe_disks()
+
+    if !matches!(
+        method,
+        DiskWipeMethod::NvmeFormat | DiskWipeMethod::NvmeSanitize
+    ) {
+        return Ok(());
+    }
+
+    for disk in disks {
+        let controller = nvme_controller(&disk.path).ok_or_else(|| {
+            format!(
+                "Cannot wipe disk '{}' using '{method}', as it is not a NVMe namespace",
+                disk.path
+            )
+        })?;
+
+        // Sanitizing erases all namespaces of the controller, not just the target disk
+        if method == DiskWipeMethod::NvmeSanitize {
+            let other = all_disks.iter().find(|other| {
+                nvme_controller(&other.path) == Some(controller)
+                    && !disks.iter().any(|disk| disk.index == other.index)
+            });
+            if let Some(other) = other {
+                return Err(format!(
+                    "Cannot wipe disk '{}' using '{method}', as it would also erase '{}' on the \
+                    same controller, which is not an installation target",
+                    disk.path, other.path
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the path of the NVMe controller of a NVMe namespace, e.g. `/dev/nvme0` for
+/// `/dev/nvme0n1`.
+fn nvme_controller(path: &str) -> Option<&str> {
+    let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+
+    let (controller, namespace) = path.strip_prefix("/dev/nvme")?.split_once('n')?;
+    if is_number(controller) && is_number(namespace) {
+        Some(&path[..path.len() - namespace.len() - 1])
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -153,6 +215,13 @@ mod tests {
         (0..num).map(dummy_disk).collect()
     }
 
+    fn dummy_nvme_disk(index: usize, controller: usize, namespace: usize) -> Disk {
+        Disk {
+            path: format!("/dev/nvme{controller}n{namespace}"),
+            ..dummy_disk(index)
+        }
+    }
+
     #[test]
     fn duplicate_disks() {
         assert!(check_for_duplicate_disks(&dummy_disks(2)).is_ok());
@@ -240,4 +309,56 @@ mod tests {
         assert!(check_zfs_raid_config(ZfsRaidLevel::RaidZ3, &disks[..5]).is_ok());
         assert!(check_zfs_raid_config(ZfsRaidLevel::RaidZ3, &disks).is_ok());
     }
+
+    #[test]
+    fn disk_wipe_method() {
+        let nvme_disks = [
+            dummy_nvme_disk(0, 0, 1),
+            dummy_nvme_disk(1, 0, 2),
+            dummy_nvme_disk(2, 1, 1),
+            dummy_nvme_disk(3, 10, 1),
+        ];
+        let mut all_disks = dummy_disks(2);
+        all_disks.extend_from_slice(&nvme_disks);
+
+        for method in [DiskWipeMethod::Discard, DiskWipeMethod::Zero] {
+            assert!(check_disk_wipe_method(method, &all_disks, &all_disks).is_ok());
+        }
+        assert!(
+            check_disk_wipe_method(DiskWipeMethod::AtaSecureErase, &all_disks, &all_disks).is_ok()
+        );
+
+        let format = DiskWipeMethod::NvmeFormat;
+        assert!(check_disk_wipe_method(format, &nvme_disks[..1], &all_disks).is_ok());
+        assert!(check_disk_wipe_method(format, &nvme_disks, &all_disks).is_ok());
+        assert_eq!(
+            check_disk_wipe_method(format, &all_disks, &all_disks),
+            Err(
+                "Cannot wipe disk '/dev/dummy0' using 'nvme-format', as it is not a NVMe namespace"
+                    .to_owned()
+            ),
+        );
+
+        let sanitize = DiskWipeMethod::NvmeSanitize;
+        assert!(check_disk_wipe_method(sanitize, &nvme_disks, &all_disks).is_ok());
+        assert!(check_disk_wipe_method(sanitize, &nvme_disks[..2], &all_disks).is_ok());
+        assert!(check_disk_wipe_method(sanitize, &nvme_disks[2..], &all_disks).is_ok());
+        assert_eq!(
+            check_disk_wipe_method(sanitize, &nvme_disks[1..], &all_disks),
+            Err(
+                "Cannot wipe disk '/dev/nvme0n2' using 'nvme-sanitize', as it would also erase \
+                '/dev/nvme0n1' on the same controller, which is not an installation target"
+                    .to_owned()
+            ),
+        );
+    }
+
+    #[test]
+    fn nvme_controller_of_path() {
+        assert_eq!(nvme_controller("/dev/nvme0n1"), Some("/dev/nvme0"));
+        assert_eq!(nvme_controller("/dev/nvme12n3"), Some("/dev/nvme12"));
+        assert_eq!(nvme_controller("/dev/nvme0n1p1"), None);
+        assert_eq!(nvme_controller("/dev/nvme0"), None);
+        assert_eq!(nvme_controller("/dev/sda"), None);
+    }
 }
@@ -73,12 +73,24 @@ pub fn get(url: &str, fingerprint: Option<&str>) -> Result<String> {
 /// * `fingerprint` - SHA256 cert fingerprint if certificate pinning should be used. Optional.
 /// * `payload` - The payload to send to the server. Expected to be a JSON formatted string.
 pub fn post(url: &str, fingerprint: Option<&str>, payload: String) -> Result<String> {
-    Ok(build_agent(fingerprint)?
+    Ok(post_with_content_type(url, fingerprint, payload)?.0)
+}
+
+/// Same as [`post`], but additionally returns the `Content-Type` header of the response, if the
+/// server sent one.
+pub fn post_with_content_type(
+    url: &str,
+    fingerprint: Option<&str>,
+    payload: String,
+) -> Result<(String, Option<String>)> {
+    let response = build_agent(fingerprint)?
         .post(url)
         .set("Content-Type", "application/json; charset=utf-8")
         .timeout(std::time::Duration::from_secs(60))
-        .send_string(&payload)?
-        .into_string()?)
+        .send_string(&payload)?;
+
+    let content_type = response.header("Content-Type").map(str::to_owned);
+    Ok((response.into_string()?, content_type))
 }
 
 struct VerifyCertFingerprint {
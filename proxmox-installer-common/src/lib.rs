@@ -1,4 +1,6 @@
+pub mod cmdline;
 pub mod disk_checks;
+pub mod net_checks;
 pub mod options;
 pub mod setup;
 pub mod sysinfo;
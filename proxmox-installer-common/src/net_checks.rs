@@ -0,0 +1,372 @@
+use std::net::IpAddr;
+
+use crate::options::{BridgeOptions, NetworkOptions, BOND_NAME, MTU_RANGE};
+use crate::utils::CidrAddress;
+
+/// Returns the host part of an address and the mask covering all host bits of its network, both
+/// as 128-bit values regardless of the IP version.
+fn host_part(cidr: &CidrAddress, addr: &IpAddr) -> (u128, u128) {
+    let (addr, bits) = match addr {
+        IpAddr::V4(addr) => (u128::from(u32::from(*addr)), 32),
+        IpAddr::V6(addr) => (u128::from(*addr), 128),
+    };
+    let host_mask = u128::MAX
+        .checked_shr((128 - bits + cidr.mask()) as u32)
+        .unwrap_or(0);
+
+    (addr & host_mask, host_mask)
+}
+
+/// Returns a description of why the given address cannot be used for a host, its gateway or DNS
+/// server at all, if that is the case.
+fn special_address_kind(addr: &IpAddr) -> Option<&'static str> {
+    match addr {
+        addr if addr.is_unspecified() => Some("the unspecified address"),
+        addr if addr.is_loopback() => Some("a loopback address"),
+        addr if addr.is_multicast() => Some("a multicast address"),
+        IpAddr::V4(addr) if addr.is_broadcast() => Some("the broadcast address"),
+        IpAddr::V4(addr) if addr.is_link_local() => Some("a link-local address"),
+        IpAddr::V6(addr) if addr.is_unicast_link_local() => Some("a link-local address"),
+        _ => None,
+    }
+}
+
+/// Returns a description of why the given address cannot be used for a host within the network
+/// of `cidr`, if it is its network or broadcast address. Networks with only two or less
+/// addresses (i.e. /31 and /32, or /127 and /128 respectively) do not reserve any of them.
+fn reserved_address_kind(cidr: &CidrAddress, addr: &IpAddr) -> Option<&'static str> {
+    let (host, host_mask) = host_part(cidr, addr);
+    if host_mask < 3 {
+        return None;
+    }
+
+    if host == 0 {
+        Some("the network address")
+    } else if host == host_mask && addr.is_ipv4() {
+        Some("the broadcast address")
+    } else {
+        None
+    }
+}
+
+/// Checks the addresses of the host, i.e. that they are unicast addresses which are neither the
+/// network nor broadcast address of their network and that their prefix length allows routing
+/// over a gateway. Returns all problems found.
+///
+/// # Arguments
+///
+/// * `addresses` - Addresses of the management interface, at most one per IP version.
+pub fn check_addresses(addresses: &[CidrAddress]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for cidr in addresses {
+        let addr = cidr.addr();
+        let max_mask = if cidr.is_ipv4() { 32 } else { 128 };
+
+        if let Some(kind) = special_address_kind(&addr) {
+            problems.push(format!(
+                "Address {addr} is {kind} and cannot be used as host address."
+            ));
+        } else if let Some(kind) = reserved_address_kind(cidr, &addr) {
+            problems.push(format!(
+                "Address {addr} is {kind} of its network and cannot be used as host address."
+            ));
+        }
+
+        if cidr.mask() == 0 {
+            problems.push(format!(
+                "Prefix length /0 of address {cidr} spans the whole address space, \
+                 please use the prefix length of the actual network."
+            ));
+        } else if cidr.mask() == max_mask {
+            problems.push(format!(
+                "Prefix length /{max_mask} of address {cidr} leaves no room for a gateway, \
+                 please use the prefix length of the actual network."
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Checks the gateways for the addresses of an interface, i.e. that there is exactly one gateway
+/// for each address and that it can be reached directly from within the network of the address.
+/// IPv6 link-local gateways are always reachable and thus allowed too. Returns all problems
+/// found.
+///
+/// # Arguments
+///
+/// * `addresses` - Addresses of the management interface, at most one per IP version.
+/// * `gateways` - Gateways to check, at most one per IP version.
+pub fn check_gateways(addresses: &[CidrAddress], gateways: &[IpAddr]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for gateway in gateways {
+        let link_local = matches!(gateway, IpAddr::V6(addr) if addr.is_unicast_link_local());
+
+        if !link_local {
+            if let Some(kind) = special_address_kind(gateway) {
+                problems.push(format!(
+                    "Gateway {gateway} is {kind} and cannot be used as gateway."
+                ));
+                continue;
+            }
+        }
+
+        match addresses
+            .iter()
+            .find(|cidr| cidr.is_ipv4() == gateway.is_ipv4())
+        {
+            Some(cidr) if cidr.addr() == *gateway => problems.push(format!(
+                "Gateway {gateway} is the address of the host itself."
+            )),
+            Some(_) if link_local => {}
+            Some(cidr) if !cidr.contains(gateway) => problems.push(format!(
+                "Gateway {gateway} is not within the network of address {cidr} and thus \
+                 cannot be reached directly."
+            )),
+            Some(cidr) => {
+                if let Some(kind) = reserved_address_kind(cidr, gateway) {
+                    problems.push(format!(
+                        "Gateway {gateway} is {kind} of the network of address {cidr}."
+                    ));
+                }
+            }
+            None => problems.push(format!(
+                "Gateway {gateway} has no address of the same IP version."
+            )),
+        }
+    }
+    for cidr in addresses {
+        match gateways
+            .iter()
+            .filter(|gateway| gateway.is_ipv4() == cidr.is_ipv4())
+            .count()
+        {
+            0 => problems.push(format!("No gateway set for address {cidr}.")),
+            1 => {}
+            _ => problems.push(format!("Multiple gateways set for address {cidr}.")),
+        }
+    }
+
+    problems
+}
+
+/// Checks that the DNS servers are usable and can be reached using the addresses of an interface,
+/// i.e. that there is an address of the same IP version for each of them. Returns all problems
+/// found.
+///
+/// # Arguments
+///
+/// * `addresses` - Addresses of the management interface, at most one per IP version.
+/// * `dns_servers` - DNS servers to check.
+pub fn check_dns_servers(addresses: &[CidrAddress], dns_servers: &[IpAddr]) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if dns_servers.len() > NetworkOptions::MAX_DNS_SERVERS {
+        problems.push(format!(
+            "At most {} DNS servers can be set.",
+            NetworkOptions::MAX_DNS_SERVERS
+        ));
+    }
+    for dns in dns_servers {
+        if dns.is_unspecified() || dns.is_multicast() {
+            let kind = special_address_kind(dns).unwrap_or_default();
+            problems.push(format!(
+                "DNS server {dns} is {kind} and cannot be used as DNS server."
+            ));
+        } else if !addresses.iter().any(|cidr| cidr.is_ipv4() == dns.is_ipv4()) {
+            problems.push(format!(
+                "DNS server {dns} has no address of the same IP version."
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Checks that the MTU is within the supported range.
+pub fn check_mtu(mtu: u16) -> Result<(), String> {
+    if MTU_RANGE.contains(&mtu) {
+        Ok(())
+    } else {
+        Err(format!(
+            "MTU {mtu} is not supported, it must be between {} and {}.",
+            MTU_RANGE.start(),
+            MTU_RANGE.end()
+        ))
+    }
+}
+
+/// Checks the settings of the bridge the management interface is put on, i.e. that its name is a
+/// valid interface name, also with the VLAN ID appended if the management traffic is tagged, and
+/// that its VLAN IDs include the one of the management interface. Returns all problems found.
+///
+/// # Arguments
+///
+/// * `bridge` - Settings of the bridge to check.
+/// * `vlan` - VLAN ID the management traffic is tagged with, if any.
+pub fn check_bridge(bridge: &BridgeOptions, vlan: Option<u16>) -> Vec<String> {
+    // Interface names are limited to IFNAMSIZ (16) bytes, including the terminating null byte
+    const MAX_IFNAME_LEN: usize = 15;
+
+    let mut problems = Vec::new();
+    let name = &bridge.name;
+
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        problems.push(format!(
+            "Bridge name '{name}' must start with a letter and consist of letters, digits, \
+             '_' and '-' only."
+        ));
+    } else if name == BOND_NAME {
+        problems.push(format!(
+            "Bridge name '{name}' is already used for the bond of the management interface."
+        ));
+    }
+
+    let ifname = match vlan {
+        Some(vlan) => format!("{name}.{vlan}"),
+        None => name.clone(),
+    };
+    if ifname.len() > MAX_IFNAME_LEN {
+        problems.push(format!(
+            "Bridge name '{name}' is too long, as interface '{ifname}' must not be longer than \
+             {MAX_IFNAME_LEN} characters."
+        ));
+    }
+
+    if let Some(vlan) = vlan {
+        if !bridge.vids.contains(vlan) {
+            problems.push(format!(
+                "VLAN ID {vlan} of the management interface is not within the VLAN IDs {} \
+                 passed through the bridge.",
+                bridge.vids
+            ));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> CidrAddress {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn host_addresses() {
+        assert!(check_addresses(&[cidr("192.168.1.10/24"), cidr("2001:db8::10/64")]).is_empty());
+        assert!(check_addresses(&[cidr("10.0.0.0/31"), cidr("10.0.0.255/23")]).is_empty());
+
+        for (address, problem) in [
+            ("192.168.1.0/24", "is the network address of its network"),
+            (
+                "192.168.1.255/24",
+                "is the broadcast address of its network",
+            ),
+            ("2001:db8::/64", "is the network address of its network"),
+            ("127.0.0.1/8", "is a loopback address"),
+            ("::1/64", "is a loopback address"),
+            ("224.0.0.5/24", "is a multicast address"),
+            ("ff02::1/64", "is a multicast address"),
+            ("169.254.10.10/16", "is a link-local address"),
+            ("fe80::10/64", "is a link-local address"),
+            ("0.0.0.0/24", "is the unspecified address"),
+            ("10.0.0.1/0", "Prefix length /0"),
+            ("10.0.0.1/32", "Prefix length /32"),
+            ("2001:db8::10/128", "Prefix length /128"),
+        ] {
+            let problems = check_addresses(&[cidr(address)]);
+            assert_eq!(problems.len(), 1, "{address}: {problems:?}");
+            assert!(problems[0].contains(problem), "{address}: {problems:?}");
+        }
+    }
+
+    #[test]
+    fn gateways() {
+        let addresses = [cidr("192.168.1.10/24"), cidr("2001:db8::10/64")];
+        assert!(check_gateways(&addresses, &[ip("192.168.1.1"), ip("2001:db8::1")]).is_empty());
+        assert!(check_gateways(&addresses, &[ip("192.168.1.1"), ip("fe80::1")]).is_empty());
+
+        for (gateway, problem) in [
+            (
+                "192.168.2.1",
+                "is not within the network of address 192.168.1.10/24",
+            ),
+            ("192.168.1.10", "is the address of the host itself"),
+            ("192.168.1.0", "is the network address of the network"),
+            ("192.168.1.255", "is the broadcast address of the network"),
+            ("127.0.0.1", "is a loopback address"),
+            ("0.0.0.0", "is the unspecified address"),
+        ] {
+            let problems = check_gateways(&addresses[..1], &[ip(gateway)]);
+            assert_eq!(problems.len(), 1, "{gateway}: {problems:?}");
+            assert!(problems[0].contains(problem), "{gateway}: {problems:?}");
+        }
+
+        assert_eq!(
+            check_gateways(&addresses, &[ip("192.168.1.1")]),
+            vec!["No gateway set for address 2001:db8::10/64."]
+        );
+    }
+
+    #[test]
+    fn dns_servers() {
+        let addresses = [cidr("192.168.1.10/24")];
+        assert!(check_dns_servers(&addresses, &[ip("192.168.1.1"), ip("127.0.0.1")]).is_empty());
+
+        assert_eq!(
+            check_dns_servers(&addresses, &[ip("0.0.0.0"), ip("2001:db8::1")]),
+            vec![
+                "DNS server 0.0.0.0 is the unspecified address and cannot be used as DNS server.",
+                "DNS server 2001:db8::1 has no address of the same IP version.",
+            ]
+        );
+    }
+
+    #[test]
+    fn bridge() {
+        let bridge = BridgeOptions {
+            name: "vmbr1".to_owned(),
+            vlan_aware: true,
+            vids: "100-199".parse().unwrap(),
+        };
+        assert!(check_bridge(&bridge, None).is_empty());
+        assert!(check_bridge(&bridge, Some(100)).is_empty());
+        assert_eq!(
+            check_bridge(&bridge, Some(42)),
+            vec![
+                "VLAN ID 42 of the management interface is not within the VLAN IDs 100-199 \
+                 passed through the bridge."
+            ]
+        );
+
+        for name in ["1bridge", "br.0", "br 0", "", "bond0", "averylongbridge0"] {
+            let bridge = BridgeOptions {
+                name: name.to_owned(),
+                ..Default::default()
+            };
+            assert_eq!(check_bridge(&bridge, None).len(), 1, "{name}");
+        }
+
+        let bridge = BridgeOptions {
+            name: "bridge123456".to_owned(),
+            ..Default::default()
+        };
+        assert!(check_bridge(&bridge, Some(42)).is_empty());
+        assert!(
+            check_bridge(&bridge, Some(420))[0].contains("'bridge123456.420' must not be longer")
+        );
+    }
+}
@@ -1,17 +1,20 @@
 use anyhow::{bail, Result};
 use regex::Regex;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::{Deserialize, Serialize};
 use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 use std::sync::OnceLock;
-use std::{cmp, fmt};
+use std::{borrow::Cow, cmp, fmt};
 
 use crate::setup::{
-    LocaleInfo, NetworkInfo, ProductConfig, ProxmoxProduct, RuntimeInfo, SetupInfo,
+    Gateway, Interface, LocaleInfo, NetworkInfo, ProductConfig, ProxmoxProduct, RuntimeInfo,
+    SetupInfo,
 };
 use crate::utils::{CidrAddress, Fqdn};
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all(deserialize = "lowercase", serialize = "UPPERCASE"))]
 pub enum BtrfsRaidLevel {
     #[serde(alias = "RAID0")]
@@ -24,7 +27,7 @@ pub enum BtrfsRaidLevel {
 
 serde_plain::derive_display_from_serialize!(BtrfsRaidLevel);
 
-#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all(deserialize = "lowercase", serialize = "UPPERCASE"))]
 pub enum ZfsRaidLevel {
     #[serde(alias = "RAID0")]
@@ -126,6 +129,31 @@ impl FromStr for FsType {
 
 serde_plain::derive_deserialize_from_fromstr!(FsType, "valid filesystem");
 
+impl JsonSchema for FsType {
+    fn schema_name() -> Cow<'static, str> {
+        "FsType".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": [
+                "ext4",
+                "xfs",
+                "zfs (RAID0)",
+                "zfs (RAID1)",
+                "zfs (RAID10)",
+                "zfs (RAIDZ-1)",
+                "zfs (RAIDZ-2)",
+                "zfs (RAIDZ-3)",
+                "btrfs (RAID0)",
+                "btrfs (RAID1)",
+                "btrfs (RAID10)",
+            ],
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct LvmBootdiskOptions {
     pub total_size: f64,
@@ -148,7 +176,7 @@ impl LvmBootdiskOptions {
 }
 
 /// See the accompanying mount option in btrfs(5).
-#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all(deserialize = "lowercase"))]
 pub enum BtrfsCompressOption {
     On,
@@ -195,7 +223,7 @@ impl BtrfsBootdiskOptions {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ZfsCompressOption {
     #[default]
@@ -221,7 +249,7 @@ pub const ZFS_COMPRESS_OPTIONS: &[ZfsCompressOption] = {
     &[On, Off, Lzjb, Lz4, Zle, Gzip, Zstd]
 };
 
-#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum ZfsChecksumOption {
     #[default]
@@ -280,7 +308,7 @@ impl ZfsBootdiskOptions {
 ///
 /// # Returns
 /// The default ZFS maximum ARC size in MiB for this system.
-fn default_zfs_arc_max(product: ProxmoxProduct, total_memory: usize) -> usize {
+pub fn default_zfs_arc_max(product: ProxmoxProduct, total_memory: usize) -> usize {
     if product != ProxmoxProduct::PVE {
         // For products other the PVE, just let ZFS decide on its own. Setting `0`
         // causes the installer to skip writing the `zfs_arc_max` module parameter.
@@ -292,6 +320,52 @@ fn default_zfs_arc_max(product: ProxmoxProduct, total_memory: usize) -> usize {
     }
 }
 
+/// How to deal with data already present on the system, e.g. from a previous installation.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExistingDataPolicy {
+    /// Abort the installation if the target disks contain any data, or if a volume group or ZFS
+    /// pool with the name used by the installation already exists.
+    Refuse,
+    /// Wipe the target disks and rename existing volume groups and ZFS pools with the name used
+    /// by the installation.
+    #[default]
+    Rename,
+    /// Deactivate the volume groups on the target disks and clear all signatures and partition
+    /// tables from them before partitioning. Existing volume groups and ZFS pools with the name
+    /// used by the installation on other disks are renamed.
+    Wipe,
+}
+
+serde_plain::derive_display_from_serialize!(ExistingDataPolicy);
+
+/// How to wipe the target disks before partitioning them, in addition to clearing the
+/// signatures of existing partitions.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiskWipeMethod {
+    /// Discard all blocks of the disk, see blkdiscard(8).
+    Discard,
+    /// Overwrite the first and last GiB of the disk with zeros, to clear old RAID and ZFS labels.
+    Zero,
+    /// Format the NVMe namespace with a user data erase.
+    NvmeFormat,
+    /// Sanitize the NVMe controller with a block erase, which affects all of its namespaces. All
+    /// of them must therefore be installation targets.
+    NvmeSanitize,
+    /// Use the ATA security erase feature of the disk. This temporarily sets a random ATA user
+    /// password, which the disk clears again once the erase completed. The password is logged
+    /// to the installation log, to unlock the disk should the erase fail.
+    AtaSecureErase,
+}
+
+serde_plain::derive_display_from_serialize!(DiskWipeMethod);
+
+pub const DISK_WIPE_METHODS: &[DiskWipeMethod] = {
+    use DiskWipeMethod::*;
+    &[Discard, Zero, NvmeFormat, NvmeSanitize, AtaSecureErase]
+};
+
 #[derive(Clone, Debug)]
 pub enum AdvancedBootdiskOptions {
     Lvm(LvmBootdiskOptions),
@@ -346,6 +420,7 @@ pub struct BootdiskOptions {
     pub disks: Vec<Disk>,
     pub fstype: FsType,
     pub advanced: AdvancedBootdiskOptions,
+    pub wipe: Option<DiskWipeMethod>,
 }
 
 impl BootdiskOptions {
@@ -354,6 +429,7 @@ impl BootdiskOptions {
             disks: vec![disk.clone()],
             fstype: FsType::Ext4,
             advanced: AdvancedBootdiskOptions::Lvm(LvmBootdiskOptions::defaults_from(disk)),
+            wipe: None,
         }
     }
 }
@@ -396,64 +472,333 @@ impl TimezoneOptions {
     }
 }
 
+/// Range of valid VLAN IDs, as 0 and 4095 are reserved.
+pub const VLAN_ID_RANGE: RangeInclusive<u16> = 1..=4094;
+
+/// Name of the bond created for a bonded management uplink.
+pub const BOND_NAME: &str = "bond0";
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum BondMode {
+    BalanceRr,
+    #[default]
+    ActiveBackup,
+    BalanceXor,
+    Broadcast,
+    #[serde(rename = "802.3ad")]
+    Lacp,
+    BalanceTlb,
+    BalanceAlb,
+}
+
+serde_plain::derive_display_from_serialize!(BondMode);
+
+impl BondMode {
+    /// Whether the mode distributes traffic according to a transmit hash policy.
+    pub fn uses_xmit_hash_policy(&self) -> bool {
+        matches!(self, BondMode::BalanceXor | BondMode::Lacp)
+    }
+}
+
+pub const BOND_MODES: &[BondMode] = {
+    use BondMode::*;
+    &[
+        BalanceRr,
+        ActiveBackup,
+        BalanceXor,
+        Broadcast,
+        Lacp,
+        BalanceTlb,
+        BalanceAlb,
+    ]
+};
+
+#[derive(Copy, Clone, Debug, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+pub enum BondXmitHashPolicy {
+    #[serde(rename = "layer2")]
+    Layer2,
+    #[serde(rename = "layer2+3")]
+    Layer2_3,
+    #[serde(rename = "layer3+4")]
+    Layer3_4,
+}
+
+serde_plain::derive_display_from_serialize!(BondXmitHashPolicy);
+
+/// Bond the management bridge or interface is put on top of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BondOptions {
+    /// Interfaces enslaved to the bond, the first one being used as the management interface
+    /// otherwise.
+    pub slaves: Vec<String>,
+    pub mode: BondMode,
+    pub xmit_hash_policy: Option<BondXmitHashPolicy>,
+    /// MII link monitoring interval in milliseconds.
+    pub miimon: u32,
+}
+
+impl BondOptions {
+    pub const DEFAULT_MIIMON: u32 = 100;
+
+    pub fn new(slaves: Vec<String>, mode: BondMode) -> Self {
+        Self {
+            slaves,
+            mode,
+            xmit_hash_policy: None,
+            miimon: Self::DEFAULT_MIIMON,
+        }
+    }
+}
+
+/// Default name of the bridge the management interface is put on, for products using a bridged
+/// network setup.
+pub const DEFAULT_BRIDGE_NAME: &str = "vmbr0";
+
+/// Range of supported MTUs, from the minimum MTU required by IPv6 up to jumbo frames as commonly
+/// supported by NICs.
+pub const MTU_RANGE: RangeInclusive<u16> = 1280..=9216;
+
+/// Range of VLAN IDs passed through a VLAN-aware bridge, written as e.g. `2-4094`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct VlanIdRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl VlanIdRange {
+    /// Returns `true` if the given VLAN ID is within the range.
+    pub fn contains(&self, vlan: u16) -> bool {
+        (self.start..=self.end).contains(&vlan)
+    }
+
+    /// Returns the default range, extended to include the VLAN ID of the management interface.
+    pub fn default_for(vlan: Option<u16>) -> Self {
+        let default = Self::default();
+        match vlan {
+            Some(vlan) => Self {
+                start: default.start.min(vlan),
+                end: default.end.max(vlan),
+            },
+            None => default,
+        }
+    }
+}
+
+impl Default for VlanIdRange {
+    fn default() -> Self {
+        Self {
+            start: 2,
+            end: *VLAN_ID_RANGE.end(),
+        }
+    }
+}
+
+impl FromStr for VlanIdRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |vlan: &str| {
+            vlan.trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|vlan| VLAN_ID_RANGE.contains(vlan))
+                .ok_or_else(|| format!("'{vlan}' is not a VLAN ID between 1 and 4094"))
+        };
+
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(s)?, parse(s)?),
+        };
+
+        if start > end {
+            Err(format!("VLAN ID range '{s}' must not end before it starts"))
+        } else {
+            Ok(Self { start, end })
+        }
+    }
+}
+
+impl fmt::Display for VlanIdRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+serde_plain::derive_deserialize_from_fromstr!(VlanIdRange, "VLAN ID range, e.g. '2-4094'");
+serde_plain::derive_serialize_from_display!(VlanIdRange);
+
+impl JsonSchema for VlanIdRange {
+    fn schema_name() -> Cow<'static, str> {
+        "VlanIdRange".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "pattern": r"^\d{1,4}(-\d{1,4})?$",
+        })
+    }
+}
+
+/// Bridge the management interface is put on, for products using a bridged network setup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BridgeOptions {
+    pub name: String,
+    /// Whether the bridge is VLAN-aware. It always is if the management traffic is VLAN-tagged.
+    pub vlan_aware: bool,
+    /// VLAN IDs passed through the bridge if it is VLAN-aware.
+    pub vids: VlanIdRange,
+}
+
+impl BridgeOptions {
+    /// Returns the default bridge settings, passing through the VLAN ID of the management
+    /// interface if it is VLAN-aware.
+    pub fn default_for(vlan: Option<u16>) -> Self {
+        Self {
+            vids: VlanIdRange::default_for(vlan),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for BridgeOptions {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_BRIDGE_NAME.to_owned(),
+            vlan_aware: false,
+            vids: VlanIdRange::default(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct NetworkOptions {
     pub ifname: String,
+    /// VLAN ID the management traffic is tagged with, if any.
+    pub vlan: Option<u16>,
+    /// Bond of multiple interfaces to use instead of `ifname` alone, if any.
+    pub bond: Option<BondOptions>,
     pub fqdn: Fqdn,
     pub address: CidrAddress,
     pub gateway: IpAddr,
-    pub dns_server: IpAddr,
+    /// IPv6 address and gateway in addition to the IPv4 `address` and `gateway`, for dual-stack
+    /// configurations.
+    pub address6: Option<CidrAddress>,
+    pub gateway6: Option<IpAddr>,
+    pub dns_servers: Vec<IpAddr>,
+    /// DNS search domains, the domain of the FQDN is used if empty.
+    pub search_domains: Vec<String>,
+    /// MTU of the management interface, the default of the NIC is used if unset.
+    pub mtu: Option<u16>,
+    /// Non-default settings of the bridge the management interface is put on, if any.
+    pub bridge: Option<BridgeOptions>,
 }
 
 impl NetworkOptions {
     const DEFAULT_DOMAIN: &'static str = "example.invalid";
+    /// Maximum number of DNS servers, as more are ignored by the resolver, see resolv.conf(5).
+    pub const MAX_DNS_SERVERS: usize = 3;
 
     pub fn defaults_from(setup: &SetupInfo, network: &NetworkInfo) -> Self {
         let mut this = Self {
             ifname: String::new(),
+            vlan: None,
+            bond: None,
             fqdn: Self::construct_fqdn(network, setup.config.product.default_hostname()),
             // Safety: The provided mask will always be valid.
             address: CidrAddress::new(Ipv4Addr::UNSPECIFIED, 0).unwrap(),
             gateway: Ipv4Addr::UNSPECIFIED.into(),
-            dns_server: Ipv4Addr::UNSPECIFIED.into(),
+            address6: None,
+            gateway6: None,
+            dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+            search_domains: Vec::new(),
+            mtu: None,
+            bridge: None,
         };
 
-        if let Some(ip) = network.dns.dns.first() {
-            this.dns_server = *ip;
+        if !network.dns.dns.is_empty() {
+            this.dns_servers = network
+                .dns
+                .dns
+                .iter()
+                .take(Self::MAX_DNS_SERVERS)
+                .copied()
+                .collect();
         }
 
-        if let Some(routes) = &network.routes {
-            let mut filled = false;
-            if let Some(gw) = &routes.gateway4 {
-                if let Some(iface) = network.interfaces.get(&gw.dev) {
-                    this.ifname.clone_from(&iface.name);
-                    if let Some(addresses) = &iface.addresses {
-                        if let Some(addr) = addresses.iter().find(|addr| addr.is_ipv4()) {
-                            this.gateway = gw.gateway;
-                            this.address = addr.clone();
-                            filled = true;
-                        }
+        let Some(routes) = &network.routes else {
+            return this;
+        };
+        let ipv4 = routes
+            .gateway4
+            .as_ref()
+            .and_then(|gw| Self::address_with_gateway(network, gw, CidrAddress::is_ipv4));
+        let ipv6 = routes
+            .gateway6
+            .as_ref()
+            .and_then(|gw| Self::address_with_gateway(network, gw, CidrAddress::is_ipv6));
+
+        match (ipv4, ipv6) {
+            (Some((iface, address, gateway)), ipv6) => {
+                (this.ifname, this.vlan) = Self::interface_with_vlan(network, iface);
+                this.address = address;
+                this.gateway = gateway;
+
+                // Both address families can only be configured on the same interface
+                if let Some((iface6, address6, gateway6)) = ipv6 {
+                    if iface6.name == iface.name {
+                        this.address6 = Some(address6);
+                        this.gateway6 = Some(gateway6);
                     }
                 }
             }
-            if !filled {
-                if let Some(gw) = &routes.gateway6 {
-                    if let Some(iface) = network.interfaces.get(&gw.dev) {
-                        if let Some(addresses) = &iface.addresses {
-                            if let Some(addr) = addresses.iter().find(|addr| addr.is_ipv6()) {
-                                this.ifname.clone_from(&iface.name);
-                                this.gateway = gw.gateway;
-                                this.address = addr.clone();
-                            }
-                        }
-                    }
-                }
+            (None, Some((iface, address, gateway))) => {
+                (this.ifname, this.vlan) = Self::interface_with_vlan(network, iface);
+                this.address = address;
+                this.gateway = gateway;
             }
+            (None, None) => {}
         }
 
         this
     }
 
+    /// Returns the interface of the default gateway, together with its first address of the
+    /// given family, if it has one.
+    fn address_with_gateway<'a>(
+        network: &'a NetworkInfo,
+        gateway: &Gateway,
+        family: fn(&CidrAddress) -> bool,
+    ) -> Option<(&'a Interface, CidrAddress, IpAddr)> {
+        let iface = network.interfaces.get(&gateway.dev)?;
+        let address = iface.addresses.as_ref()?.iter().find(|addr| family(addr))?;
+        Some((iface, address.clone(), gateway.gateway))
+    }
+
+    /// Returns the settings of the bridge the management interface is put on. If the management
+    /// traffic is VLAN-tagged, the bridge is always set up, using the defaults if not configured
+    /// otherwise.
+    pub fn bridge_options(&self) -> Option<BridgeOptions> {
+        self.bridge
+            .clone()
+            .or_else(|| self.vlan.map(|vlan| BridgeOptions::default_for(Some(vlan))))
+    }
+
+    /// Returns the interface name and VLAN ID to use for an interface of the installation
+    /// environment. VLAN interfaces, as used for obtaining a lease on a tagged VLAN, are resolved
+    /// to their parent interface.
+    fn interface_with_vlan(network: &NetworkInfo, iface: &Interface) -> (String, Option<u16>) {
+        match network.vlan_parent(&iface.name) {
+            Some((parent, vlan)) => (parent.name.clone(), Some(vlan)),
+            None => (iface.name.clone(), None),
+        }
+    }
+
     fn construct_fqdn(network: &NetworkInfo, default_hostname: &str) -> Fqdn {
         let hostname = network.hostname.as_deref().unwrap_or(default_hostname);
 
@@ -520,4 +865,35 @@ mod tests {
             assert_eq!(default_zfs_arc_max(ProxmoxProduct::PDM, *total_memory), 0);
         }
     }
+
+    #[test]
+    fn vlan_id_range() {
+        assert_eq!(VlanIdRange::default_for(None).to_string(), "2-4094");
+        assert_eq!(VlanIdRange::default_for(Some(42)).to_string(), "2-4094");
+        assert_eq!(VlanIdRange::default_for(Some(1)).to_string(), "1-4094");
+
+        let range: VlanIdRange = "2-4094".parse().unwrap();
+        assert_eq!(range, VlanIdRange::default());
+        assert_eq!(range.to_string(), "2-4094");
+
+        let range: VlanIdRange = "42".parse().unwrap();
+        assert!(range.contains(42) && !range.contains(43));
+        assert_eq!(range.to_string(), "42");
+
+        for invalid in ["", "0-10", "10-4095", "20-10", "a-b", "1-2-3"] {
+            assert!(invalid.parse::<VlanIdRange>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn fs_type_schema_matches_serialization() {
+        let schema = FsType::json_schema(&mut SchemaGenerator::default());
+        let values = schema.get("enum").and_then(|v| v.as_array()).unwrap();
+
+        for value in values {
+            let value = value.as_str().unwrap();
+            let fstype = FsType::from_str(value).unwrap();
+            assert_eq!(serde_plain::to_string(&fstype).unwrap(), value);
+        }
+    }
 }
@@ -9,18 +9,20 @@ use std::{
     process::{self, Command, Stdio},
 };
 
+use schemars::JsonSchema;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::{
     options::{
-        BtrfsBootdiskOptions, BtrfsCompressOption, Disk, FsType, ZfsBootdiskOptions,
-        ZfsChecksumOption, ZfsCompressOption,
+        BondMode, BondOptions, BondXmitHashPolicy, BridgeOptions, BtrfsBootdiskOptions,
+        BtrfsCompressOption, Disk, DiskWipeMethod, ExistingDataPolicy, FsType, VlanIdRange,
+        ZfsBootdiskOptions, ZfsChecksumOption, ZfsCompressOption,
     },
     utils::CidrAddress,
 };
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Serialize)]
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Serialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ProxmoxProduct {
     PVE,
@@ -38,6 +40,13 @@ impl ProxmoxProduct {
             Self::PDM => "pdm",
         }
     }
+
+    /// Whether the management interface is put on a bridge on the installed system.
+    ///
+    /// See also Proxmox/Install/ISOEnv.pm
+    pub fn bridged_network(self) -> bool {
+        self == Self::PVE
+    }
 }
 
 impl fmt::Display for ProxmoxProduct {
@@ -51,12 +60,16 @@ impl fmt::Display for ProxmoxProduct {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct ProductConfig {
     pub fullname: String,
     pub product: ProxmoxProduct,
     #[serde(deserialize_with = "deserialize_bool_from_int")]
     pub enable_btrfs: bool,
+    /// Recommended minimum size of the root disk in GB, below which the installation needs to be
+    /// confirmed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recommended_min_hdsize: Option<f64>,
 }
 
 impl ProductConfig {
@@ -66,11 +79,12 @@ impl ProductConfig {
             fullname: String::from("Proxmox VE (mocked)"),
             product: ProxmoxProduct::PVE,
             enable_btrfs: true,
+            recommended_min_hdsize: Some(8.),
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct IsoInfo {
     pub release: String,
     pub isorelease: String,
@@ -259,6 +273,47 @@ impl From<BtrfsBootdiskOptions> for InstallBtrfsOption {
     }
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InstallBondOption {
+    pub slaves: Vec<String>,
+    pub mode: BondMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xmit_hash_policy: Option<BondXmitHashPolicy>,
+    pub miimon: u32,
+}
+
+impl From<BondOptions> for InstallBondOption {
+    fn from(opts: BondOptions) -> Self {
+        InstallBondOption {
+            slaves: opts.slaves,
+            mode: opts.mode,
+            xmit_hash_policy: opts.xmit_hash_policy,
+            miimon: opts.miimon,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct InstallBridgeOption {
+    pub name: String,
+    #[serde(
+        serialize_with = "serialize_bool_as_u32",
+        deserialize_with = "deserialize_bool_from_int"
+    )]
+    pub vlan_aware: bool,
+    pub vids: VlanIdRange,
+}
+
+impl From<BridgeOptions> for InstallBridgeOption {
+    fn from(opts: BridgeOptions) -> Self {
+        InstallBridgeOption {
+            name: opts.name,
+            vlan_aware: opts.vlan_aware,
+            vids: opts.vids,
+        }
+    }
+}
+
 pub fn read_json<T: for<'de> Deserialize<'de>, P: AsRef<Path>>(path: P) -> Result<T, String> {
     let file = File::open(path).map_err(|err| err.to_string())?;
     let reader = BufReader::new(file);
@@ -380,6 +435,10 @@ pub struct RuntimeInfo {
     /// Network addresses, gateways and DNS info.
     pub network: NetworkInfo,
 
+    /// Kernel commandline the installation environment was booted with.
+    #[serde(default)]
+    pub kernel_cmdline: String,
+
     /// Total memory of the system in MiB.
     pub total_memory: usize,
 
@@ -392,7 +451,7 @@ pub struct RuntimeInfo {
     pub secure_boot: Option<bool>,
 }
 
-#[derive(Copy, Clone, Eq, Deserialize, PartialEq, Serialize)]
+#[derive(Copy, Clone, Eq, Deserialize, PartialEq, Serialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum BootType {
     Bios,
@@ -413,6 +472,33 @@ pub struct NetworkInfo {
     pub hostname: Option<String>,
 }
 
+impl NetworkInfo {
+    /// Splits the name of a VLAN interface created on top of another interface, like `eno1.42`,
+    /// into that parent interface and the VLAN ID.
+    pub fn vlan_parent(&self, name: &str) -> Option<(&Interface, u16)> {
+        let (parent, vlan) = name.rsplit_once('.')?;
+        Some((self.interfaces.get(parent)?, vlan.parse().ok()?))
+    }
+
+    /// Returns the interface the installation environment received its DHCP lease on, i.e. the
+    /// one of the default route, preferring IPv4. VLAN interfaces are resolved to their parent
+    /// interface.
+    pub fn dhcp_interface(&self) -> Option<&Interface> {
+        let routes = self.routes.as_ref()?;
+
+        [&routes.gateway4, &routes.gateway6]
+            .into_iter()
+            .flatten()
+            .find_map(|gateway| {
+                let iface = self.interfaces.get(&gateway.dev)?;
+                Some(match self.vlan_parent(&iface.name) {
+                    Some((parent, _)) => parent,
+                    None => iface,
+                })
+            })
+    }
+}
+
 #[derive(Clone, Deserialize)]
 pub struct Dns {
     pub domain: Option<String>,
@@ -473,6 +559,14 @@ pub struct Interface {
     #[serde(default)]
     #[serde(deserialize_with = "deserialize_cidr_list")]
     pub addresses: Option<Vec<CidrAddress>>,
+
+    /// Whether the interface has a carrier, i.e. a link, if known.
+    #[serde(default, deserialize_with = "deserialize_bool_from_int_maybe")]
+    pub carrier: Option<bool>,
+
+    /// Link speed in Mbit/s, if known.
+    #[serde(default)]
+    pub speed: Option<u32>,
 }
 
 impl Interface {
@@ -480,6 +574,13 @@ impl Interface {
     pub fn render(&self) -> String {
         format!("{} {}", self.state.render(), self.name)
     }
+
+    /// Whether the interface has a link, falling back to its operational state if the carrier is
+    /// unknown.
+    pub fn has_carrier(&self) -> bool {
+        self.carrier
+            .unwrap_or(matches!(self.state, InterfaceState::Up))
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -549,6 +650,10 @@ pub struct InstallConfig {
     pub disk_selection: BTreeMap<String, String>,
 
     pub existing_storage_auto_rename: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub existing_data: Option<ExistingDataPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wipe: Option<DiskWipeMethod>,
 
     pub country: String,
     pub timezone: String,
@@ -560,13 +665,29 @@ pub struct InstallConfig {
     pub root_ssh_keys: Vec<String>,
 
     pub mngmt_nic: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vlan_id: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bond_opts: Option<InstallBondOption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mtu: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge_opts: Option<InstallBridgeOption>,
 
     pub hostname: String,
     pub domain: String,
     #[serde(serialize_with = "serialize_as_display")]
     pub cidr: CidrAddress,
     pub gateway: IpAddr,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cidr6: Option<CidrAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway6: Option<IpAddr>,
     pub dns: IpAddr,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_dns: Vec<IpAddr>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub search_domains: Vec<String>,
 
     pub first_boot: InstallFirstBootSetup,
 }
@@ -1,11 +1,12 @@
 use std::{collections::HashMap, fs};
 
 use anyhow::{bail, Result};
+use schemars::JsonSchema;
 use serde::Serialize;
 
 const DMI_PATH: &str = "/sys/devices/virtual/dmi/id";
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SystemDMI {
     system: HashMap<String, String>,
     baseboard: HashMap<String, String>,
@@ -1,10 +1,12 @@
 use std::{
+    borrow::Cow,
     fmt,
     net::{AddrParseError, IpAddr},
     num::ParseIntError,
     str::FromStr,
 };
 
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
 use serde::Deserialize;
 
 /// Possible errors that might occur when parsing CIDR addresses.
@@ -73,6 +75,22 @@ impl CidrAddress {
     pub fn mask(&self) -> usize {
         self.mask
     }
+
+    /// Returns `true` if the given address is within the network of this address, i.e. is of the
+    /// same address family and shares the network prefix.
+    pub fn contains(&self, addr: &IpAddr) -> bool {
+        match (self.addr, addr) {
+            (IpAddr::V4(network), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.mask as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.mask as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*addr) & mask
+            }
+            _ => false,
+        }
+    }
 }
 
 impl FromStr for CidrAddress {
@@ -116,6 +134,19 @@ impl<'de> Deserialize<'de> for CidrAddress {
 
 serde_plain::derive_serialize_from_display!(CidrAddress);
 
+impl JsonSchema for CidrAddress {
+    fn schema_name() -> Cow<'static, str> {
+        "CidrAddress".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "IPv4 or IPv6 address with network mask in CIDR notation",
+        })
+    }
+}
+
 fn mask_limit(addr: &IpAddr) -> usize {
     if addr.is_ipv4() {
         32
@@ -212,6 +243,12 @@ impl Fqdn {
         }
     }
 
+    /// Checks whether the given string is a valid domain name, e.g. for use as DNS search
+    /// domain. Unlike an FQDN, it may consist of a single label.
+    pub fn is_valid_domain(domain: &str) -> bool {
+        domain.len() <= Self::MAX_LENGTH && domain.split('.').all(Self::validate_single)
+    }
+
     pub fn host(&self) -> Option<&str> {
         self.has_host().then_some(&self.parts[0])
     }
@@ -277,6 +314,20 @@ impl<'de> Deserialize<'de> for Fqdn {
     }
 }
 
+impl JsonSchema for Fqdn {
+    fn schema_name() -> Cow<'static, str> {
+        "Fqdn".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "format": "hostname",
+            "description": "Fully qualified domain name",
+        })
+    }
+}
+
 impl PartialEq for Fqdn {
     // Case-insensitive comparison, as per RFC 952 "ASSUMPTIONS", RFC 1035 sec. 2.3.3. "Character
     // Case" and RFC 4343 as a whole
@@ -363,4 +414,19 @@ mod tests {
         assert_ne!(Fqdn::from("foo.com"), Fqdn::from("bar.com"));
         assert_ne!(Fqdn::from("example.com"), Fqdn::from("example.net"));
     }
+
+    #[test]
+    fn cidr_contains() {
+        let cidr: CidrAddress = "192.168.0.10/24".parse().unwrap();
+        assert!(cidr.contains(&"192.168.0.1".parse().unwrap()));
+        assert!(!cidr.contains(&"192.168.1.1".parse().unwrap()));
+        assert!(!cidr.contains(&"::1".parse().unwrap()));
+
+        let cidr: CidrAddress = "2001:db8::10/64".parse().unwrap();
+        assert!(cidr.contains(&"2001:db8::1".parse().unwrap()));
+        assert!(!cidr.contains(&"2001:db8:1::1".parse().unwrap()));
+
+        let cidr: CidrAddress = "10.0.0.1/0".parse().unwrap();
+        assert!(cidr.contains(&"192.168.0.1".parse().unwrap()));
+    }
 }
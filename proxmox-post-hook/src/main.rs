@@ -22,7 +22,7 @@ use std::{
 use anyhow::{anyhow, bail, Context, Result};
 use proxmox_auto_installer::{
     answer::{Answer, PostNotificationHookInfo},
-    udevinfo::{UdevInfo, UdevProperties},
+    udevinfo::{DiskAttributes, UdevInfo, UdevProperties},
 };
 use proxmox_installer_common::{
     options::{Disk, FsType},
@@ -33,10 +33,11 @@ use proxmox_installer_common::{
     sysinfo::SystemDMI,
     utils::CidrAddress,
 };
+use schemars::JsonSchema;
 use serde::Serialize;
 
 /// Information about the system boot status.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct BootInfo {
     /// Whether the system is booted using UEFI or legacy BIOS.
     mode: BootType,
@@ -46,7 +47,7 @@ struct BootInfo {
 }
 
 /// Holds all the public keys for the different algorithms available.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct SshPublicHostKeys {
     // ECDSA-based public host key
     ecdsa: String,
@@ -57,7 +58,7 @@ struct SshPublicHostKeys {
 }
 
 /// Holds information about a single disk in the system.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 struct DiskInfo {
     /// Size in bytes
@@ -67,10 +68,13 @@ struct DiskInfo {
     is_bootdisk: Option<bool>,
     /// Properties about the device as given by udev.
     udev_properties: UdevProperties,
+    /// Attributes of the disk as read from sysfs by the installer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<DiskAttributes>,
 }
 
 /// Holds information about the management network interface.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 struct NetworkInterfaceInfo {
     /// MAC address of the interface
@@ -79,7 +83,7 @@ struct NetworkInterfaceInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     address: Option<CidrAddress>,
     /// Set to true if the interface is the chosen management interface during
-    /// installation.
+    /// installation, or part of the chosen management bond.
     #[serde(skip_serializing_if = "Option::is_none")]
     is_management: Option<bool>,
     /// Properties about the device as given by udev.
@@ -87,7 +91,7 @@ struct NetworkInterfaceInfo {
 }
 
 /// Information about the installed product itself.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 struct ProductInfo {
     /// Full name of the product
@@ -100,7 +104,7 @@ struct ProductInfo {
 
 /// The current kernel version.
 /// Aligns with the format as used by the `/nodes/<node>/status` API of each product.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct KernelVersionInformation {
     /// The systemname/nodename
     pub sysname: String,
@@ -113,7 +117,7 @@ struct KernelVersionInformation {
 }
 
 /// Information about the CPU(s) installed in the system
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct CpuInfo {
     /// Number of physical CPU cores.
     cores: usize,
@@ -130,7 +134,7 @@ struct CpuInfo {
 }
 
 /// Metadata of the hook, such as schema version of the document.
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 struct PostHookInfoSchema {
     /// major.minor version describing the schema version of this document, in a semanticy-version
@@ -140,11 +144,12 @@ struct PostHookInfoSchema {
     /// field.
     /// minor: Incremented when adding functionality in a backwards-compatible matter, e.g.
     /// adding a new field.
+    #[schemars(extend("const" = PostHookInfoSchema::SCHEMA_VERSION))]
     version: String,
 }
 
 impl PostHookInfoSchema {
-    const SCHEMA_VERSION: &str = "1.0";
+    const SCHEMA_VERSION: &str = "2.0";
 }
 
 impl Default for PostHookInfoSchema {
@@ -159,7 +164,12 @@ impl Default for PostHookInfoSchema {
 ///
 /// NOTE: The format is versioned through `schema.version` (`$schema.version` in the
 /// resulting JSON), ensure you update it when this struct or any of its members gets modified.
-#[derive(Serialize)]
+/// The published JSON schema in `schema/post-hook-info.json` is checked against this struct by the
+/// tests below.
+#[derive(Serialize, JsonSchema)]
+#[schemars(
+    description = "All data sent as request payload with the post-installation-webhook POST request."
+)]
 #[serde(rename_all = "kebab-case")]
 struct PostHookInfo {
     // This field is prefixed by `$` on purpose, to indicate that it is document metadata and not
@@ -310,6 +320,7 @@ impl PostHookInfo {
                         size: (config.hdsize * (SIZE_GIB as f64)) as usize,
                         is_bootdisk,
                         udev_properties: get_udev_properties(disk)?,
+                        attributes: udev.disk_attributes.get(&disk.index).cloned(),
                     })
                 })
                 .collect()
@@ -330,6 +341,7 @@ impl PostHookInfo {
                         size: (config.hdsize * (SIZE_GIB as f64)) as usize,
                         is_bootdisk,
                         udev_properties: get_udev_properties(disk)?,
+                        attributes: udev.disk_attributes.get(&disk.index).cloned(),
                     })
                 })
                 .collect()
@@ -364,7 +376,12 @@ impl PostHookInfo {
                     })?
                     .clone();
 
-                if config.mngmt_nic == nic.name {
+                let is_bond_slave = config
+                    .bond_opts
+                    .as_ref()
+                    .is_some_and(|bond| bond.slaves.contains(&nic.name));
+
+                if config.mngmt_nic == nic.name || is_bond_slave {
                     // Use the actual IP address from the low-level install config, as the runtime info
                     // contains the original IP address from DHCP.
                     anyhow::Ok(NetworkInterfaceInfo {
@@ -593,7 +610,7 @@ impl PostHookInfo {
 /// # Arguments
 ///
 /// * `callback` - Callback to call with the absolute path where the chroot environment root is
-///                mounted.
+///   mounted.
 fn with_chroot<R, F: FnOnce(&str) -> Result<R>>(callback: F) -> Result<R> {
     let ec = Command::new("proxmox-chroot")
         .arg("prepare")
@@ -657,7 +674,14 @@ fn main() -> ExitCode {
 
 #[cfg(test)]
 mod tests {
-    use crate::PostHookInfo;
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use proxmox_auto_installer::utils::payload_json_schema;
+
+    use crate::{PostHookInfo, PostHookInfoSchema};
 
     #[test]
     fn finds_correct_kernel_package_name() {
@@ -816,4 +840,35 @@ ii |amd64|proxmox-kernel-6.8.8-2-pve-signed
             "/boot/vmlinuz-6.8.8-2-pve"
         );
     }
+
+    fn published_schema_path() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("schema/post-hook-info.json")
+    }
+
+    #[test]
+    fn payload_schema_is_up_to_date() {
+        let path = published_schema_path();
+        let current = serde_json::to_value(payload_json_schema::<PostHookInfo>()).unwrap();
+        let published: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        if current == published {
+            return;
+        }
+
+        assert_ne!(
+            published["$defs"]["PostHookInfoSchema"]["properties"]["version"]["const"],
+            PostHookInfoSchema::SCHEMA_VERSION,
+            "PostHookInfo was modified without bumping PostHookInfoSchema::SCHEMA_VERSION"
+        );
+        panic!("schema version was bumped, update {path:?} by running 'make update-schemas'");
+    }
+
+    #[test]
+    #[ignore = "writes the published schema, run via 'make update-schemas'"]
+    fn update_payload_schema() {
+        let schema = serde_json::to_value(payload_json_schema::<PostHookInfo>()).unwrap();
+        let content = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        fs::write(published_schema_path(), content).unwrap();
+    }
 }
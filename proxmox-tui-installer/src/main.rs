@@ -17,9 +17,13 @@ mod options;
 use options::{InstallerOptions, PasswordOptions};
 
 use proxmox_installer_common::{
-    options::{email_validate, BootdiskOptions, NetworkOptions, TimezoneOptions},
+    net_checks::{check_addresses, check_bridge, check_dns_servers, check_gateways, check_mtu},
+    options::{
+        email_validate, BondMode, BondOptions, BootdiskOptions, BridgeOptions, NetworkOptions,
+        TimezoneOptions, VlanIdRange, BOND_MODES, VLAN_ID_RANGE,
+    },
     setup::{installer_setup, LocaleInfo, ProxmoxProduct, RuntimeInfo, SetupInfo},
-    utils::Fqdn,
+    utils::{CidrAddress, Fqdn},
 };
 
 mod setup;
@@ -28,8 +32,8 @@ mod system;
 
 mod views;
 use views::{
-    BootdiskOptionsView, CidrAddressEditView, FormView, InstallProgressView, TableView,
-    TableViewItem, TimezoneOptionsView,
+    BootdiskOptionsView, CidrAddressEditView, FormView, InstallProgressView, MultiSelectView,
+    TableView, TableViewItem, TimezoneOptionsView,
 };
 
 // TextView::center() seems to garble the first two lines, so fix it manually here.
@@ -480,7 +484,12 @@ fn password_dialog(siv: &mut Cursive) -> InstallerView {
 fn network_dialog(siv: &mut Cursive) -> InstallerView {
     let state = siv.user_data::<InstallerState>().unwrap();
     let options = &state.options.network;
-    let ifaces = state.runtime_info.network.interfaces.values();
+    let network = &state.runtime_info.network;
+    // VLAN interfaces of the installation environment are configured through the VLAN ID instead
+    let ifaces = network
+        .interfaces
+        .values()
+        .filter(|iface| network.vlan_parent(&iface.name).is_none());
     let ifnames = ifaces
         .clone()
         .map(|iface| (iface.render(), iface.name.clone()));
@@ -491,12 +500,55 @@ fn network_dialog(siv: &mut Cursive) -> InstallerView {
     let selected = ifaces_selection
         .iter()
         .position(|(_label, iface)| *iface == options.ifname)
-        .unwrap_or(ifaces.len() - 1);
+        .unwrap_or(ifaces.clone().count() - 1);
 
     ifaces_selection.set_selection(selected);
 
+    let bond_mode_selection = SelectView::new()
+        .popup()
+        .item("none", None)
+        .with_all(
+            BOND_MODES
+                .iter()
+                .map(|mode| (mode.to_string(), Some(*mode))),
+        )
+        .selected(
+            options
+                .bond
+                .as_ref()
+                .and_then(|bond| BOND_MODES.iter().position(|mode| *mode == bond.mode))
+                .map(|index| index + 1)
+                .unwrap_or(0),
+        );
+
+    let mut bond_slaves: Vec<_> = ifaces
+        .clone()
+        .map(|iface| {
+            let selected = options
+                .bond
+                .as_ref()
+                .is_some_and(|bond| bond.slaves.contains(&iface.name));
+            (iface.render(), iface.name.clone(), selected)
+        })
+        .collect();
+    bond_slaves.sort_by(|a, b| a.1.cmp(&b.1));
+
     let inner = FormView::new()
         .child("Management interface", ifaces_selection)
+        .child("Bond mode", bond_mode_selection)
+        .child(
+            "Bond interfaces",
+            MultiSelectView::new("network-bond-slaves", "Bond interfaces", bond_slaves),
+        )
+        .child(
+            "VLAN ID (optional)",
+            EditView::new().content(
+                options
+                    .vlan
+                    .map(|vlan| vlan.to_string())
+                    .unwrap_or_default(),
+            ),
+        )
         .child(
             "Hostname (FQDN)",
             EditView::new().content(options.fqdn.to_string()),
@@ -510,55 +562,215 @@ fn network_dialog(siv: &mut Cursive) -> InstallerView {
             EditView::new().content(options.gateway.to_string()),
         )
         .child(
-            "DNS server address",
-            EditView::new().content(options.dns_server.to_string()),
+            "Second IP address (optional)",
+            EditView::new().content(
+                options
+                    .address6
+                    .as_ref()
+                    .map(|address| address.to_string())
+                    .unwrap_or_default(),
+            ),
+        )
+        .child(
+            "Second gateway address",
+            EditView::new().content(
+                options
+                    .gateway6
+                    .map(|gateway| gateway.to_string())
+                    .unwrap_or_default(),
+            ),
+        )
+        .child(
+            "DNS server addresses",
+            EditView::new().content(
+                options
+                    .dns_servers
+                    .iter()
+                    .map(|dns| dns.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        )
+        .child(
+            "DNS search domains (optional)",
+            EditView::new().content(options.search_domains.join(" ")),
         )
         .with_name("network-options");
 
+    let inner = LinearLayout::vertical()
+        .child(inner)
+        .child(DummyView)
+        .child(
+            LinearLayout::horizontal()
+                .child(DummyView.full_width())
+                .child(Button::new("Advanced options", |siv| {
+                    let dialog = advanced_network_options_dialog(siv);
+                    siv.add_layer(dialog);
+                })),
+        );
+
     InstallerView::new(
         state,
         inner,
         Box::new(|siv| {
+            // Saved directly to the installer state by the advanced options dialog
+            let (mtu, bridge) = siv
+                .user_data::<InstallerState>()
+                .map(|state| {
+                    (
+                        state.options.network.mtu,
+                        state.options.network.bridge.clone(),
+                    )
+                })
+                .unwrap(); // Safety: InstallerState must always be set
+
             let options = siv.call_on_name("network-options", |view: &mut FormView| {
                 let ifname = view
                     .get_value::<SelectView, _>(0)
                     .ok_or("failed to retrieve management interface name")?;
 
+                let bond_mode = view
+                    .get_value::<SelectView<Option<BondMode>>, _>(1)
+                    .ok_or("failed to retrieve bond mode")?;
+
+                let bond_slaves = view
+                    .get_value::<MultiSelectView<String>, _>(2)
+                    .ok_or("failed to retrieve bond interfaces")?;
+
+                // The bond replaces the single management interface, with its first interface
+                // taking that role otherwise
+                let (ifname, bond) = match bond_mode {
+                    Some(mode) => match bond_slaves.first() {
+                        Some(first) => (
+                            first.clone(),
+                            Some(BondOptions::new(bond_slaves.clone(), mode)),
+                        ),
+                        None => {
+                            return Err("at least one bond interface must be selected".to_owned())
+                        }
+                    },
+                    None => (ifname, None),
+                };
+
+                let vlan = view
+                    .get_value::<EditView, _>(3)
+                    .ok_or("failed to retrieve VLAN ID")?;
+                let vlan = match vlan.trim() {
+                    "" => None,
+                    vlan => Some(
+                        vlan.parse::<u16>()
+                            .ok()
+                            .filter(|vlan| VLAN_ID_RANGE.contains(vlan))
+                            .ok_or("VLAN ID must be a number between 1 and 4094")?,
+                    ),
+                };
+
                 let fqdn = view
-                    .get_value::<EditView, _>(1)
+                    .get_value::<EditView, _>(4)
                     .ok_or("failed to retrieve host FQDN")?
                     .parse::<Fqdn>()
                     .map_err(|err| format!("hostname does not look valid:\n\n{err}"))?;
 
                 let address = view
-                    .get_value::<CidrAddressEditView, _>(2)
+                    .get_value::<CidrAddressEditView, _>(5)
                     .ok_or("failed to retrieve host address")?;
 
                 let gateway = view
-                    .get_value::<EditView, _>(3)
+                    .get_value::<EditView, _>(6)
                     .ok_or("failed to retrieve gateway address")?
                     .parse::<IpAddr>()
                     .map_err(|err| err.to_string())?;
 
-                let dns_server = view
-                    .get_value::<EditView, _>(4)
-                    .ok_or("failed to retrieve DNS server address")?
-                    .parse::<IpAddr>()
+                let address6 = view
+                    .get_value::<EditView, _>(7)
+                    .ok_or("failed to retrieve second host address")?;
+                let address6 = match address6.trim() {
+                    "" => None,
+                    address => Some(
+                        address
+                            .parse::<CidrAddress>()
+                            .map_err(|_| "second IP address must be in CIDR notation")?,
+                    ),
+                };
+
+                let gateway6 = view
+                    .get_value::<EditView, _>(8)
+                    .ok_or("failed to retrieve second gateway address")?;
+                let gateway6 = match gateway6.trim() {
+                    "" => None,
+                    gateway => Some(gateway.parse::<IpAddr>().map_err(|err| err.to_string())?),
+                };
+
+                let dns_servers = view
+                    .get_value::<EditView, _>(9)
+                    .ok_or("failed to retrieve DNS server addresses")?
+                    .split([',', ' '])
+                    .filter(|dns| !dns.is_empty())
+                    .map(|dns| dns.parse::<IpAddr>())
+                    .collect::<Result<Vec<_>, _>>()
                     .map_err(|err| err.to_string())?;
 
-                if address.addr().is_ipv4() != gateway.is_ipv4() {
-                    Err("host and gateway IP address version must not differ".to_owned())
-                } else if address.addr().is_ipv4() != dns_server.is_ipv4() {
-                    Err("host and DNS IP address version must not differ".to_owned())
+                let search_domains: Vec<String> = view
+                    .get_value::<EditView, _>(10)
+                    .ok_or("failed to retrieve DNS search domains")?
+                    .split([',', ' '])
+                    .filter(|domain| !domain.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+
+                let mut addresses = vec![address.clone()];
+                addresses.extend(address6.clone());
+                let mut gateways = vec![gateway];
+                gateways.extend(gateway6);
+
+                let mut problems = check_addresses(&addresses);
+                problems.extend(check_gateways(&addresses, &gateways));
+                problems.extend(check_dns_servers(&addresses, &dns_servers));
+                if let Some(bridge) = &bridge {
+                    problems.extend(check_bridge(bridge, vlan));
+                }
+                if let Some(domain) = search_domains
+                    .iter()
+                    .find(|domain| !Fqdn::is_valid_domain(domain))
+                {
+                    problems.push(format!("'{domain}' is not a valid search domain."));
+                }
+
+                if address6
+                    .as_ref()
+                    .is_some_and(|address6| address6.is_ipv4() == address.is_ipv4())
+                {
+                    Err("second IP address must be of the other IP version".to_owned())
+                } else if address6.is_none() && gateway6.is_some() {
+                    Err("second gateway address requires a second IP address".to_owned())
+                } else if dns_servers.is_empty() {
+                    Err("at least one DNS server address must be set".to_owned())
+                } else if !problems.is_empty() {
+                    Err(problems.join("\n"))
                 } else if fqdn.to_string().ends_with(".invalid") {
                     Err("hostname does not look valid".to_owned())
                 } else {
+                    // Use the IPv4 address as primary one for dual-stack configurations
+                    let ((address, gateway), (address6, gateway6)) = match (address6, gateway6) {
+                        (Some(address6), Some(gateway6)) if address.is_ipv6() => {
+                            ((address6, gateway6), (Some(address), Some(gateway)))
+                        }
+                        second => ((address, gateway), second),
+                    };
+
                     Ok(NetworkOptions {
                         ifname,
+                        vlan,
+                        bond,
                         fqdn,
                         address,
                         gateway,
-                        dns_server,
+                        address6,
+                        gateway6,
+                        dns_servers,
+                        search_domains,
+                        mtu,
+                        bridge,
                     })
                 }
             });
@@ -603,6 +815,92 @@ impl TableViewItem for SummaryOption {
     }
 }
 
+/// Creates the dialog for the advanced network options, i.e. the MTU and, for products using a
+/// bridged network setup, the settings of the management bridge. The options are saved to the
+/// installer state once confirmed.
+fn advanced_network_options_dialog(siv: &mut Cursive) -> Dialog {
+    let state = siv.user_data::<InstallerState>().unwrap();
+    let options = &state.options.network;
+    let bridged = state.setup_info.config.product.bridged_network();
+    let vlan = options.vlan;
+    let bridge = options
+        .bridge
+        .clone()
+        .unwrap_or_else(|| BridgeOptions::default_for(vlan));
+
+    let inner = FormView::new()
+        .child(
+            "MTU (optional)",
+            EditView::new().content(options.mtu.map(|mtu| mtu.to_string()).unwrap_or_default()),
+        )
+        .child_conditional(bridged, "Bridge name", EditView::new().content(bridge.name))
+        .child_conditional(
+            bridged,
+            "VLAN-aware bridge",
+            Checkbox::new().with_checked(bridge.vlan_aware),
+        )
+        .child_conditional(
+            bridged,
+            "Bridge VLAN IDs",
+            EditView::new().content(bridge.vids.to_string()),
+        )
+        .with_name("advanced-network-options");
+
+    Dialog::around(inner)
+        .title("Advanced network options")
+        .button("Ok", move |siv| {
+            let options = siv.call_on_name("advanced-network-options", |view: &mut FormView| {
+                let mtu = view
+                    .get_value::<EditView, _>(0)
+                    .ok_or("failed to retrieve MTU")?;
+                let mtu = match mtu.trim() {
+                    "" => None,
+                    mtu => {
+                        let mtu = mtu.parse::<u16>().map_err(|_| "MTU must be a number")?;
+                        check_mtu(mtu)?;
+                        Some(mtu)
+                    }
+                };
+
+                // The bridge settings are only shown for products using a bridged network setup
+                let bridge = if view.len() > 1 {
+                    let bridge = BridgeOptions {
+                        name: view
+                            .get_value::<EditView, _>(1)
+                            .ok_or("failed to retrieve bridge name")?
+                            .trim()
+                            .to_owned(),
+                        vlan_aware: view
+                            .get_value::<Checkbox, _>(2)
+                            .ok_or("failed to retrieve VLAN-awareness of the bridge")?,
+                        vids: view
+                            .get_value::<EditView, _>(3)
+                            .ok_or("failed to retrieve bridge VLAN IDs")?
+                            .parse::<VlanIdRange>()?,
+                    };
+                    Some(bridge).filter(|bridge| *bridge != BridgeOptions::default_for(vlan))
+                } else {
+                    None
+                };
+
+                Ok::<_, String>((mtu, bridge))
+            });
+
+            match options {
+                Some(Ok((mtu, bridge))) => {
+                    siv.with_user_data(|state: &mut InstallerState| {
+                        state.options.network.mtu = mtu;
+                        state.options.network.bridge = bridge;
+                    });
+                    siv.pop_layer();
+                }
+                Some(Err(err)) => siv.add_layer(Dialog::info(format!("Invalid values: {err}"))),
+                _ => siv.add_layer(Dialog::info("Invalid values")),
+            }
+        })
+        .dismiss_button("Cancel")
+}
+
 fn summary_dialog(siv: &mut Cursive) -> InstallerView {
     let state = siv.user_data::<InstallerState>().unwrap();
     let autoreboot = state.options.autoreboot;
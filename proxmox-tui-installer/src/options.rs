@@ -3,6 +3,7 @@ use crate::SummaryOption;
 use proxmox_installer_common::{
     options::{
         BootdiskOptions, BtrfsRaidLevel, FsType, NetworkOptions, TimezoneOptions, ZfsRaidLevel,
+        BOND_NAME,
     },
     setup::LocaleInfo,
     EMAIL_DEFAULT_PLACEHOLDER,
@@ -57,7 +58,12 @@ impl InstallerOptions {
             .map(|l| &l.name)
             .unwrap_or(&self.timezone.kb_layout);
 
-        vec![
+        let mngmt_iface = match &self.network.bond {
+            Some(bond) => format!("{BOND_NAME} ({}: {})", bond.mode, bond.slaves.join(", ")),
+            None => self.network.ifname.clone(),
+        };
+
+        let mut summary = vec![
             SummaryOption::new("Bootdisk filesystem", self.bootdisk.fstype.to_string()),
             SummaryOption::new(
                 "Bootdisk(s)",
@@ -71,12 +77,65 @@ impl InstallerOptions {
             SummaryOption::new("Timezone", &self.timezone.timezone),
             SummaryOption::new("Keyboard layout", kb_layout),
             SummaryOption::new("Administrator email", &self.password.email),
-            SummaryOption::new("Management interface", &self.network.ifname),
+            SummaryOption::new(
+                "Management interface",
+                match self.network.vlan {
+                    Some(vlan) => format!("{mngmt_iface} (VLAN {vlan})"),
+                    None => mngmt_iface,
+                },
+            ),
             SummaryOption::new("Hostname", self.network.fqdn.to_string()),
-            SummaryOption::new("Host IP (CIDR)", self.network.address.to_string()),
-            SummaryOption::new("Gateway", self.network.gateway.to_string()),
-            SummaryOption::new("DNS", self.network.dns_server.to_string()),
-        ]
+            SummaryOption::new(
+                "Host IP (CIDR)",
+                match &self.network.address6 {
+                    Some(address6) => format!("{}, {address6}", self.network.address),
+                    None => self.network.address.to_string(),
+                },
+            ),
+            SummaryOption::new(
+                "Gateway",
+                match &self.network.gateway6 {
+                    Some(gateway6) => format!("{}, {gateway6}", self.network.gateway),
+                    None => self.network.gateway.to_string(),
+                },
+            ),
+            SummaryOption::new(
+                "DNS",
+                self.network
+                    .dns_servers
+                    .iter()
+                    .map(|dns| dns.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            SummaryOption::new(
+                "DNS search domains",
+                if self.network.search_domains.is_empty() {
+                    self.network.fqdn.domain()
+                } else {
+                    self.network.search_domains.join(" ")
+                },
+            ),
+        ];
+
+        if let Some(wipe) = self.bootdisk.wipe {
+            summary.insert(2, SummaryOption::new("Bootdisk wipe", wipe.to_string()));
+        }
+        if let Some(bridge) = &self.network.bridge {
+            summary.push(SummaryOption::new(
+                "Management bridge",
+                if bridge.vlan_aware {
+                    format!("{} (VLAN-aware: {})", bridge.name, bridge.vids)
+                } else {
+                    bridge.name.clone()
+                },
+            ));
+        }
+        if let Some(mtu) = self.network.mtu {
+            summary.push(SummaryOption::new("MTU", mtu.to_string()));
+        }
+
+        summary
     }
 }
 
@@ -88,7 +147,7 @@ mod tests {
         utils::{CidrAddress, Fqdn},
     };
     use std::collections::BTreeMap;
-    use std::net::{IpAddr, Ipv4Addr};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
     #[test]
     fn network_options_from_setup_network_info() {
@@ -105,6 +164,8 @@ mod tests {
                 addresses: Some(vec![
                     CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap()
                 ]),
+                carrier: None,
+                speed: None,
             },
         );
 
@@ -128,10 +189,17 @@ mod tests {
             NetworkOptions::defaults_from(&setup, &info),
             NetworkOptions {
                 ifname: "eth0".to_owned(),
+                vlan: None,
+                bond: None,
                 fqdn: Fqdn::from("foo.bar.com").unwrap(),
                 address: CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap(),
                 gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
-                dns_server: Ipv4Addr::UNSPECIFIED.into(),
+                address6: None,
+                gateway6: None,
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
             }
         );
 
@@ -140,10 +208,17 @@ mod tests {
             NetworkOptions::defaults_from(&setup, &info),
             NetworkOptions {
                 ifname: "eth0".to_owned(),
+                vlan: None,
+                bond: None,
                 fqdn: Fqdn::from("pve.bar.com").unwrap(),
                 address: CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap(),
                 gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
-                dns_server: Ipv4Addr::UNSPECIFIED.into(),
+                address6: None,
+                gateway6: None,
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
             }
         );
 
@@ -152,10 +227,17 @@ mod tests {
             NetworkOptions::defaults_from(&setup, &info),
             NetworkOptions {
                 ifname: "eth0".to_owned(),
+                vlan: None,
+                bond: None,
                 fqdn: Fqdn::from("pve.example.invalid").unwrap(),
                 address: CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap(),
                 gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
-                dns_server: Ipv4Addr::UNSPECIFIED.into(),
+                address6: None,
+                gateway6: None,
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
             }
         );
 
@@ -164,10 +246,97 @@ mod tests {
             NetworkOptions::defaults_from(&setup, &info),
             NetworkOptions {
                 ifname: "eth0".to_owned(),
+                vlan: None,
+                bond: None,
                 fqdn: Fqdn::from("foo.example.invalid").unwrap(),
                 address: CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap(),
                 gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
-                dns_server: Ipv4Addr::UNSPECIFIED.into(),
+                address6: None,
+                gateway6: None,
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
+            }
+        );
+
+        // Leases of both address families on the same interface result in a dual-stack setup
+        let address6 =
+            CidrAddress::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2), 64).unwrap();
+        let gateway6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let mut dual_stack = info.clone();
+        let eth0 = dual_stack.interfaces.get_mut("eth0").unwrap();
+        eth0.addresses.as_mut().unwrap().push(address6.clone());
+        dual_stack.routes.as_mut().unwrap().gateway6 = Some(Gateway {
+            dev: "eth0".to_owned(),
+            gateway: gateway6,
+        });
+        pretty_assertions::assert_eq!(
+            NetworkOptions::defaults_from(&setup, &dual_stack),
+            NetworkOptions {
+                ifname: "eth0".to_owned(),
+                vlan: None,
+                bond: None,
+                fqdn: Fqdn::from("foo.example.invalid").unwrap(),
+                address: CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap(),
+                gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+                address6: Some(address6.clone()),
+                gateway6: Some(gateway6),
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
+            }
+        );
+
+        // Without an IPv4 lease, the IPv6 address is used on its own
+        let eth0 = dual_stack.interfaces.get_mut("eth0").unwrap();
+        eth0.addresses = Some(vec![address6.clone()]);
+        pretty_assertions::assert_eq!(
+            NetworkOptions::defaults_from(&setup, &dual_stack),
+            NetworkOptions {
+                ifname: "eth0".to_owned(),
+                vlan: None,
+                bond: None,
+                fqdn: Fqdn::from("foo.example.invalid").unwrap(),
+                address: address6,
+                gateway: gateway6,
+                address6: None,
+                gateway6: None,
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
+            }
+        );
+
+        // A lease on a VLAN interface results in its parent interface with the VLAN ID
+        let mut vlan_iface = info.interfaces.remove("eth0").unwrap();
+        info.interfaces.insert(
+            "eth0".to_owned(),
+            Interface {
+                addresses: None,
+                ..vlan_iface.clone()
+            },
+        );
+        vlan_iface.name = "eth0.42".to_owned();
+        info.interfaces.insert("eth0.42".to_owned(), vlan_iface);
+        info.routes.as_mut().unwrap().gateway4.as_mut().unwrap().dev = "eth0.42".to_owned();
+        pretty_assertions::assert_eq!(
+            NetworkOptions::defaults_from(&setup, &info),
+            NetworkOptions {
+                ifname: "eth0".to_owned(),
+                vlan: Some(42),
+                bond: None,
+                fqdn: Fqdn::from("foo.example.invalid").unwrap(),
+                address: CidrAddress::new(Ipv4Addr::new(192, 168, 0, 2), 24).unwrap(),
+                gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+                address6: None,
+                gateway6: None,
+                dns_servers: vec![Ipv4Addr::UNSPECIFIED.into()],
+                search_domains: vec![],
+                mtu: None,
+                bridge: None,
             }
         );
     }
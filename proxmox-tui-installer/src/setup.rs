@@ -3,11 +3,19 @@ use std::collections::BTreeMap;
 use crate::options::InstallerOptions;
 use proxmox_installer_common::{
     options::AdvancedBootdiskOptions,
-    setup::{InstallConfig, InstallFirstBootSetup, InstallRootPassword},
+    setup::{
+        InstallBondOption, InstallBridgeOption, InstallConfig, InstallFirstBootSetup,
+        InstallRootPassword,
+    },
 };
 
 impl From<InstallerOptions> for InstallConfig {
     fn from(options: InstallerOptions) -> Self {
+        let bridge_opts = options
+            .network
+            .bridge_options()
+            .map(InstallBridgeOption::from);
+
         let mut config = Self {
             autoreboot: options.autoreboot as usize,
 
@@ -22,6 +30,8 @@ impl From<InstallerOptions> for InstallConfig {
             target_hd: None,
             disk_selection: BTreeMap::new(),
             existing_storage_auto_rename: 0,
+            existing_data: None,
+            wipe: options.bootdisk.wipe,
 
             country: options.timezone.country,
             timezone: options.timezone.timezone,
@@ -35,6 +45,10 @@ impl From<InstallerOptions> for InstallConfig {
             root_ssh_keys: vec![],
 
             mngmt_nic: options.network.ifname,
+            vlan_id: options.network.vlan,
+            bond_opts: options.network.bond.map(InstallBondOption::from),
+            mtu: options.network.mtu,
+            bridge_opts,
 
             // Safety: At this point, it is know that we have a valid FQDN, as
             // this is set by the TUI network panel, which only lets the user
@@ -43,7 +57,13 @@ impl From<InstallerOptions> for InstallConfig {
             domain: options.network.fqdn.domain(),
             cidr: options.network.address,
             gateway: options.network.gateway,
-            dns: options.network.dns_server,
+            cidr6: options.network.address6,
+            gateway6: options.network.gateway6,
+            // Safety: The TUI network panel only lets the user continue if at least one DNS
+            // server is provided.
+            dns: options.network.dns_servers[0],
+            additional_dns: options.network.dns_servers[1..].to_vec(),
+            search_domains: options.network.search_domains,
 
             first_boot: InstallFirstBootSetup::default(),
         };
@@ -18,13 +18,13 @@ use crate::InstallerState;
 
 use proxmox_installer_common::{
     disk_checks::{
-        check_btrfs_raid_config, check_disks_4kn_legacy_boot, check_for_duplicate_disks,
-        check_zfs_raid_config,
+        check_btrfs_raid_config, check_disk_wipe_method, check_disks_4kn_legacy_boot,
+        check_for_duplicate_disks, check_zfs_raid_config,
     },
     options::{
-        AdvancedBootdiskOptions, BootdiskOptions, BtrfsBootdiskOptions, Disk, FsType,
-        LvmBootdiskOptions, ZfsBootdiskOptions, BTRFS_COMPRESS_OPTIONS, ZFS_CHECKSUM_OPTIONS,
-        ZFS_COMPRESS_OPTIONS,
+        AdvancedBootdiskOptions, BootdiskOptions, BtrfsBootdiskOptions, Disk, DiskWipeMethod,
+        FsType, LvmBootdiskOptions, ZfsBootdiskOptions, BTRFS_COMPRESS_OPTIONS, DISK_WIPE_METHODS,
+        ZFS_CHECKSUM_OPTIONS, ZFS_COMPRESS_OPTIONS,
     },
     setup::{BootType, ProductConfig, ProxmoxProduct, RuntimeInfo},
 };
@@ -115,6 +115,8 @@ impl ViewWrapper for BootdiskOptionsView {
 
 struct AdvancedBootdiskOptionsView {
     view: LinearLayout,
+    /// All disks of the system, as sanitizing a NVMe controller affects all of its namespaces
+    all_disks: Vec<Disk>,
 }
 
 impl AdvancedBootdiskOptionsView {
@@ -149,9 +151,25 @@ impl AdvancedBootdiskOptionsView {
                 }
             });
 
+        let wipe_select = SelectView::new()
+            .popup()
+            .item("none", None)
+            .with_all(DISK_WIPE_METHODS.iter().map(|m| (m.to_string(), Some(*m))))
+            .selected(
+                DISK_WIPE_METHODS
+                    .iter()
+                    .position(|m| Some(*m) == options.wipe)
+                    .map(|pos| pos + 1)
+                    .unwrap_or_default(),
+            );
+
         let mut view = LinearLayout::vertical()
             .child(DummyView.full_width())
-            .child(FormView::new().child("Filesystem", fstype_select))
+            .child(
+                FormView::new()
+                    .child("Filesystem", fstype_select)
+                    .child("Disk wipe", wipe_select),
+            )
             .child(DummyView.full_width());
 
         // Create the appropriate (inner) advanced options view
@@ -169,7 +187,10 @@ impl AdvancedBootdiskOptionsView {
             }
         };
 
-        Self { view }
+        Self {
+            view,
+            all_disks: runinfo.disks.clone(),
+        }
     }
 
     /// Called when a new filesystem type is chosen by the user.
@@ -182,7 +203,7 @@ impl AdvancedBootdiskOptionsView {
     /// # Arguments
     /// * `siv` - Cursive instance
     /// * `fstype` - The chosen filesystem type by the user, for which the UI should be
-    ///              updated accordingly
+    ///   updated accordingly
     /// * `options_ref` - [`BootdiskOptionsRef`] where advanced disk options should be saved to
     fn fstype_on_submit(siv: &mut Cursive, fstype: &FsType, options_ref: BootdiskOptionsRef) {
         let state = siv.user_data::<InstallerState>().unwrap();
@@ -202,7 +223,7 @@ impl AdvancedBootdiskOptionsView {
         // Update the (inner) options view
         let screen_size = siv.screen_size();
         siv.call_on_name("advanced-bootdisk-options-dialog", |view: &mut Dialog| {
-            if let Some(AdvancedBootdiskOptionsView { view }) =
+            if let Some(AdvancedBootdiskOptionsView { view, .. }) =
                 view.get_content_mut().downcast_mut()
             {
                 view.remove_child(3);
@@ -248,28 +269,35 @@ impl AdvancedBootdiskOptionsView {
     }
 
     fn get_values(&mut self) -> Result<BootdiskOptions, String> {
-        let fstype = self
+        let form = self
             .view
             .get_child(1)
-            .and_then(|v| v.downcast_ref::<FormView>())
+            .and_then(|v| v.downcast_ref::<FormView>());
+
+        let fstype = form
             .and_then(|v| v.get_value::<SelectView<FsType>, _>(0))
             .ok_or("Failed to retrieve filesystem type".to_owned())?;
 
+        let wipe = form
+            .and_then(|v| v.get_value::<SelectView<Option<DiskWipeMethod>>, _>(1))
+            .ok_or("Failed to retrieve disk wipe method".to_owned())?;
+
         let advanced = self
             .view
             .get_child_mut(3)
             .ok_or("Failed to retrieve advanced bootdisk options view".to_owned())?;
 
-        if let Some(view) = advanced.downcast_mut::<LvmBootdiskOptionsView>() {
+        let options = if let Some(view) = advanced.downcast_mut::<LvmBootdiskOptionsView>() {
             let (disk, advanced) = view
                 .get_values()
                 .ok_or("Failed to retrieve advanced bootdisk options")?;
 
-            Ok(BootdiskOptions {
+            BootdiskOptions {
                 disks: vec![disk],
                 fstype,
                 advanced: AdvancedBootdiskOptions::Lvm(advanced),
-            })
+                wipe,
+            }
         } else if let Some(view) = advanced.downcast_mut::<ZfsBootdiskOptionsView>() {
             let (disks, advanced) = view
                 .get_values()
@@ -279,11 +307,12 @@ impl AdvancedBootdiskOptionsView {
                 check_zfs_raid_config(level, &disks).map_err(|err| format!("{fstype}: {err}"))?;
             }
 
-            Ok(BootdiskOptions {
+            BootdiskOptions {
                 disks,
                 fstype,
                 advanced: AdvancedBootdiskOptions::Zfs(advanced),
-            })
+                wipe,
+            }
         } else if let Some(view) = advanced.downcast_mut::<BtrfsBootdiskOptionsView>() {
             let (disks, advanced) = view
                 .get_values()
@@ -293,14 +322,21 @@ impl AdvancedBootdiskOptionsView {
                 check_btrfs_raid_config(level, &disks).map_err(|err| format!("{fstype}: {err}"))?;
             }
 
-            Ok(BootdiskOptions {
+            BootdiskOptions {
                 disks,
                 fstype,
                 advanced: AdvancedBootdiskOptions::Btrfs(advanced),
-            })
+                wipe,
+            }
         } else {
-            Err("Invalid bootdisk view state".to_owned())
+            return Err("Invalid bootdisk view state".to_owned());
+        };
+
+        if let Some(method) = wipe {
+            check_disk_wipe_method(method, &options.disks, &self.all_disks)?;
         }
+
+        Ok(options)
     }
 }
 
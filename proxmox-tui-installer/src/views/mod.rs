@@ -4,7 +4,7 @@ use cursive::{
     event::{Event, EventResult},
     theme::BaseColor,
     view::{Resizable, ViewWrapper},
-    views::{EditView, LinearLayout, NamedView, ResizedView, SelectView, TextView},
+    views::{Checkbox, EditView, LinearLayout, NamedView, ResizedView, SelectView, TextView},
     Printer, Rect, Vec2, View,
 };
 
@@ -16,6 +16,9 @@ pub use bootdisk::*;
 mod install_progress;
 pub use install_progress::*;
 
+mod multi_select;
+pub use multi_select::*;
+
 mod tabbed_view;
 pub use tabbed_view::*;
 
@@ -182,7 +185,7 @@ impl<T: Copy + ToString + FromStr + PartialOrd> NumericEditView<T> {
     ///
     /// # Arguments
     /// * `content` - New, stringified content for the inner [`EditView`]. Must be a valid value
-    ///               according to the container type `T`.
+    ///   according to the container type `T`.
     fn content_inner(mut self, content: &str) -> Self {
         let mut inner = EditView::new();
         std::mem::swap(self.inner_mut(), &mut inner);
@@ -371,6 +374,12 @@ impl FormViewGetValue<String> for EditView {
     }
 }
 
+impl FormViewGetValue<bool> for Checkbox {
+    fn get_value(&self) -> Option<bool> {
+        Some(self.is_checked())
+    }
+}
+
 impl<T: 'static + Clone + Send + Sync> FormViewGetValue<T> for SelectView<T> {
     fn get_value(&self) -> Option<T> {
         self.selection().map(|v| (*v).clone())
@@ -0,0 +1,143 @@
+use std::{
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use cursive::{
+    view::{Nameable, ViewWrapper},
+    views::{Button, Checkbox, Dialog, LinearLayout, NamedView, ScrollView, TextView},
+};
+
+use super::FormViewGetValue;
+
+struct MultiSelectItem<T> {
+    label: String,
+    value: T,
+    selected: bool,
+}
+
+/// Single-line view for selecting any number of items from a list. Shows the currently selected
+/// values and opens a dialog with a checkbox for each item when activated.
+pub struct MultiSelectView<T> {
+    view: NamedView<Button>,
+    items: Arc<Mutex<Vec<MultiSelectItem<T>>>>,
+}
+
+impl<T: 'static + Clone + Display + Send + Sync> MultiSelectView<T> {
+    /// Creates a new [`MultiSelectView`].
+    ///
+    /// # Arguments
+    /// * `name` - Unique name of the view, needed to update the shown selection.
+    /// * `title` - Title of the selection dialog.
+    /// * `items` - Label, value and initial selection state of each item.
+    pub fn new(
+        name: &str,
+        title: &str,
+        items: impl IntoIterator<Item = (String, T, bool)>,
+    ) -> Self {
+        let items = Arc::new(Mutex::new(
+            items
+                .into_iter()
+                .map(|(label, value, selected)| MultiSelectItem {
+                    label,
+                    value,
+                    selected,
+                })
+                .collect::<Vec<_>>(),
+        ));
+
+        let button = Button::new_raw(Self::label(&items.lock().unwrap()), {
+            let name = name.to_owned();
+            let title = title.to_owned();
+            let items = items.clone();
+            move |siv| siv.add_layer(Self::selection_dialog(&name, &title, items.clone()))
+        });
+
+        Self {
+            view: button.with_name(name),
+            items,
+        }
+    }
+
+    /// Returns the values of all selected items.
+    pub fn selection(&self) -> Vec<T> {
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|item| item.selected)
+            .map(|item| item.value.clone())
+            .collect()
+    }
+
+    fn label(items: &[MultiSelectItem<T>]) -> String {
+        let selected: Vec<String> = items
+            .iter()
+            .filter(|item| item.selected)
+            .map(|item| item.value.to_string())
+            .collect();
+
+        if selected.is_empty() {
+            "[none]".to_owned()
+        } else {
+            format!("[{}]", selected.join(", "))
+        }
+    }
+
+    fn selection_dialog(
+        name: &str,
+        title: &str,
+        items: Arc<Mutex<Vec<MultiSelectItem<T>>>>,
+    ) -> Dialog {
+        // Only apply the changes once the dialog is confirmed
+        let pending = Arc::new(Mutex::new(
+            items
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|item| item.selected)
+                .collect::<Vec<_>>(),
+        ));
+
+        let mut list = LinearLayout::vertical();
+        for (index, item) in items.lock().unwrap().iter().enumerate() {
+            let pending = pending.clone();
+            list.add_child(
+                LinearLayout::horizontal()
+                    .child(
+                        Checkbox::new()
+                            .with_checked(item.selected)
+                            .on_change(move |_, checked| pending.lock().unwrap()[index] = checked),
+                    )
+                    .child(TextView::new(format!(" {}", item.label))),
+            );
+        }
+
+        let name = name.to_owned();
+        Dialog::around(ScrollView::new(list))
+            .title(title)
+            .button("Ok", move |siv| {
+                let label = {
+                    let mut items = items.lock().unwrap();
+                    for (item, selected) in items.iter_mut().zip(pending.lock().unwrap().iter()) {
+                        item.selected = *selected;
+                    }
+                    Self::label(&items)
+                };
+
+                siv.call_on_name(&name, |view: &mut Button| view.set_label_raw(label));
+                siv.pop_layer();
+            })
+            .dismiss_button("Cancel")
+    }
+}
+
+impl<T: 'static + Send + Sync> ViewWrapper for MultiSelectView<T> {
+    cursive::wrap_impl!(self.view: NamedView<Button>);
+}
+
+impl<T: 'static + Clone + Display + Send + Sync> FormViewGetValue<Vec<T>> for MultiSelectView<T> {
+    fn get_value(&self) -> Option<Vec<T>> {
+        Some(self.selection())
+    }
+}
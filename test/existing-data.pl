@@ -0,0 +1,79 @@
+#!/usr/bin/perl
+
+use strict;
+use warnings;
+
+use File::Temp;
+use Test::More tests => 7;
+
+use Proxmox::Install;
+use Proxmox::Sys::Block;
+use Proxmox::Sys::File qw(file_read_all);
+use Proxmox::UI;
+
+my $log_file = File::Temp->new();
+Proxmox::Log::init($log_file->filename);
+
+Proxmox::UI::init_stdio();
+
+my $LSBLK_TEST_OUTPUT = <<EOT;
+NAME="/dev/sda" PTTYPE="gpt" FSTYPE="" LABEL=""
+NAME="/dev/sda1" PTTYPE="gpt" FSTYPE="" LABEL=""
+NAME="/dev/sda2" PTTYPE="gpt" FSTYPE="vfat" LABEL=""
+NAME="/dev/sda3" PTTYPE="gpt" FSTYPE="LVM2_member" LABEL=""
+NAME="/dev/sda4" PTTYPE="gpt" FSTYPE="zfs_member" LABEL="rpool"
+NAME="/dev/sda5" PTTYPE="gpt" FSTYPE="btrfs" LABEL="my data"
+EOT
+
+open(my $fh, '<', \$LSBLK_TEST_OUTPUT);
+my $report = Proxmox::Sys::Block::lsblk_existing_data_parse_output($fh, '/dev/sda');
+is_deeply($report, [
+    "/dev/sda: gpt partition table",
+    "/dev/sda2: 'vfat' signature",
+    "/dev/sda3: LVM physical volume",
+    "/dev/sda4: ZFS pool member labelled 'rpool'",
+    "/dev/sda5: Btrfs filesystem labelled 'my data'",
+], 'partition table and signatures are reported');
+
+open($fh, '<', \qq(NAME="/dev/sdb" PTTYPE="" FSTYPE="" LABEL=""\n));
+$report = Proxmox::Sys::Block::lsblk_existing_data_parse_output($fh, '/dev/sdb');
+is_deeply($report, [], 'empty disk is not reported');
+
+my $vgs = {
+    'vg-uuid-1' => { pvs => '/dev/sdc3' },
+    'vg-uuid-2' => { pvs => '/dev/sdd3, /dev/sde3' },
+};
+my $pools = [
+    { name => 'rpool', id => '9412322616744093413', state => 'ONLINE' },
+    { name => 'testpool', id => '4958685680270539150', state => 'ONLINE' },
+    { name => 'rpool-OLD-123', id => '1234', state => 'ONLINE' },
+];
+
+$report = Proxmox::Install::existing_storage_report('pve', $vgs, 'rpool', $pools);
+is_deeply($report, [
+    "LVM volume group 'pve' on /dev/sdc3",
+    "LVM volume group 'pve' on /dev/sdd3, /dev/sde3",
+    "ZFS pool 'rpool' (id 9412322616744093413)",
+], 'volume groups and pools with the installation names are reported');
+
+$report = Proxmox::Install::existing_storage_report('pve', undef, 'tank', $pools);
+is_deeply($report, [], 'storage with other names is not reported');
+
+my $pvs = [
+    ['/dev/sda3', 'pve'],
+    ['/dev/sdaa1', 'backup'],
+    ['/dev/nvme0n1p3', 'pve-OLD-1234'],
+    ['/dev/nvme0n1p4', 'pve-OLD-1234'],
+    ['/dev/nvme1n1', 'data'],
+    ['/dev/sdb1', undef],
+];
+
+my $wiped = Proxmox::Install::volume_groups_on_disks(['/dev/sda', '/dev/nvme0n1', '/dev/sdb'], $pvs);
+is_deeply($wiped, ['pve', 'pve-OLD-1234'], 'volume groups on the target disks are deactivated on wipe');
+
+$wiped = Proxmox::Install::volume_groups_on_disks(['/dev/nvme1n1'], $pvs);
+is_deeply($wiped, ['data'], 'volume groups on whole-disk physical volumes are deactivated on wipe');
+
+# Check the log for errors/warnings
+my $log = file_read_all($log_file->filename);
+ok($log !~ m/(WARN|ERROR): /, 'no warnings or errors logged');
@@ -0,0 +1,51 @@
+#!/usr/bin/perl
+
+use strict;
+use warnings;
+
+use Test::More tests => 6;
+
+use Proxmox::Sys::Block;
+
+my $HDPARM_TEST_OUTPUT = <<EOT;
+Commands/features:
+	Enabled	Supported:
+	   *	SMART feature set
+	    	Security Mode feature set
+	   *	Power Management feature set
+Security: 
+	Master password revision code = 65534
+		supported
+	not	enabled
+	not	locked
+		frozen
+	not	expired: security count
+		supported: enhanced erase
+	32min for SECURITY ERASE UNIT. 32min for ENHANCED SECURITY ERASE UNIT.
+Logical Unit WWN Device Identifier: 5002538c405dbf10
+EOT
+
+my $state = Proxmox::Sys::Block::hdparm_security_parse_output($HDPARM_TEST_OUTPUT);
+is_deeply($state, { supported => 1, enabled => 0, locked => 0, frozen => 1 },
+    'security state is parsed');
+
+(my $not_frozen = $HDPARM_TEST_OUTPUT) =~ s/^\t\tfrozen$/\tnot\tfrozen/m;
+$state = Proxmox::Sys::Block::hdparm_security_parse_output($not_frozen);
+is_deeply($state, { supported => 1, enabled => 0, locked => 0, frozen => 0 },
+    'negated states are not set');
+
+(my $unsupported = $HDPARM_TEST_OUTPUT) =~ s/^\t\tsupported$/\tnot\tsupported/m;
+$state = Proxmox::Sys::Block::hdparm_security_parse_output($unsupported);
+is($state->{supported}, 0, "'supported: enhanced erase' does not count as supported");
+
+$state = Proxmox::Sys::Block::hdparm_security_parse_output(<<EOT);
+Commands/features:
+	Enabled	Supported:
+	   *	SMART feature set
+		supported
+EOT
+is($state, undef, 'missing security section');
+
+my $pass = Proxmox::Sys::Block::ata_security_password();
+like($pass, qr/^[0-9a-f]{16}$/, 'ATA security password is 16 hexadecimal digits');
+isnt(Proxmox::Sys::Block::ata_security_password(), $pass, 'ATA security password is random');
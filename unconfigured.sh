@@ -9,6 +9,8 @@ parse_cmdline() {
     proxdebug=0
     proxtui=0
     serial=0
+    static_net=0
+    vlan=
     # shellcheck disable=SC2013 # per word splitting is wanted here
     for par in $(cat /proc/cmdline); do
         case $par in
@@ -24,6 +26,12 @@ parse_cmdline() {
             console=ttyS*)
                 serial=1
             ;;
+            ip=*/*|proxmox-net=*)
+                static_net=1
+            ;;
+            proxmox-vlan=*)
+                vlan=${par#proxmox-vlan=}
+            ;;
         esac
     done;
 }
@@ -219,13 +227,48 @@ if [ $start_auto_installer -ne 0 ]; then
 option proxmox-auto-installer-manifest-url code 250 = text;
 option proxmox-auto-installer-cert-fingerprint code 251 = text;
 also request proxmox-auto-installer-manifest-url, proxmox-auto-installer-cert-fingerprint;
+option dhcp6.proxmox-auto-installer-manifest-url code 250 = text;
+option dhcp6.proxmox-auto-installer-cert-fingerprint code 251 = text;
+also request dhcp6.proxmox-auto-installer-manifest-url, dhcp6.proxmox-auto-installer-cert-fingerprint;
 EOF
 fi
 
-# try to get ip config with dhcp
-echo -n "Attempting to get DHCP leases... "
-dhclient -v
-echo "done"
+# obtain the DHCP lease on a tagged VLAN instead, if configured for the auto installer
+dhcp_ifaces=()
+if [ $start_auto_installer -ne 0 ] && [ -n "$vlan" ]; then
+    echo "Setting up VLAN $vlan on all physical network interfaces"
+    for iface in /sys/class/net/*; do
+        name=$(basename "$iface")
+        if [ ! -e "$iface/device" ] || [ -d "$iface/wireless" ]; then
+            continue
+        fi
+        if [ ${#name} -gt $((15 - ${#vlan} - 1)) ]; then
+            echo "skipping $name, name too long for VLAN interface"
+            continue
+        fi
+        ip link set "$name" up || true
+        if ip link add link "$name" name "$name.$vlan" type vlan id "$vlan"; then
+            dhcp_ifaces+=("$name.$vlan")
+        fi
+    done
+fi
+
+if [ $start_auto_installer -ne 0 ] && [ $static_net -ne 0 ]; then
+    # brought up by proxmox-fetch-answer instead
+    echo "Static network configuration on kernel commandline, skipping DHCP"
+else
+    # try to get ip config with dhcp
+    echo -n "Attempting to get DHCP leases... "
+    dhclient -v "${dhcp_ifaces[@]}"
+    echo "done"
+
+    if [ $start_auto_installer -ne 0 ]; then
+        # IPv6-only networks might provide the location of the answer file via DHCPv6 only
+        echo -n "Attempting to get DHCPv6 leases... "
+        timeout 30 dhclient -6 -1 -v "${dhcp_ifaces[@]}" || echo -n "failed - "
+        echo "done"
+    fi
+fi
 
 echo "Starting chrony for opportunistic time-sync... "
 chronyd || echo "starting chrony failed ($?)"
@@ -252,6 +295,11 @@ elif [ $start_auto_installer -ne 0 ]; then
         echo "and enter 'exit' or press 'CTRL' + 'D' when finished."
         debugsh || true
     fi
+
+    if [ $static_net -ne 0 ]; then
+        echo "Updating runtime environment with the network configuration from kernel commandline"
+        /usr/bin/proxmox-low-level-installer dump-env
+    fi
     echo "Starting automatic installation"
 
     if /usr/bin/proxmox-auto-installer </run/automatic-installer-answers; then